@@ -0,0 +1,222 @@
+// src/api.rs — HTTP endpoints beside the Socket.IO layer.
+//
+// GET /messages   — fetch one stored message after a content-free sync push.
+//                   The caller authenticates with `Authorization: Bearer <api key>`:
+//                   the key the server issued the device on `store_fcm_token`
+//                   (DEVICE_REGISTERED), never sent anywhere but that device.
+// GET /recordings/:egress_id — download a finished call recording; same
+//                   authentication, open to the call's participants only.
+// GET /stats/calls — call duration, outcome and quality aggregates: the
+//                   authenticated user's calls and the whole server's.
+// POST /voicemail/:call_id — upload the voicemail offered after an unanswered
+//                   call. Authenticated with the one-time token from VOICEMAIL_OFFER.
+// GET /voicemail/:call_id — download it; device API key authentication, open to
+//                   the caller and callee only.
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+use socketioxide::SocketIo;
+use tracing::warn;
+
+use crate::{
+    stats::{self, CallStatsReport},
+    types::{AppState, ErrorPayload, StoredMessage, UserMap},
+    voicemail,
+};
+
+type ApiError = (StatusCode, Json<ErrorPayload>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(ErrorPayload { message: message.into() }))
+}
+
+// ── GET /messages ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct MessageQuery {
+    pub conversation_key: String,
+    pub message_id:       String,
+}
+
+pub async fn get_message(
+    State(state): State<AppState>,
+    headers:      HeaderMap,
+    Query(q):     Query<MessageQuery>,
+) -> Result<Json<StoredMessage>, ApiError> {
+    let user_id = authenticate_device(&headers, &state.users).await
+        .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "Unknown or missing device API key"))?;
+
+    if !is_conversation_member(&state, &user_id, &q.conversation_key).await {
+        warn!("[api] '{user_id}' denied access to '{}'", q.conversation_key);
+        return Err(api_error(StatusCode::FORBIDDEN, "Not a member of this conversation"));
+    }
+
+    let store = state.messages.read().await;
+    store.get(&q.conversation_key)
+        .and_then(|msgs| msgs.iter().find(|m| m.message_id == q.message_id))
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Message not found"))
+}
+
+// ── GET /recordings/:egress_id ────────────────────────────────────────────────
+
+pub async fn get_recording(
+    State(state):    State<AppState>,
+    headers:         HeaderMap,
+    Path(egress_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = authenticate_device(&headers, &state.users).await
+        .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "Unknown or missing device API key"))?;
+
+    let (record, recording) = {
+        let history = state.history.read().await;
+        history.values()
+            .find_map(|r| r.recordings.iter()
+                .find(|rec| rec.egress_id == egress_id)
+                .map(|rec| (r.clone(), rec.clone())))
+            .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Recording not found"))?
+    };
+
+    let allowed = if record.is_group {
+        state.groups.read().await
+            .get(&record.target)
+            .is_some_and(|g| g.members.contains(&user_id))
+    } else {
+        record.caller == user_id || record.target == user_id
+    };
+    if !allowed {
+        warn!("[api] '{user_id}' denied access to recording '{egress_id}'");
+        return Err(api_error(StatusCode::FORBIDDEN, "Not a participant of this call"));
+    }
+    if recording.stopped_at.is_none() {
+        return Err(api_error(StatusCode::CONFLICT, "Recording is still in progress"));
+    }
+
+    let path = state.media.recording_path(&recording.file)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Recording file not available"))?;
+    let bytes = tokio::fs::read(&path).await.map_err(|e| {
+        warn!("[api] recording '{egress_id}' unreadable at {}: {e}", path.display());
+        api_error(StatusCode::NOT_FOUND, "Recording file not available")
+    })?;
+
+    let content_type = if recording.file.ends_with(".mp4") { "video/mp4" } else { "audio/ogg" };
+    let disposition  = format!("attachment; filename=\"{}\"", recording.file);
+    Ok(([(header::CONTENT_TYPE, content_type.to_owned()), (header::CONTENT_DISPOSITION, disposition)], bytes))
+}
+
+// ── GET /stats/calls ──────────────────────────────────────────────────────────
+
+pub async fn get_call_stats(
+    State(state): State<AppState>,
+    headers:      HeaderMap,
+) -> Result<Json<CallStatsReport>, ApiError> {
+    let user_id = authenticate_device(&headers, &state.users).await
+        .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "Unknown or missing device API key"))?;
+
+    Ok(Json(stats::report(&*state.history.read().await, &user_id)))
+}
+
+// ── POST /voicemail/:call_id ──────────────────────────────────────────────────
+
+pub async fn post_voicemail(
+    State(state):   State<AppState>,
+    Extension(io):  Extension<SocketIo>,
+    headers:        HeaderMap,
+    Path(call_id):  Path<String>,
+    body:           Bytes,
+) -> Result<Json<StoredMessage>, ApiError> {
+    let content_type = headers.get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let ext = voicemail::extension(&content_type)
+        .ok_or_else(|| api_error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported audio format"))?;
+    if body.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Empty voicemail"));
+    }
+
+    let token = bearer_token(&headers)
+        .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "Missing upload token"))?;
+    let offer = voicemail::take_offer(&state, &call_id, token).await.ok_or_else(|| {
+        warn!("[api] voicemail upload for '{call_id}' refused");
+        api_error(StatusCode::FORBIDDEN, "No voicemail offer for this call, or it has expired")
+    })?;
+
+    voicemail::deliver(&state, &io, &call_id, offer, &content_type, ext, &body).await
+        .map(Json)
+        .map_err(|e| {
+            warn!("[api] voicemail '{call_id}' could not be stored: {e}");
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, "Voicemail could not be stored")
+        })
+}
+
+// ── GET /voicemail/:call_id ───────────────────────────────────────────────────
+
+pub async fn get_voicemail(
+    State(state):  State<AppState>,
+    headers:       HeaderMap,
+    Path(call_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = authenticate_device(&headers, &state.users).await
+        .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "Unknown or missing device API key"))?;
+
+    let (message, attachment) = {
+        let store = state.messages.read().await;
+        store.values().flatten()
+            .find_map(|m| m.voicemail.as_ref()
+                .filter(|v| v.call_id == call_id)
+                .map(|v| (m.clone(), v.clone())))
+            .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Voicemail not found"))?
+    };
+    if message.from != user_id && message.target != user_id {
+        warn!("[api] '{user_id}' denied access to voicemail '{call_id}'");
+        return Err(api_error(StatusCode::FORBIDDEN, "Not a party of this call"));
+    }
+
+    let ext  = voicemail::extension(&attachment.content_type).unwrap_or("bin");
+    let path = voicemail::file_path(&call_id, ext);
+    let bytes = tokio::fs::read(&path).await.map_err(|e| {
+        warn!("[api] voicemail '{call_id}' unreadable at {}: {e}", path.display());
+        api_error(StatusCode::NOT_FOUND, "Voicemail file not available")
+    })?;
+
+    let disposition = format!("attachment; filename=\"voicemail-{call_id}.{ext}\"");
+    Ok(([(header::CONTENT_TYPE, attachment.content_type), (header::CONTENT_DISPOSITION, disposition)], bytes))
+}
+
+// ── Internal helpers ──────────────────────────────────────────────────────────
+
+/// Resolves the bearer token to the user owning the device it is the API key of.
+async fn authenticate_device(headers: &HeaderMap, users: &UserMap) -> Option<String> {
+    let key = bearer_token(headers)?;
+
+    users.read().await.values()
+        .find(|u| u.devices.iter().any(|d| d.api_key == key))
+        .map(|u| u.user_id.clone())
+}
+
+/// The non-empty token of an `Authorization: Bearer` header.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let token = headers.get(header::AUTHORIZATION)?
+        .to_str().ok()?
+        .strip_prefix("Bearer ")?
+        .trim();
+    (!token.is_empty()).then_some(token)
+}
+
+/// DM keys are "{a}::{b}"; group keys are "group::{group_id}".
+async fn is_conversation_member(state: &AppState, user_id: &str, key: &str) -> bool {
+    if let Some(group_id) = key.strip_prefix("group::") {
+        return state.groups.read().await
+            .get(group_id)
+            .is_some_and(|g| g.members.iter().any(|m| m == user_id));
+    }
+    key.split_once("::").is_some_and(|(a, b)| a == user_id || b == user_id)
+}
@@ -1,32 +1,110 @@
 // src/fcm.rs
 
 use gcp_auth::TokenProvider;
+use serde_json::Value;
 use tracing::{error, info, warn};
 
-use crate::types::FCM_PROJECT_ID;
+use crate::{
+    settings,
+    types::{dm_key, group_key, ChatDelivery, Device, Platform, ScheduledCall, CHAT_PUSH_TTL_SEC},
+};
+
+// Android notification channels — the app must create channels with these ids.
+const ANDROID_CALL_CHANNEL: &str = "incoming_calls";
+const ANDROID_CHAT_CHANNEL: &str = "chat_messages";
+const ANDROID_SCHEDULE_CHANNEL: &str = "scheduled_calls";
+
+// APNs categories — the iOS app registers matching UNNotificationCategory actions.
+const APNS_CALL_CATEGORY: &str = "INCOMING_CALL";
+const APNS_CHAT_CATEGORY: &str = "CHAT_MESSAGE";
+const APNS_SCHEDULE_CATEGORY: &str = "SCHEDULED_CALL";
+
+// Web Push artwork, resolved against the service worker's origin.
+const WEB_ICON:  &str = "/favicon.ico";
+const WEB_BADGE: &str = "/favicon.ico";
 
 /// What the caller should do with a token after a send attempt.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenStatus {
     Ok,
+    /// Delivery failed for a transient reason (network, auth, 5xx) — keep the token.
+    Failed,
     /// FCM says the token is permanently invalid:
     /// Caller must evict this token from the user map so it is never retried.
     Evict,
 }
 
+/// A push to fan out to many tokens; rendered per token by [`send`].
+#[derive(Debug, Clone)]
+pub enum Push {
+    Call      { call_id: String, from: String, to: String, video: bool, ring_sec: u64 },
+    ChatDm    { message_id: String, from: String, to: String, content: String },
+    ChatGroup { message_id: String, from: String, group_id: String, group_name: String, content: String },
+    /// A call was scheduled (`reminder: false`) or is about to start (`reminder: true`).
+    Schedule  { call: ScheduledCall, reminder: bool },
+    /// `from` left a voicemail for `to` after an unanswered call.
+    Voicemail { message_id: String, from: String, to: String, call_id: String },
+}
+
+impl Push {
+    /// Call-related pushes obey the device's `calls` preference, the rest `messages`.
+    pub fn is_call(&self) -> bool { matches!(self, Push::Call { .. } | Push::Schedule { .. }) }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Push::Call { .. }      => "call",
+            Push::ChatDm { .. }    => "chat-dm",
+            Push::ChatGroup { .. } => "chat-group",
+            Push::Schedule { .. }  => "schedule",
+            Push::Voicemail { .. } => "voicemail",
+        }
+    }
+}
+
+/// Sends `push` to a single device, honouring its chat delivery preference.
+pub async fn send(
+    device:    &Device,
+    push:      &Push,
+    auth:      &dyn TokenProvider,
+    http:      &reqwest::Client,
+) -> TokenStatus {
+    let sync = device.prefs.chat_delivery == ChatDelivery::Sync;
+    match push {
+        Push::Call { call_id, from, to, video, ring_sec } =>
+            send_fcm_notification(device, call_id, from, to, *video, *ring_sec, auth, http).await,
+        Push::ChatDm { message_id, from, to, .. } if sync =>
+            send_chat_sync_notification(device, &dm_key(from, to), message_id, auth, http).await,
+        Push::ChatDm { from, to, content, .. } =>
+            send_chat_dm_notification(device, from, to, content, auth, http).await,
+        Push::ChatGroup { message_id, group_id, .. } if sync =>
+            send_chat_sync_notification(device, &group_key(group_id), message_id, auth, http).await,
+        Push::ChatGroup { from, group_id, group_name, content, .. } =>
+            send_chat_group_notification(device, from, group_id, group_name, content, auth, http).await,
+        Push::Schedule { call, reminder } =>
+            send_schedule_notification(device, call, *reminder, auth, http).await,
+        Push::Voicemail { message_id, from, to, .. } if sync =>
+            send_chat_sync_notification(device, &dm_key(from, to), message_id, auth, http).await,
+        Push::Voicemail { from, to, call_id, .. } =>
+            send_voicemail_notification(device, from, to, call_id, auth, http).await,
+    }
+}
+
 // ── Call notification ─────────────────────────────────────────────────────────
 
-pub async fn send_fcm_notification(
-    fcm_token: &str,
+#[allow(clippy::too_many_arguments)]
+async fn send_fcm_notification(
+    device:    &Device,
+    call_id:   &str,
     from:      &str,
     to:        &str,
     video:     bool,
+    ring_sec:  u64,
     auth:      &dyn TokenProvider,
     http:      &reqwest::Client,
 ) -> TokenStatus {
-    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Ok };
+    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Failed };
 
-    let url  = format!("https://fcm.googleapis.com/v1/projects/{FCM_PROJECT_ID}/messages:send");
+    let url  = send_url();
     
     let title = if video {
         format!("📹 Incoming video call from {from}")
@@ -34,62 +112,67 @@ pub async fn send_fcm_notification(
         format!("📞 Incoming audio call from {from}")
     };
     
-    let body = serde_json::json!({
-        "message": {
-            "token": fcm_token,
-            "data": {
-                "action": if video { "incoming_video_call" } else { "incoming_call" },
-                "caller": from,
-                "callee": to,
-                "title":  title,
-                "body":   "Tap Accept to answer",
-                "video":  if video { "true" } else { "false" },
-            },
-            "android": { "priority": "high" },
-            "apns":    { "headers": { "apns-priority": "10" } },
-            "webpush": { "headers": { "Urgency": "high" } },
-        }
+    let data = serde_json::json!({
+        "action":     if video { "incoming_video_call" } else { "incoming_call" },
+        "call_id":    call_id,
+        "caller":     from,
+        "callee":     to,
+        "title":      title,
+        "body":       "Tap Accept to answer",
+        "video":      if video { "true" } else { "false" },
+        // Unix seconds after which the ring is stale, should the OS deliver it late anyway
+        "expires_at": (chrono::Utc::now().timestamp() + ring_sec as i64).to_string(),
     });
+    let notice = Notice {
+        kind:         PushKind::Call,
+        title,
+        body:         "Tap Accept to answer".into(),
+        tag:          format!("incoming-call-{from}"),
+        collapse_key: format!("call-{call_id}"),
+        ttl_sec:      ring_sec,
+    };
+    let body = build_message(device, data, &notice);
 
-    send_raw(fcm_token, &url, &bearer, &body, http, "call").await
+    send_raw(&device.token, &url, &bearer, &body, http, "call").await
 }
 
 // ── Chat DM notification ──────────────────────────────────────────────────────
 
-pub async fn send_chat_dm_notification(
-    fcm_token: &str,
+async fn send_chat_dm_notification(
+    device:    &Device,
     from:      &str,
     to:        &str,
     content:   &str,
     auth:      &dyn TokenProvider,
     http:      &reqwest::Client,
 ) -> TokenStatus {
-    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Ok };
-
-    let preview: String = content.chars().take(200).collect();
-    let url  = format!("https://fcm.googleapis.com/v1/projects/{FCM_PROJECT_ID}/messages:send");
-    let body = serde_json::json!({
-        "message": {
-            "token": fcm_token,
-            "data": {
-                "action":  "chat_message",
-                "sender":  from,   // "from" is reserved by FCM
-                "to":      to,
-                "content": preview,
-            },
-            "android": { "priority": "high" },
-            "apns":    { "headers": { "apns-priority": "10" } },
-            "webpush": { "headers": { "Urgency": "high" } },
-        }
+    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Failed };
+
+    let preview: String = content.chars().take(settings::get().fcm.preview_chars).collect();
+    let url  = send_url();
+    let data = serde_json::json!({
+        "action":  "chat_message",
+        "sender":  from,   // "from" is reserved by FCM
+        "to":      to,
+        "content": preview,
     });
+    let notice = Notice {
+        kind:         PushKind::Chat,
+        title:        format!("💬 {from}"),
+        body:         preview,
+        tag:          format!("chat-dm-{from}"),
+        collapse_key: dm_key(from, to),
+        ttl_sec:      CHAT_PUSH_TTL_SEC,
+    };
+    let body = build_message(device, data, &notice);
 
-    send_raw(fcm_token, &url, &bearer, &body, http, "chat-dm").await
+    send_raw(&device.token, &url, &bearer, &body, http, "chat-dm").await
 }
 
 // ── Chat group notification ───────────────────────────────────────────────────
 
-pub async fn send_chat_group_notification(
-    fcm_token:  &str,
+async fn send_chat_group_notification(
+    device:     &Device,
     from:       &str,
     group_id:   &str,
     group_name: &str,
@@ -97,31 +180,277 @@ pub async fn send_chat_group_notification(
     auth:       &dyn TokenProvider,
     http:       &reqwest::Client,
 ) -> TokenStatus {
-    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Ok };
-
-    let preview: String = content.chars().take(200).collect();
-    let url  = format!("https://fcm.googleapis.com/v1/projects/{FCM_PROJECT_ID}/messages:send");
-    let body = serde_json::json!({
-        "message": {
-            "token": fcm_token,
-            "data": {
-                "action":     "chat_message",
-                "sender":     from,   // "from" is reserved by FCM
-                "group_id":   group_id,
-                "group_name": group_name,
-                "content":    preview,
+    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Failed };
+
+    let preview: String = content.chars().take(settings::get().fcm.preview_chars).collect();
+    let url  = send_url();
+    let data = serde_json::json!({
+        "action":     "chat_message",
+        "sender":     from,   // "from" is reserved by FCM
+        "group_id":   group_id,
+        "group_name": group_name,
+        "content":    preview,
+    });
+    let notice = Notice {
+        kind:         PushKind::Chat,
+        title:        format!("{from} in {group_name}"),
+        body:         preview,
+        tag:          format!("chat-group-{group_id}"),
+        collapse_key: group_key(group_id),
+        ttl_sec:      CHAT_PUSH_TTL_SEC,
+    };
+    let body = build_message(device, data, &notice);
+
+    send_raw(&device.token, &url, &bearer, &body, http, "chat-group").await
+}
+
+// ── Chat sync notification ────────────────────────────────────────────────────
+
+// Data-only wake-up: the client fetches the message itself from `GET /messages`.
+// No collapse key — every message id must reach the client to be fetched.
+async fn send_chat_sync_notification(
+    device:           &Device,
+    conversation_key: &str,
+    message_id:       &str,
+    auth:             &dyn TokenProvider,
+    http:             &reqwest::Client,
+) -> TokenStatus {
+    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Failed };
+
+    let url = send_url();
+    let ttl = CHAT_PUSH_TTL_SEC;
+    let mut message = serde_json::json!({
+        "token": device.token,
+        "data": {
+            "action":           "chat_sync",
+            "conversation_key": conversation_key,
+            "message_id":       message_id,
+        },
+    });
+    let (key, block) = match device.platform {
+        Platform::Android => ("android", serde_json::json!({
+            "priority": "high",
+            "ttl":      format!("{ttl}s"),
+        })),
+        // Background pushes must use priority 5 and content-available, with no alert
+        Platform::Ios => ("apns", serde_json::json!({
+            "headers": {
+                "apns-priority":   "5",
+                "apns-push-type":  "background",
+                "apns-expiration": (chrono::Utc::now().timestamp() + ttl as i64).to_string(),
             },
-            "android": { "priority": "high" },
-            "apns":    { "headers": { "apns-priority": "10" } },
-            "webpush": { "headers": { "Urgency": "high" } },
-        }
+            "payload": { "aps": { "content-available": 1 } },
+        })),
+        Platform::Web => ("webpush", serde_json::json!({
+            "headers": { "Urgency": "normal", "TTL": ttl.to_string() },
+        })),
+    };
+    message[key] = block;
+    let body = serde_json::json!({ "message": message });
+
+    send_raw(&device.token, &url, &bearer, &body, http, "chat-sync").await
+}
+
+// ── Scheduled call notification ───────────────────────────────────────────────
+
+// A plain alert (not the full-screen call style); it expires at the start time.
+async fn send_schedule_notification(
+    device:    &Device,
+    call:      &ScheduledCall,
+    reminder:  bool,
+    auth:      &dyn TokenProvider,
+    http:      &reqwest::Client,
+) -> TokenStatus {
+    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Failed };
+
+    let url  = send_url();
+    let when = call.start_at.format("%Y-%m-%d %H:%M UTC").to_string();
+    let (title, body) = if reminder {
+        (format!("⏰ {} starts in {} min", call.title, call.remind_before), format!("Scheduled by {}", call.created_by))
+    } else {
+        (format!("📅 {} scheduled a call", call.created_by), format!("{} — {when}", call.title))
+    };
+    let data = serde_json::json!({
+        "action":      if reminder { "scheduled_call_reminder" } else { "call_scheduled" },
+        "schedule_id": call.schedule_id,
+        "creator":     call.created_by,
+        "start_at":    call.start_at.to_rfc3339(),
+        "title":       title,
+        "body":        body,
+    });
+    let notice = Notice {
+        kind:         PushKind::Schedule,
+        title,
+        body,
+        tag:          format!("schedule-{}", call.schedule_id),
+        collapse_key: format!("schedule-{}", call.schedule_id),
+        ttl_sec:      (call.start_at - chrono::Utc::now()).num_seconds().max(60) as u64,
+    };
+    let body = build_message(device, data, &notice);
+
+    send_raw(&device.token, &url, &bearer, &body, http, "schedule").await
+}
+
+// ── Voicemail notification ────────────────────────────────────────────────────
+
+async fn send_voicemail_notification(
+    device:    &Device,
+    from:      &str,
+    to:        &str,
+    call_id:   &str,
+    auth:      &dyn TokenProvider,
+    http:      &reqwest::Client,
+) -> TokenStatus {
+    let bearer = match get_bearer(auth).await { Some(t) => t, None => return TokenStatus::Failed };
+
+    let url  = send_url();
+    let data = serde_json::json!({
+        "action":  "voicemail",
+        "sender":  from,   // "from" is reserved by FCM
+        "to":      to,
+        "call_id": call_id,
     });
+    let notice = Notice {
+        kind:         PushKind::Chat,
+        title:        format!("🎙 New voicemail from {from}"),
+        body:         "Tap to listen".into(),
+        tag:          format!("chat-dm-{from}"),
+        collapse_key: format!("voicemail-{call_id}"),
+        ttl_sec:      CHAT_PUSH_TTL_SEC,
+    };
+    let body = build_message(device, data, &notice);
+
+    send_raw(&device.token, &url, &bearer, &body, http, "voicemail").await
+}
+
+// ── Platform payloads ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PushKind {
+    Call,
+    Chat,
+    /// Scheduled-call notices and reminders: shown, but nothing to answer or reply to.
+    Schedule,
+}
+
+/// Visible part of a push, rendered into the block for the token's platform.
+struct Notice {
+    kind:         PushKind,
+    title:        String,
+    body:         String,
+    tag:          String,   // a newer notification with the same tag replaces the older one
+    collapse_key: String,   // undelivered pushes sharing a key collapse into the newest one
+    ttl_sec:      u64,      // FCM drops the push if the device stays unreachable this long
+}
 
-    send_raw(fcm_token, &url, &bearer, &body, http, "chat-group").await
+/// Wraps `data` into an FCM v1 message carrying only the block for `device.platform`.
+fn build_message(device: &Device, data: Value, notice: &Notice) -> Value {
+    let mut message = serde_json::json!({ "token": device.token, "data": data });
+    let (key, block) = match device.platform {
+        Platform::Android => ("android", android_config(notice, &message["data"])),
+        Platform::Ios     => ("apns",    apns_config(notice)),
+        Platform::Web     => ("webpush", webpush_config(notice)),
+    };
+    message[key] = block;
+    serde_json::json!({ "message": message })
+}
+
+fn android_config(notice: &Notice, data: &Value) -> Value {
+    match notice.kind {
+        // Calls stay data-only: the app builds a CallStyle notification with a
+        // full-screen intent itself, which a `notification` block would bypass.
+        PushKind::Call => {
+            let mut data = data.clone();
+            data["channel_id"]         = ANDROID_CALL_CHANNEL.into();
+            data["style"]              = "call".into();
+            data["full_screen_intent"] = "true".into();
+            serde_json::json!({
+                "priority":     "high",
+                "ttl":          format!("{}s", notice.ttl_sec),
+                "collapse_key": notice.collapse_key,
+                "data":         data,
+            })
+        }
+        PushKind::Chat | PushKind::Schedule => serde_json::json!({
+            "priority":     "high",
+            "ttl":          format!("{}s", notice.ttl_sec),
+            "collapse_key": notice.collapse_key,
+            "notification": {
+                "channel_id": if notice.kind == PushKind::Chat { ANDROID_CHAT_CHANNEL } else { ANDROID_SCHEDULE_CHANNEL },
+                "title":      notice.title,
+                "body":       notice.body,
+                "tag":        notice.tag,
+            },
+        }),
+    }
+}
+
+fn apns_config(notice: &Notice) -> Value {
+    let (category, level) = match notice.kind {
+        PushKind::Call     => (APNS_CALL_CATEGORY, "time-sensitive"),
+        PushKind::Chat     => (APNS_CHAT_CATEGORY, "active"),
+        PushKind::Schedule => (APNS_SCHEDULE_CATEGORY, "active"),
+    };
+    let expiration = chrono::Utc::now().timestamp() + notice.ttl_sec as i64;
+    serde_json::json!({
+        "headers": {
+            "apns-priority":    "10",
+            "apns-push-type":   "alert",
+            "apns-expiration":  expiration.to_string(),
+            "apns-collapse-id": apns_collapse_id(&notice.collapse_key),
+        },
+        "payload": {
+            "aps": {
+                "alert":              { "title": notice.title, "body": notice.body },
+                "category":           category,
+                "thread-id":          notice.tag,
+                "sound":              "default",
+                "interruption-level": level,
+            },
+        },
+    })
+}
+
+fn webpush_config(notice: &Notice) -> Value {
+    let actions = match notice.kind {
+        PushKind::Call => serde_json::json!([
+            { "action": "accept", "title": "✅ Accept" },
+            { "action": "reject", "title": "❌ Decline" },
+        ]),
+        PushKind::Chat => serde_json::json!([
+            { "action": "reply", "title": "↩ Reply", "type": "text", "placeholder": "Type a reply…" },
+        ]),
+        PushKind::Schedule => serde_json::json!([]),
+    };
+    serde_json::json!({
+        "headers": { "Urgency": "high", "TTL": notice.ttl_sec.to_string() },
+        "notification": {
+            "title":              notice.title,
+            "body":               notice.body,
+            "icon":               WEB_ICON,
+            "badge":              WEB_BADGE,
+            "tag":                notice.tag,
+            "renotify":           true,
+            "requireInteraction": notice.kind == PushKind::Call,
+            "actions":            actions,
+        },
+    })
+}
+
+// APNs rejects collapse ids longer than 64 bytes; cut on a char boundary.
+fn apns_collapse_id(key: &str) -> &str {
+    let mut end = key.len().min(64);
+    while !key.is_char_boundary(end) { end -= 1; }
+    &key[..end]
 }
 
 // ── Internal helpers ──────────────────────────────────────────────────────────
 
+/// FCM v1 send endpoint for the configured project.
+fn send_url() -> String {
+    format!("https://fcm.googleapis.com/v1/projects/{}/messages:send", settings::get().fcm.project_id)
+}
+
 async fn get_bearer(auth: &dyn TokenProvider) -> Option<String> {
     match auth.token(&["https://www.googleapis.com/auth/firebase.messaging"]).await {
         Ok(t)  => Some(t.as_str().to_owned()),
@@ -156,12 +485,82 @@ async fn send_raw(
                 TokenStatus::Evict
             } else {
                 error!("[fcm/{label}] ✗ HTTP {status}: {text}");
-                TokenStatus::Ok
+                TokenStatus::Failed
             }
         }
         Err(e) => {
             error!("[fcm/{label}] request error: {e}");
-            TokenStatus::Ok
+            TokenStatus::Failed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DevicePrefs, PushKind as Transport};
+
+    fn device(platform: Platform) -> Device {
+        let now = chrono::Utc::now();
+        Device {
+            device_id:    "d1".into(),
+            platform,
+            push_kind:    Transport::Fcm,
+            token:        "token".into(),
+            api_key:      String::new(),
+            app_version:  None,
+            prefs:        DevicePrefs::default(),
+            created_at:   now,
+            refreshed_at: now,
+        }
+    }
+
+    fn reminder() -> Notice {
+        Notice {
+            kind:         PushKind::Schedule,
+            title:        "⏰ Standup starts in 5 min".into(),
+            body:         "Scheduled by alice".into(),
+            tag:          "schedule-s1".into(),
+            collapse_key: "schedule-s1".into(),
+            ttl_sec:      300,
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn schedule_notices_offer_no_reply() {
+        let data = serde_json::json!({ "action": "scheduled_call_reminder" });
+
+        let web = build_message(&device(Platform::Web), data.clone(), &reminder());
+        assert_eq!(web["message"]["webpush"]["notification"]["actions"], serde_json::json!([]));
+
+        let android = build_message(&device(Platform::Android), data.clone(), &reminder());
+        assert_eq!(android["message"]["android"]["notification"]["channel_id"], ANDROID_SCHEDULE_CHANNEL);
+
+        let ios = build_message(&device(Platform::Ios), data, &reminder());
+        assert_eq!(ios["message"]["apns"]["payload"]["aps"]["category"], APNS_SCHEDULE_CATEGORY);
+    }
+
+    #[test]
+    fn short_collapse_ids_are_kept() {
+        assert_eq!(apns_collapse_id(""), "");
+        assert_eq!(apns_collapse_id("call::c1"), "call::c1");
+        let max = "k".repeat(64);
+        assert_eq!(apns_collapse_id(&max), max);
+    }
+
+    #[test]
+    fn long_collapse_ids_are_cut_to_64_bytes() {
+        let key = format!("chat::{}", "x".repeat(100));
+        assert_eq!(apns_collapse_id(&key), &key[..64]);
+    }
+
+    #[test]
+    fn collapse_ids_are_cut_on_a_char_boundary() {
+        // 63 ASCII bytes, then a 3-byte character straddling the limit
+        let key = format!("{}€tail", "g".repeat(63));
+        assert_eq!(apns_collapse_id(&key), "g".repeat(63));
+
+        let emoji = "😀".repeat(20);   // 80 bytes, 4 per char
+        assert_eq!(apns_collapse_id(&emoji), "😀".repeat(16));
+    }
+}
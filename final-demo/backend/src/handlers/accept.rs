@@ -86,27 +86,32 @@
 // }
 // src/handlers/accept.rs — Callee accepts a ringing 1-to-1 call.
 // LiveKit: on accept → create room → generate tokens → send to both sides.
+// Answering a waiting call first holds or ends the call the callee is on.
+// Only the placing and the accepting tab are signalled; the parties' other
+// tabs (including the callee's, which stop ringing) get CALL_ON_OTHER_DEVICE.
 
 use socketioxide::extract::{Data, SocketRef, State};
 use tracing::info;
 
+use super::{
+    call::{active_dm_key, ringing_key},
+    call_control::{announce, park},
+};
 use crate::{
-    livekit::{create_room, dm_room_name, generate_token},
+    media::{dm_room_name, session_room_name, ParticipantRole},
+    routing, stats,
     types::{
-        event, AcceptPayload, AppState, CallAcceptedPayload, CallEndedPayload,
-        CallStatus, ErrorPayload, LiveKitTokenPayload,
+        event, AcceptPayload, ActiveCallAction, AppState, CallAcceptedPayload, CallOutcome,
+        CallSession, CallStatus, ErrorPayload, LiveKitTokenPayload, WaitingCallEndedPayload,
     },
 };
 
-// event name for livekit token (add to your event mod in types.rs)
-const EV_LIVEKIT_TOKEN: &str = "livekit_token";
-
 pub async fn on_accept(
     socket: SocketRef,
     State(state): State<AppState>,
     Data(payload): Data<AcceptPayload>,
 ) {
-    let AcceptPayload { from, to } = payload; // from=callee, to=caller
+    let AcceptPayload { from, to, active_call } = payload; // from=callee, to=caller
     let socket_id = socket.id;
 
     if !super::call::identity_matches(&state, socket_id, &from).await {
@@ -116,82 +121,110 @@ pub async fn on_accept(
 
     let mut calls = state.calls.write().await;
 
-    let Some(session) = calls.get_mut(&from) else {
-        emit_error(&socket, "No active call to accept");
+    let Some(key) = ringing_key(&calls, &from, &to) else {
+        // Guard against double-accept: this tab lost the race to another one
+        if let Some(s) = calls.get(&from).filter(|s| s.caller == to && s.status == CallStatus::Active) {
+            let _ = socket.emit(event::CALL_ON_OTHER_DEVICE, &routing::elsewhere_payload(s, &from));
+        } else {
+            emit_error(&socket, "No active call to accept");
+        }
         return;
     };
-    if session.caller != to {
-        emit_error(&socket, "Caller mismatch");
-        return;
-    }
 
-    // Guard against double-accept
-    if session.status == CallStatus::Active {
-        let _ = socket.emit(event::CALL_ENDED,
-            &CallEndedPayload { reason: "Call accepted on another tab".into() });
-        return;
+    // ── Waiting call: set the current call aside ──────────────────────────────
+    let (mut held, mut ended) = (None, None);
+    if key != from {
+        if calls.get(&from).is_some_and(|s| s.status == CallStatus::Ringing) {
+            emit_error(&socket, "Answer or reject your other incoming call first");
+            return;
+        }
+        if let Some(current) = active_dm_key(&calls, &from) {
+            match active_call {
+                ActiveCallAction::Hold => held  = park(&mut calls, &current, &from),
+                ActiveCallAction::End  => ended = calls.remove(&current),
+            }
+        }
+        let session = calls.remove(&key).unwrap();
+        calls.insert(from.clone(), session);
     }
 
+    let session = calls.get_mut(&from).unwrap();
     session.status = CallStatus::Active;
-    let caller_socket_id = session.caller_socket_id;
+    session.callee_socket_id = Some(socket_id);
+    let session = session.clone();
+    let (video, call_id) = (session.video, session.call_id.clone());
     drop(calls);
+    stats::joined(&state.history, &call_id, &[&from]).await;
 
-    // ── LiveKit: create room + generate tokens ────────────────────────────────
-    let room_name = dm_room_name(&from, &to);
-    let lk = &state.livekit;
+    if let Some(held) = held {
+        announce(&socket, &state, held, &from, true).await;
+    }
+    if let Some(ended) = ended {
+        stats::ended(&state.history, &ended.call_id, CallOutcome::Completed).await;
+        let other = routing::other_party(&ended, &from);
+        let room  = session_room_name(&ended);
+        let media = state.media.clone();
+        tokio::spawn(async move { media.delete_room(&room).await });
+        routing::end_for(&socket, &state.users, &ended, &other, &format!("{from} answered another call")).await;
+        routing::release_elsewhere(&socket, &state.users, &ended, &from).await;
+    }
 
-    // Create the room (idempotent — safe to call even if room exists)
-    create_room(lk, &room_name).await;
-
-    // Generate one token per participant
-    let callee_token = generate_token(lk, &room_name, &from);
-    let caller_token = generate_token(lk, &room_name, &to);
-
-    let users = state.users.read().await;
-
-    // ── Notify caller tabs ────────────────────────────────────────────────────
-    if let Some(cs) = users.get(&to) {
-        for sid in &cs.socket_ids {
-            if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                // 1. call_accepted signal
-                let _ = peer.emit(event::CALL_ACCEPTED, &CallAcceptedPayload { by: from.clone() });
-
-                // 2. livekit token (only to the tab that placed the call)
-                if *sid == caller_socket_id {
-                    if let Some(ref token) = caller_token {
-                        let _ = peer.emit(EV_LIVEKIT_TOKEN, &LiveKitTokenPayload {
-                            room:  room_name.clone(),
-                            token: token.clone(),
-                            url:   lk.url.clone(),
-                        });
-                    }
-                }
-            }
-        }
+    // ── A waiting call was shown beside another call; take it down there ──────
+    if key != from {
+        routing::emit_elsewhere(&socket, &state.users, &session, &from, event::WAITING_CALL_ENDED,
+            &WaitingCallEndedPayload { call_id: call_id.clone(), reason: "Answered on another tab".into() }).await;
     }
 
-    // ── Dismiss ringing on other callee tabs ──────────────────────────────────
-    if let Some(cs) = users.get(&from) {
-        for sid in &cs.socket_ids {
-            if *sid != socket_id {
-                if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                    let _ = peer.emit(event::CALL_ENDED,
-                        &CallEndedPayload { reason: "Answered on another tab".into() });
-                }
-            }
-        }
+    connect(&socket, &state, &session, &from, &to).await;
+    let kind = if video { "video" } else { "audio" };
+    info!("[✓] '{from}' accepted {kind} call from '{to}' — {} room '{}'", state.media.name(), dm_room_name(&call_id));
+}
+
+/// Puts the parties of the freshly Active 1-to-1 `session` in touch once
+/// `from` answered it on `socket`: media room and credentials,
+/// CALL_ACCEPTED to the tab that placed it, and CALL_ON_OTHER_DEVICE to
+/// both parties' other tabs.
+pub async fn connect(socket: &SocketRef, state: &AppState, session: &CallSession, from: &str, to: &str) {
+    let (video, call_id) = (session.video, session.call_id.clone());
+
+    // ── LiveKit: create room + generate tokens ────────────────────────────────
+    let room_name = dm_room_name(&call_id);
+    let media = &state.media;
+
+    // Create the room (idempotent — safe to call even if room exists)
+    media.create_room(&room_name).await;
+
+    // Generate one set of credentials per participant
+    let callee_creds = media.join_credentials(&room_name, from, &call_id, ParticipantRole::Speaker, video);
+    let caller_creds = media.join_credentials(&room_name, to, &call_id, ParticipantRole::Speaker, video);
+
+    // ── Notify the tab that placed the call ───────────────────────────────────
+    routing::emit_to_side(socket, &state.users, session, to, event::CALL_ACCEPTED,
+        &CallAcceptedPayload { by: from.to_owned() }).await;
+    if let Some(ref creds) = caller_creds {
+        routing::emit_to_side(socket, &state.users, session, to, event::LIVEKIT_TOKEN, &LiveKitTokenPayload {
+            call_id: call_id.clone(),
+            media:   creds.media.to_owned(),
+            room:    room_name.clone(),
+            token:   creds.token.clone(),
+            url:     creds.url.clone(),
+        }).await;
     }
 
     // ── Send LiveKit token to the accepting callee tab ────────────────────────
-    if let Some(ref token) = callee_token {
-        let _ = socket.emit(EV_LIVEKIT_TOKEN, &LiveKitTokenPayload {
-            room:  room_name.clone(),
-            token: token.clone(),
-            url:   lk.url.clone(),
+    if let Some(ref creds) = callee_creds {
+        let _ = socket.emit(event::LIVEKIT_TOKEN, &LiveKitTokenPayload {
+            call_id: call_id.clone(),
+            media:   creds.media.to_owned(),
+            room:    room_name.clone(),
+            token:   creds.token.clone(),
+            url:     creds.url.clone(),
         });
     }
 
-    info!("[✓] '{from}' accepted call from '{to}' — LiveKit room '{room_name}'");
+    // ── Other tabs of both parties stop ringing and may take the call over ────
+    routing::announce_elsewhere(socket, &state.users, session, from).await;
+    routing::announce_elsewhere(socket, &state.users, session, to).await;
 }
 
 fn emit_error(socket: &SocketRef, message: &str) {
@@ -2,16 +2,22 @@
 
 use socketioxide::extract::{Data, SocketRef, State};
 use socketioxide::socket::Sid;
-use std::sync::Arc;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
 use tracing::{info, warn};
+use uuid::Uuid;
 
 use crate::{
-    fcm::send_fcm_notification,
+    fcm::Push,
+    push::{ring_stages, RingStage},
     types::{
-        event, AppState, CallEndedPayload, CallMap, CallPayload, CallSession,
-        CallStatus, CallTarget, ErrorPayload, IncomingCallPayload, UserMap,
-        RING_TIMEOUT_SEC,
+        event, waiting_key, AppState, CallEndedPayload, CallOutcome, CallPayload, CallRecord,
+        CallSession, CallStatus, CallTarget, CallUnavailablePayload, ErrorPayload,
+        IncomingCallPayload, RingTimeouts, WaitingCallEndedPayload,
     },
+    routing, stats, voicemail,
 };
 
 pub async fn on_call(
@@ -19,7 +25,7 @@ pub async fn on_call(
     State(state): State<AppState>,
     Data(payload): Data<CallPayload>,
 ) {
-    let CallPayload { from, to, video } = payload;
+    let CallPayload { from, to, video, ring_timeout_sec } = payload;
     let socket_id = socket.id;  // Sid
 
     if from == to {
@@ -32,115 +38,217 @@ pub async fn on_call(
         return;
     }
 
-    let users = state.users.read().await;
-    let calls = state.calls.read().await;
+    {
+        let calls = state.calls.read().await;
 
-    let Some(callee_state) = users.get(&to) else {
-        emit_error(&socket, &format!("User '{to}' is not registered"));
-        return;
-    };
+        // Already ringing them — nothing to do
+        if ringing_key(&calls, &to, &from).is_some() {
+            return;
+        }
 
-    // If a call to this callee already exists from the same caller
-    if let Some(existing) = calls.get(&to) {
-        if existing.caller != from {
-            emit_error(&socket, &format!("'{to}' is busy on another call"));
+        // Prevent the caller from placing a new call while already in an active one
+        if on_active_call(&calls, &from) {
+            emit_error(&socket, "You are already on a call");
             return;
         }
-        return; 
     }
 
-    // Prevent the caller from placing a new call while already in an active one
-    let caller_busy = calls.values().any(|s| {
-        (s.caller == from || s.target.id() == from.as_str()) && s.status == CallStatus::Active
+    // The callee's call prefs may refuse the call before anything rings
+    let refused = state.users.read().await.get(&to).and_then(|u| {
+        let prefs = &u.call_prefs;
+        prefs.refusal(&to, &from).map(|reason| (reason, prefs.leave_message))
     });
-    if caller_busy {
-        emit_error(&socket, "You are already on a call");
+    if let Some((reason, leave_message)) = refused {
+        let _ = socket.emit(event::CALL_UNAVAILABLE,
+            &CallUnavailablePayload { to: to.clone(), reason, leave_message });
+        info!("[✗] {from} → {to} refused by call prefs");
         return;
     }
 
-    // Deliver "incoming_call" to every open tab of the callee
-    for sid in &callee_state.socket_ids {
-        if let Some(peer) = socket.broadcast().get_socket(*sid) {
-            let _ = peer.emit(event::INCOMING_CALL, &IncomingCallPayload { from: from.clone(), video: video.unwrap_or(false) });
-        }
+    let ring_sec = RingTimeouts::clamp(ring_timeout_sec, state.ring.direct_sec);
+    if let Err(message) = ring(&state, &socket, &from, &to, video.unwrap_or(false), ring_sec).await {
+        emit_error(&socket, &message);
     }
+}
 
-    // If callee is offline but has FCM tokens, send push notifications in a background task
-    let fcm_tokens = callee_state.fcm_tokens.clone();
-    if !fcm_tokens.is_empty() {
-        let (f, t2, http) = (from.clone(), to.clone(), state.http.clone());
-        let auth_clone = state.auth.clone();
-        let is_video = video.unwrap_or(false);
-        tokio::spawn(async move {
-            for token in fcm_tokens {
-                send_fcm_notification(&token, &f, &t2, is_video, auth_clone.as_ref(), &http).await;
-            }
-        });
-    } else if !callee_state.is_online() {
-        emit_error(&socket, &format!("'{to}' is offline and has no FCM token registered"));
-        return;
+/// Rings `to` for a call from `from`, placed from the tab `caller_socket`:
+/// INCOMING_CALL on every callee tab, a push to their devices (staged by the
+/// callee's ring strategy), and a Ringing session with its timeout. The
+/// caller's other tabs hear of it through CALL_ON_OTHER_DEVICE. A callee already on a 1-to-1 call gets it as a
+/// waiting call; it rings for `ring_sec`. Returns the new call id, or why
+/// the callee cannot be rung.
+pub async fn ring(
+    state:         &AppState,
+    caller_socket: &SocketRef,
+    from:          &str,
+    to:            &str,
+    video:         bool,
+    ring_sec:      u64,
+) -> Result<String, String> {
+    if crate::shutdown::draining(state) {
+        return Err("Server is restarting — try again shortly".into());
     }
+    let users = state.users.read().await;
+    let mut calls = state.calls.write().await;
+
+    let Some(callee_state) = users.get(to) else {
+        return Err(format!("User '{to}' is not registered"));
+    };
+
+    // On a call already → ring as a waiting call (at most one at a time)
+    let waiting = active_dm_key(&calls, to).is_some();
+    let key = if waiting { waiting_key(to) } else { to.to_owned() };
+    let Entry::Vacant(slot) = calls.entry(key.clone()) else {
+        return Err(format!("'{to}' is busy on another call"));
+    };
+
+    let call_id = Uuid::new_v4().to_string();
 
+    // Push to the callee's devices that accept calls, in a background task:
+    // the first stage now, later ones from the ring timeout while it still rings
+    let push = Push::Call {
+        call_id: call_id.clone(),
+        from:    from.to_owned(),
+        to:      to.to_owned(),
+        video,
+        ring_sec,
+    };
+    let mut stages = ring_stages(callee_state, &push);
+    stages.retain(|s| s.after_sec < ring_sec);
+    if stages.is_empty() && !callee_state.is_online() {
+        return Err(format!("'{to}' is offline and has no device registered for calls"));
+    }
+
+    // Take the key before anything reaches the callee (keyed by callee id,
+    // or waiting::{callee} while they finish their current call); the ring
+    // timeout replaces the placeholder handle once it runs
+    let target = CallTarget::User(to.to_owned());
+    let session = slot.insert(CallSession {
+        call_id:          call_id.clone(),
+        caller:           from.to_owned(),
+        target:           target.clone(),
+        status:           CallStatus::Ringing,
+        caller_socket_id: caller_socket.id,
+        callee_socket_id: None,
+        participants:     Vec::new(),
+        listeners:        Vec::new(),
+        removed:          Vec::new(),
+        locked:           false,
+        invited:          Vec::new(),
+        held_by:          None,
+        video,
+        recording:        None,
+        handoff:          None,
+        _timeout_handle:  Arc::new(tokio::spawn(async {}).abort_handle()),
+    }).clone();
     drop(calls);
+    state.history.write().await.insert(call_id.clone(),
+        CallRecord::new(&call_id, from, &target, video));
+
+    // Deliver "incoming_call" to every open tab of the callee
+    for sid in &callee_state.socket_ids {
+        if let Some(peer) = caller_socket.broadcast().get_socket(*sid) {
+            let _ = peer.emit(event::INCOMING_CALL, &IncomingCallPayload {
+                call_id: call_id.clone(),
+                from:    from.to_owned(),
+                video,
+                waiting,
+                queue:   None,
+            });
+        }
+    }
+    if stages.first().is_some_and(|s| s.after_sec == 0) {
+        state.push.dispatch(push, stages.remove(0).targets);
+    }
     drop(users);
 
-    // Start a background task that auto-cancels the call after RING_TIMEOUT_SEC
+    // Start a background task that auto-cancels the call after ring_sec
     let timeout_handle = spawn_ring_timeout(
-        from.clone(), to.clone(),
-        socket.clone(),
-        state.calls.clone(),
-        state.users.clone(),
+        call_id.clone(),
+        key.clone(),
+        ring_sec,
+        stages,
+        caller_socket.clone(),
+        state.clone(),
     );
+    match state.calls.write().await.get_mut(&key) {
+        Some(s) if s.call_id == call_id => s._timeout_handle = timeout_handle, // Dropping this aborts the timeout task
+        _ => timeout_handle.abort(),   // the call is over already
+    }
 
-    // Record the call session (keyed by callee id)
-    let mut calls = state.calls.write().await;
-    calls.insert(to.clone(), CallSession {
-        caller:           from.clone(),
-        target:           CallTarget::User(to.clone()),
-        status:           CallStatus::Ringing,
-        caller_socket_id: socket_id,
-        participants:     Vec::new(),
-        video:            video.unwrap_or(false),
-        _timeout_handle:  timeout_handle, // Dropping this aborts the timeout task
-    });
+    routing::announce_elsewhere(caller_socket, &state.users, &session, from).await;
 
-    info!("[~] Ringing: {from} → {to}");
+    let kind = if waiting { " (waiting)" } else { "" };
+    info!("[~] Ringing{kind}: {from} → {to}");
+    Ok(call_id)
 }
 
 // ── Ring-timeout ──────────────────────────────────────────────────────────────
 
-// Spawns a task that fires after RING_TIMEOUT_SEC.
-// If the call is still Ringing at that point, it is removed and both sides are notified.
+// Spawns a task that fires after `ring_sec`.
+// On the way it pushes each of the callee's later ring `stages` (in order), as
+// long as the same call is still Ringing; each push rings for what is left.
+// If the call is still Ringing at the end, it is removed and both sides are notified:
+// the caller's tab that placed it and every callee tab.
+// A callee who takes messages (`leave_message`) has the caller offered a voicemail.
+#[allow(clippy::collapsible_if)]
 fn spawn_ring_timeout(
-    caller_id: String,
-    callee_id: String,
+    call_id: String,
+    key: String,
+    ring_sec: u64,
+    stages: Vec<RingStage>,
     caller_socket: SocketRef,
-    calls: CallMap,
-    users: UserMap,
+    state: AppState,
 ) -> Arc<tokio::task::AbortHandle> {
     let task = tokio::spawn(async move {
-        tokio::time::sleep(tokio::time::Duration::from_secs(RING_TIMEOUT_SEC)).await;
+        let mut elapsed = 0;
+        for stage in stages {
+            tokio::time::sleep(tokio::time::Duration::from_secs(stage.after_sec - elapsed)).await;
+            elapsed = stage.after_sec;
 
-        let mut calls_w = calls.write().await;
-        if let Some(s) = calls_w.get(&callee_id) {
-            if s.status == CallStatus::Ringing && s.caller == caller_id {
-                calls_w.remove(&callee_id);
+            let push = match state.calls.read().await.get(&key) {
+                Some(s) if s.status == CallStatus::Ringing && s.call_id == call_id => Push::Call {
+                    call_id:  call_id.clone(),
+                    from:     s.caller.clone(),
+                    to:       s.target.id().to_owned(),
+                    video:    s.video,
+                    ring_sec: ring_sec - elapsed,
+                },
+                _ => return,
+            };
+            info!("[~] ringing {} more device(s) after {elapsed}s of call '{call_id}'", stage.targets.len());
+            state.push.dispatch(push, stage.targets);
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(ring_sec - elapsed)).await;
+
+        let mut calls_w = state.calls.write().await;
+        if let Some(s) = calls_w.get(&key) {
+            if s.status == CallStatus::Ringing && s.call_id == call_id {
+                let session = calls_w.remove(&key).unwrap();
                 drop(calls_w);
+                let (caller_id, callee_id) = (session.caller.clone(), session.target.id().to_owned());
+                stats::ended(&state.history, &call_id, CallOutcome::Missed).await;
 
                 // Tell caller the ring timed out
-                let _ = caller_socket.emit(event::CALL_ENDED,
-                    &CallEndedPayload { reason: "No answer".into() });
-                
+                routing::end_for(&caller_socket, &state.users, &session, &caller_id, "No answer").await;
+
                 // Dismiss ringing UI on all callee tabs
-                let users_r = users.read().await;
+                let users_r = state.users.read().await;
+                let mut leave_message = false;
                 if let Some(cs) = users_r.get(&callee_id) {
+                    leave_message = cs.call_prefs.leave_message;
                     for sid in &cs.socket_ids {
                         if let Some(peer) = caller_socket.broadcast().get_socket(*sid) {
-                            let _ = peer.emit(event::CALL_ENDED,
-                                &CallEndedPayload { reason: "No answer".into() });
+                            dismiss_ringing(&peer, &key, &callee_id, &call_id, "No answer");
                         }
                     }
                 }
+                drop(users_r);
+
+                if leave_message {
+                    let offer = voicemail::offer(&state, &call_id, &caller_id, &callee_id).await;
+                    let _ = caller_socket.emit(event::VOICEMAIL_OFFER, &offer);
+                }
 
                 warn!("[⏱] {caller_id} → {callee_id} timed out");
             }
@@ -149,6 +257,43 @@ fn spawn_ring_timeout(
     Arc::new(task.abort_handle())
 }
 
+// ── Call lookup ───────────────────────────────────────────────────────────────
+
+/// Key of the 1-to-1 call `caller` is ringing `callee` with, waiting or not.
+pub fn ringing_key(calls: &HashMap<String, CallSession>, callee: &str, caller: &str) -> Option<String> {
+    [callee.to_owned(), waiting_key(callee)].into_iter().find(|k| {
+        calls.get(k).is_some_and(|s| s.caller == caller && s.status == CallStatus::Ringing
+            && matches!(&s.target, CallTarget::User(c) if c == callee))
+    })
+}
+
+/// Key of the active 1-to-1 call `user` is on, as caller or callee.
+pub fn active_dm_key(calls: &HashMap<String, CallSession>, user: &str) -> Option<String> {
+    calls.iter()
+        .find(|(_, s)| s.status == CallStatus::Active && match &s.target {
+            CallTarget::User(callee) => callee == user || s.caller == user,
+            _ => false,
+        })
+        .map(|(k, _)| k.clone())
+}
+
+/// Whether `user` is a party of any active call, 1-to-1 or group.
+pub fn on_active_call(calls: &HashMap<String, CallSession>, user: &str) -> bool {
+    calls.values().any(|s| s.status == CallStatus::Active
+        && (s.caller == user || s.target.id() == user || s.participants.iter().any(|p| p == user)))
+}
+
+/// Dismisses an incoming-call UI on one callee tab. A waiting call must not
+/// disturb the call that tab is on, so it gets its own event.
+pub fn dismiss_ringing(peer: &SocketRef, key: &str, callee: &str, call_id: &str, reason: &str) {
+    if key == callee {
+        let _ = peer.emit(event::CALL_ENDED, &CallEndedPayload { reason: reason.to_owned() });
+    } else {
+        let _ = peer.emit(event::WAITING_CALL_ENDED,
+            &WaitingCallEndedPayload { call_id: call_id.to_owned(), reason: reason.to_owned() });
+    }
+}
+
 // ── Helpers ───────────────────────────────────────────────────────────────────
 
 fn emit_error(socket: &SocketRef, message: &str) {
@@ -0,0 +1,326 @@
+// src/handlers/call_control.rs — Hold, resume and blind transfer of 1-to-1 calls.
+//
+// A held call is parked under "held::{call_id}" with status Held. Its room
+// stays open but neither party may publish or subscribe, so both are free to
+// take other calls meanwhile. Only the party who put it on hold (`held_by`)
+// can resume it; resuming while on another call holds that one first (a swap).
+//
+// hold_call     → CALL_HELD to both parties' tabs carrying the call
+// resume_call   → CALL_RESUMED to the same, fresh credentials to the resuming tab
+// transfer_call → the transferring party drops out (CALL_ENDED) and the other
+//                 party rings the target on a new call (CALL_TRANSFERRED)
+// transfer_call_to_device → the sending tab takes over its user's side of the
+//                 call: fresh credentials to it, CALL_MOVED to the tab it left
+//                 and to the other party
+//
+// Tabs of a party that are not bound to the call hear of it through
+// CALL_ON_OTHER_DEVICE (see crate::routing), which is what lets a user move
+// from laptop to phone.
+
+use socketioxide::extract::{Data, SocketRef, State};
+use socketioxide::socket::Sid;
+use std::{collections::HashMap, time::Instant};
+use tracing::{info, warn};
+
+use super::call::{active_dm_key, on_active_call, ring};
+use crate::{
+    media::{session_room_name, ParticipantRole},
+    routing, stats,
+    types::{
+        event, held_key, AppState, CallEndedPayload, CallHoldPayload, CallMovedPayload,
+        CallOutcome, CallSession, CallStatus, CallTarget, CallTransferredPayload, ErrorPayload,
+        HoldCallPayload, LiveKitTokenPayload, TransferCallPayload, TransferCallToDevicePayload,
+    },
+};
+
+// ── hold_call ─────────────────────────────────────────────────────────────────
+
+pub async fn on_hold_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<HoldCallPayload>,
+) {
+    let HoldCallPayload { from, call_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let mut calls = state.calls.write().await;
+    let Some(key) = find_dm(&calls, &call_id, &from, CallStatus::Active) else {
+        emit_error(&socket, "No active call to hold");
+        return;
+    };
+    let parked = park(&mut calls, &key, &from);
+    drop(calls);
+
+    if let Some(parked) = parked {
+        announce(&socket, &state, parked, &from, true).await;
+    }
+    info!("[⏸] '{from}' put call '{call_id}' on hold");
+}
+
+// ── resume_call ───────────────────────────────────────────────────────────────
+
+pub async fn on_resume_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<HoldCallPayload>,
+) {
+    let HoldCallPayload { from, call_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let mut calls = state.calls.write().await;
+
+    let Some(session) = calls.get(&held_key(&call_id)) else {
+        emit_error(&socket, "No held call to resume");
+        return;
+    };
+    let CallTarget::User(callee) = session.target.clone() else { return; };
+    if session.caller != from && callee != from {
+        emit_error(&socket, "You are not in this call");
+        return;
+    }
+    if session.held_by.as_deref() != Some(from.as_str()) {
+        emit_error(&socket, "Only the party who put the call on hold can resume it");
+        return;
+    }
+    let other = if session.caller == from { callee.clone() } else { session.caller.clone() };
+
+    if on_active_call(&calls, &other) {
+        emit_error(&socket, &format!("'{other}' is on another call"));
+        return;
+    }
+    let current = active_dm_key(&calls, &from);
+    if current.is_none() && on_active_call(&calls, &from) {
+        emit_error(&socket, "Leave your group call before resuming");
+        return;
+    }
+    // The callee's key is where the call lives while active
+    if calls.contains_key(&callee) && current.as_deref() != Some(callee.as_str()) {
+        emit_error(&socket, &format!("'{callee}' has another call ringing"));
+        return;
+    }
+
+    // Swap: the call we are on goes on hold first
+    let swapped = current.and_then(|key| park(&mut calls, &key, &from));
+
+    let mut session = calls.remove(&held_key(&call_id)).unwrap();
+    session.status  = CallStatus::Active;
+    session.held_by = None;
+    if session.caller == from {
+        session.caller_socket_id = socket.id;
+    } else {
+        session.callee_socket_id = Some(socket.id);
+    }
+    let resumed = Parked::of(&session);
+    calls.insert(callee, session);
+    drop(calls);
+
+    if let Some(swapped) = swapped {
+        announce(&socket, &state, swapped, &from, true).await;
+    }
+    let (room, video) = (resumed.room.clone(), resumed.video);
+    announce(&socket, &state, resumed, &from, false).await;
+
+    // The resuming tab may not be the one that held the call
+    if let Some(creds) = state.media.join_credentials(&room, &from, &call_id, ParticipantRole::Speaker, video) {
+        let _ = socket.emit(event::LIVEKIT_TOKEN, &LiveKitTokenPayload {
+            call_id: call_id.clone(),
+            media:   creds.media.to_owned(),
+            room:    room.clone(),
+            token:   creds.token,
+            url:     creds.url,
+        });
+    }
+    info!("[▶] '{from}' resumed call '{call_id}' with '{other}'");
+}
+
+// ── transfer_call ─────────────────────────────────────────────────────────────
+
+pub async fn on_transfer_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<TransferCallPayload>,
+) {
+    let TransferCallPayload { from, call_id, to } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    if to == from {
+        emit_error(&socket, "Cannot transfer a call to yourself");
+        return;
+    }
+    if !state.users.read().await.contains_key(&to) {
+        emit_error(&socket, &format!("User '{to}' is not registered"));
+        return;
+    }
+
+    let mut calls = state.calls.write().await;
+    let Some(key) = find_dm(&calls, &call_id, &from, CallStatus::Active) else {
+        emit_error(&socket, "No active call to transfer");
+        return;
+    };
+    let session = &calls[&key];
+    let (other, other_sid) = if session.caller == from {
+        (session.target.id().to_owned(), session.callee_socket_id)
+    } else {
+        (session.caller.clone(), Some(session.caller_socket_id))
+    };
+    if to == other {
+        emit_error(&socket, &format!("'{to}' is already in this call"));
+        return;
+    }
+    // The new call is placed from the tab the other party is talking on
+    let Some(other_socket) = other_sid.and_then(|sid| socket.broadcast().get_socket(sid)) else {
+        emit_error(&socket, &format!("'{other}' is no longer connected"));
+        return;
+    };
+    let session = calls.remove(&key).unwrap();
+    drop(calls);
+    stats::ended(&state.history, &call_id, CallOutcome::Completed).await;
+
+    let room  = session_room_name(&session);
+    let media = state.media.clone();
+    tokio::spawn(async move { media.delete_room(&room).await });
+
+    routing::end_for(&socket, &state.users, &session, &from, &format!("Call transferred to {to}")).await;
+    routing::release_elsewhere(&socket, &state.users, &session, &other).await;
+
+    match ring(&state, &other_socket, &other, &to, session.video, state.ring.direct_sec).await {
+        Ok(new_call_id) => {
+            routing::emit_to_side(&socket, &state.users, &session, &other, event::CALL_TRANSFERRED,
+                &CallTransferredPayload { call_id: new_call_id, by: from.clone(), to: to.clone() }).await;
+            info!("[↪] '{from}' transferred '{other}' to '{to}'");
+        }
+        Err(message) => {
+            routing::emit_to_side(&socket, &state.users, &session, &other, event::CALL_ENDED,
+                &CallEndedPayload { reason: format!("Transfer to {to} failed: {message}") }).await;
+            emit_error(&socket, &message);
+            warn!("[↪] '{from}' could not transfer '{other}' to '{to}': {message}");
+        }
+    }
+}
+
+// ── transfer_call_to_device ───────────────────────────────────────────────────
+
+pub async fn on_transfer_call_to_device(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<TransferCallToDevicePayload>,
+) {
+    let TransferCallToDevicePayload { from, call_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let mut calls = state.calls.write().await;
+    let Some(key) = find_dm(&calls, &call_id, &from, CallStatus::Active) else {
+        emit_error(&socket, "No active call to move");
+        return;
+    };
+    let session = calls.get_mut(&key).unwrap();
+    let left      = routing::bound_socket(session, &from);
+    let other_sid = routing::bound_socket(session, &routing::other_party(session, &from));
+    if left == Some(socket.id) {
+        emit_error(&socket, "The call is already on this device");
+        return;
+    }
+    if session.caller == from {
+        session.caller_socket_id = socket.id;
+    } else {
+        session.callee_socket_id = Some(socket.id);
+    }
+    // The old tab is about to drop out of the room; the webhook must not take
+    // that for `from` hanging up
+    session.handoff = Some((from.clone(), Instant::now()));
+    let session = session.clone();
+    let (room, video) = (session_room_name(&session), session.video);
+    drop(calls);
+
+    // The media server admits one connection per identity, so joining with
+    // these drops whatever the old tab still has open
+    if let Some(creds) = state.media.join_credentials(&room, &from, &call_id, ParticipantRole::Speaker, video) {
+        let _ = socket.emit(event::LIVEKIT_TOKEN, &LiveKitTokenPayload {
+            call_id: call_id.clone(),
+            media:   creds.media.to_owned(),
+            room:    room.clone(),
+            token:   creds.token,
+            url:     creds.url,
+        });
+    }
+
+    let moved = CallMovedPayload { call_id: call_id.clone(), user_id: from.clone() };
+    let sids: Vec<_> = left.into_iter().chain(other_sid).collect();
+    routing::emit_to(&socket, &sids, event::CALL_MOVED, &moved);
+    routing::announce_elsewhere(&socket, &state.users, &session, &from).await;
+    info!("[⇄] '{from}' moved call '{call_id}' to socket {}", socket.id);
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// What is needed to pause or resume a 1-to-1 call's media.
+pub struct Parked {
+    call_id: String,
+    room:    String,
+    video:   bool,
+    parties: Vec<String>,
+    sockets: Vec<Sid>,   // the tabs carrying it
+}
+
+impl Parked {
+    fn of(session: &CallSession) -> Self {
+        Self {
+            call_id: session.call_id.clone(),
+            room:    session_room_name(session),
+            video:   session.video,
+            parties: vec![session.caller.clone(), session.target.id().to_owned()],
+            sockets: routing::bound_sockets(session),
+        }
+    }
+}
+
+/// Moves the active call at `key` to held::{call_id}, held by `by`.
+pub fn park(calls: &mut HashMap<String, CallSession>, key: &str, by: &str) -> Option<Parked> {
+    let mut session = calls.remove(key)?;
+    session.status  = CallStatus::Held;
+    session.held_by = Some(by.to_owned());
+    let parked = Parked::of(&session);
+    calls.insert(held_key(&parked.call_id), session);
+    Some(parked)
+}
+
+/// Pauses (or restores) both parties' media and tells them with CALL_HELD
+/// (or CALL_RESUMED).
+pub async fn announce(socket: &SocketRef, state: &AppState, parked: Parked, by: &str, held: bool) {
+    for uid in &parked.parties {
+        if !state.media.set_held(&parked.room, uid, held, parked.video).await {
+            warn!("[⏸] could not update media for '{uid}' in room '{}'", parked.room);
+        }
+    }
+    let ev = if held { event::CALL_HELD } else { event::CALL_RESUMED };
+    routing::emit_to(socket, &parked.sockets, ev, &CallHoldPayload { call_id: parked.call_id, by: by.to_owned() });
+}
+
+/// Key of the 1-to-1 call `call_id` with `user` as a party, in `status`.
+fn find_dm(calls: &HashMap<String, CallSession>, call_id: &str, user: &str, status: CallStatus) -> Option<String> {
+    calls.iter()
+        .find(|(_, s)| s.call_id == call_id && s.status == status && match &s.target {
+            CallTarget::User(callee) => callee == user || s.caller == user,
+            _ => false,
+        })
+        .map(|(k, _)| k.clone())
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
@@ -0,0 +1,68 @@
+// src/handlers/call_prefs.rs — Per-user call preferences.
+//
+// get_call_prefs → call_prefs with the user's current preferences
+// set_call_prefs → replaces them and echoes call_prefs to every tab of the user
+//
+// The preferences themselves (DND, contacts only, leave-a-message) are
+// enforced in call::on_call; the ring strategy staggers the pushes of
+// call::ring across the user's devices.
+
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::info;
+
+use super::recording::emit_to_participants;
+use crate::types::{
+    event, AppState, CallPrefsPayload, ErrorPayload, GetCallPrefsPayload, SetCallPrefsPayload,
+};
+
+pub async fn on_get_call_prefs(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<GetCallPrefsPayload>,
+) {
+    let GetCallPrefsPayload { user_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &user_id).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let prefs = {
+        let users = state.users.read().await;
+        users.get(&user_id).map(|u| u.call_prefs.clone()).unwrap_or_default()
+    };
+    let _ = socket.emit(event::CALL_PREFS, &CallPrefsPayload { prefs });
+}
+
+pub async fn on_set_call_prefs(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<SetCallPrefsPayload>,
+) {
+    let SetCallPrefsPayload { user_id, mut prefs } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &user_id).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    prefs.contacts.retain(|c| c != &user_id);
+    prefs.contacts.sort();
+    prefs.contacts.dedup();
+    prefs.ring_strategy = prefs.ring_strategy.clamped();
+
+    {
+        let mut users = state.users.write().await;
+        let Some(user) = users.get_mut(&user_id) else { return; };
+        user.call_prefs = prefs.clone();
+    }
+
+    info!("[prefs] '{user_id}' call prefs: dnd={} contacts_only={} ({} contacts) leave_message={} ring={:?}",
+        prefs.dnd, prefs.contacts_only, prefs.contacts.len(), prefs.leave_message, prefs.ring_strategy);
+    emit_to_participants(&socket, &state, std::slice::from_ref(&user_id), event::CALL_PREFS,
+        &CallPrefsPayload { prefs }).await;
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
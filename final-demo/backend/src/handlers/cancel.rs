@@ -1,9 +1,19 @@
 // src/handlers/cancel.rs — Caller cancels a ringing 1-to-1 call.
+//
+// Any of the caller's tabs may cancel, not just the one that placed the call:
+// the others show it through CALL_ON_OTHER_DEVICE.
 
 use socketioxide::extract::{Data, SocketRef, State};
 use tracing::info;
 
-use crate::types::{event, AppState, CallCancelledPayload, CallStatus, CancelPayload, ErrorPayload};
+use super::call::ringing_key;
+use crate::{
+    routing, stats,
+    types::{
+        event, AppState, CallCancelledPayload, CallEndedPayload, CallOutcome, CancelPayload,
+        ErrorPayload, WaitingCallEndedPayload,
+    },
+};
 
 pub async fn on_cancel(
     socket: SocketRef,
@@ -19,25 +29,28 @@ pub async fn on_cancel(
 
     let mut calls = state.calls.write().await;
     // Only valid if the call is still Ringing and was placed by this caller
-    let valid = calls.get(&to)
-        .map(|s| s.caller == from && s.status == CallStatus::Ringing)
-        .unwrap_or(false);
+    let Some(key) = ringing_key(&calls, &to, &from) else { return; };
 
-    if !valid { return; }
-
-    calls.remove(&to);
+    let session = calls.remove(&key).unwrap();
+    let call_id = session.call_id.clone();
     drop(calls);
-    
+    stats::ended(&state.history, &call_id, CallOutcome::Cancelled).await;
+
     // Notify all callee tabs so they dismiss the incoming-call UI
-    let users = state.users.read().await;
-    if let Some(cs) = users.get(&to) {
-        for sid in &cs.socket_ids {
-            if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                let _ = peer.emit(event::CALL_CANCELLED,
-                    &CallCancelledPayload { by: from.clone() });
-            }
-        }
+    if key == to {
+        routing::emit_to_side(&socket, &state.users, &session, &to, event::CALL_CANCELLED,
+            &CallCancelledPayload { by: from.clone() }).await;
+    } else {
+        routing::emit_to_side(&socket, &state.users, &session, &to, event::WAITING_CALL_ENDED,
+            &WaitingCallEndedPayload { call_id: call_id.clone(), reason: format!("Cancelled by {from}") }).await;
+    }
+
+    // Cancelled from another tab → the placing tab stops its ringback
+    if session.caller_socket_id != socket.id {
+        routing::emit_to_side(&socket, &state.users, &session, &from, event::CALL_ENDED,
+            &CallEndedPayload { reason: "Cancelled on another device".into() }).await;
     }
+    routing::release_elsewhere(&socket, &state.users, &session, &from).await;
 
     info!("[✗] {from} cancelled call → {to}");
 }
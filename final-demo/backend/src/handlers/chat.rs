@@ -5,16 +5,18 @@ use tracing::info;
 use uuid::Uuid;
 
 use crate::{
-    fcm::{send_chat_dm_notification, send_chat_group_notification, TokenStatus},
+    fcm::Push,
+    push::{targets_for, PushTarget},
     types::{
         event, AppState, DirectMessagePayload, ErrorPayload, GroupMessagePayload,
         SendDirectMessagePayload, SendGroupMessagePayload, StoredMessage,
-        UserMap, dm_key, group_key,
+        dm_key, group_key,
     },
 };
 
 // ── 1-to-1 direct message ─────────────────────────────────────────────────────
 
+#[allow(clippy::collapsible_if)]
 pub async fn on_send_message(
     socket: SocketRef,
     State(state): State<AppState>,
@@ -52,6 +54,7 @@ pub async fn on_send_message(
             target:     to.clone(),
             content:    content.clone(),
             timestamp:  timestamp.clone(),
+            voicemail:  None,
         });
     }
 
@@ -61,6 +64,7 @@ pub async fn on_send_message(
         to:         to.clone(),
         content:    content.clone(),
         timestamp,
+        voicemail:  None,
     };
 
     let users = state.users.read().await;
@@ -75,31 +79,18 @@ pub async fn on_send_message(
     }
 
     // ── FCM push — always, regardless of online status ────────────────────────
-    let tokens = users.get(&to)
-        .map(|cs| cs.fcm_tokens.clone())
+    let push = Push::ChatDm {
+        message_id: message_id.clone(),
+        from:       from.clone(),
+        to:         to.clone(),
+        content:    content.clone(),
+    };
+    let targets = users.get(&to)
+        .map(|cs| targets_for(cs, &push))
         .unwrap_or_default();
     drop(users);
 
-    if !tokens.is_empty() {
-        let (f, t2, c) = (from.clone(), to.clone(), content.clone());
-        let auth_clone = state.auth.clone();
-        let http       = state.http.clone();
-        let users_map  = state.users.clone();
-        tokio::spawn(async move {
-            for token in tokens {
-                let status = send_chat_dm_notification(
-                    &token, &f, &t2, &c,
-                    auth_clone.as_ref(), &http,
-                ).await;
-                if status == TokenStatus::Evict {
-                    evict_token(&users_map, &t2, &token).await;
-                }
-            }
-        });
-    } else {
-        // Re-acquire to finish the echo + ack below
-        let _ = state.users.read().await;
-    }
+    state.push.dispatch(push, targets);
 
     // ── Echo to sender's other open tabs ──────────────────────────────────────
     {
@@ -166,6 +157,7 @@ pub async fn on_send_group_message(
             target:     group_id.clone(),
             content:    content.clone(),
             timestamp:  timestamp.clone(),
+            voicemail:  None,
         });
     }
 
@@ -178,8 +170,15 @@ pub async fn on_send_group_message(
     };
 
     // ── Deliver via socket + collect FCM targets in one pass ──────────────────
-    // FCM targets = (member_id, token) for every member except the sender.
-    let mut fcm_targets: Vec<(String, String)> = Vec::new();
+    // FCM targets = every device of every member except the sender.
+    let push = Push::ChatGroup {
+        message_id: message_id.clone(),
+        from:       from.clone(),
+        group_id:   group_id.clone(),
+        group_name,
+        content:    content.clone(),
+    };
+    let mut fcm_targets: Vec<PushTarget> = Vec::new();
 
     {
         let users = state.users.read().await;
@@ -192,36 +191,16 @@ pub async fn on_send_group_message(
                         let _ = peer.emit(event::GROUP_MESSAGE, &outbound);
                     }
                 }
-                // Collect FCM tokens — skip sender
+                // Collect FCM devices — skip sender
                 if member_id != &from {
-                    for token in &ms.fcm_tokens {
-                        fcm_targets.push((member_id.clone(), token.clone()));
-                    }
+                    fcm_targets.extend(targets_for(ms, &push));
                 }
             }
         }
     }
 
     // ── FCM push — always, for every member except sender ────────────────────
-    if !fcm_targets.is_empty() {
-        let (f, gid, gname, c) = (
-            from.clone(), group_id.clone(), group_name.clone(), content.clone(),
-        );
-        let auth_clone = state.auth.clone();
-        let http       = state.http.clone();
-        let users_map  = state.users.clone();
-        tokio::spawn(async move {
-            for (member_id, token) in fcm_targets {
-                let status = send_chat_group_notification(
-                    &token, &f, &gid, &gname, &c,
-                    auth_clone.as_ref(), &http,
-                ).await;
-                if status == TokenStatus::Evict {
-                    evict_token(&users_map, &member_id, &token).await;
-                }
-            }
-        });
-    }
+    state.push.dispatch(push, fcm_targets);
 
     // ── Ack sending tab ───────────────────────────────────────────────────────
     let _ = socket.emit(event::MESSAGE_SENT, &outbound);
@@ -229,22 +208,6 @@ pub async fn on_send_group_message(
     info!("[💬] Group msg '{from}' → '{group_id}' (id: {})", &message_id[..8]);
 }
 
-// ── Token eviction ────────────────────────────────────────────────────────────
-
-async fn evict_token(users: &UserMap, user_id: &str, token: &str) {
-    let mut map = users.write().await;
-    if let Some(u) = map.get_mut(user_id) {
-        let before = u.fcm_tokens.len();
-        u.fcm_tokens.retain(|t| t != token);
-        if u.fcm_tokens.len() < before {
-            tracing::warn!(
-                "[fcm] evicted dead token for '{user_id}' ({} remaining)",
-                u.fcm_tokens.len()
-            );
-        }
-    }
-}
-
 // ── Helper ────────────────────────────────────────────────────────────────────
 
 fn emit_error(socket: &SocketRef, message: &str) {
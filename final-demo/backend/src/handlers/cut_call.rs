@@ -112,18 +112,21 @@
 // }
 // src/handlers/cut_call.rs — Either side ends an active 1-to-1 call.
 // LiveKit: on cut → delete the LiveKit room.
+// Either party may hang up from any of their tabs, not only the one carrying the call.
 
 use socketioxide::extract::{Data, SocketRef, State};
-use socketioxide::socket::Sid;
 use tracing::info;
 
 use crate::{
-    livekit::{delete_room, dm_room_name},
+    media::dm_room_name,
+    routing, stats,
     types::{
-        event, AppState, CallEndedPayload, CallStatus, CallTarget, CutCallPayload, ErrorPayload,
+        event, AppState, CallOutcome, CallSession, CallStatus, CallTarget, CutCallPayload,
+        ErrorPayload, WaitingCallEndedPayload,
     },
 };
 
+#[allow(clippy::collapsible_if)]
 pub async fn on_cut_call(
     socket: SocketRef,
     State(state): State<AppState>,
@@ -144,15 +147,16 @@ pub async fn on_cut_call(
         if s.caller == to && s.status == CallStatus::Active
             && matches!(&s.target, CallTarget::User(_))
         {
-            calls.remove(&from);
+            let (call_id, room) = (s.call_id.clone(), dm_room_name(&s.call_id));
+            let session = calls.remove(&from).unwrap();
             drop(calls);
+            stats::ended(&state.history, &call_id, CallOutcome::Completed).await;
 
             // Delete LiveKit room
-            let room = dm_room_name(&from, &to);
-            let lk = state.livekit.clone();
-            tokio::spawn(async move { delete_room(&lk, &room).await });
+            let media = state.media.clone();
+            tokio::spawn(async move { media.delete_room(&room).await });
 
-            notify_both_sides(&socket, &state, &from, &to, socket_id).await;
+            notify_both_sides(&socket, &state, &session, &from).await;
             info!("[☎] '{from}' ended call with '{to}'");
             return;
         }
@@ -163,71 +167,54 @@ pub async fn on_cut_call(
         if s.caller == from && s.status == CallStatus::Active
             && matches!(&s.target, CallTarget::User(_))
         {
-            calls.remove(&to);
+            let (call_id, room) = (s.call_id.clone(), dm_room_name(&s.call_id));
+            let session = calls.remove(&to).unwrap();
             drop(calls);
+            stats::ended(&state.history, &call_id, CallOutcome::Completed).await;
 
             // Delete LiveKit room
-            let room = dm_room_name(&from, &to);
-            let lk = state.livekit.clone();
-            tokio::spawn(async move { delete_room(&lk, &room).await });
+            let media = state.media.clone();
+            tokio::spawn(async move { media.delete_room(&room).await });
 
-            notify_both_sides(&socket, &state, &to, &from, socket_id).await;
+            notify_both_sides(&socket, &state, &session, &from).await;
             info!("[☎] '{from}' ended call with '{to}'");
             return;
         }
     }
 
-    emit_error(&socket, "No active call to cut");
-}
-
-async fn notify_both_sides(
-    socket: &SocketRef,
-    state: &AppState,
-    callee_id: &str,
-    caller_id: &str,
-    initiator_sid: Sid,
-) {
-    let users = state.users.read().await;
-
-    let initiator_uid = get_user_for_socket(state, initiator_sid).await;
-    let (other_id, same_id) = if initiator_uid.as_deref() == Some(callee_id) {
-        (caller_id, callee_id)
-    } else {
-        (callee_id, caller_id)
-    };
-
-    if let Some(s) = users.get(other_id) {
-        for sid in &s.socket_ids {
-            if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                let _ = peer.emit(event::CALL_ENDED,
-                    &CallEndedPayload { reason: format!("Call ended by {same_id}") });
-            }
-        }
-    }
-
-    if let Some(s) = users.get(same_id) {
-        for sid in &s.socket_ids {
-            if *sid != initiator_sid {
-                if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                    let _ = peer.emit(event::CALL_ENDED,
-                        &CallEndedPayload { reason: "You ended the call".into() });
-                }
-            }
+    // Case 3: Either side ends a held call
+    let held = calls.iter()
+        .find(|(_, s)| s.status == CallStatus::Held
+            && ((s.caller == from && s.target.id() == to) || (s.caller == to && s.target.id() == from)))
+        .map(|(k, _)| k.clone());
+    if let Some(key) = held {
+        let session = calls.remove(&key).unwrap();
+        drop(calls);
+        stats::ended(&state.history, &session.call_id, CallOutcome::Completed).await;
+
+        let room  = dm_room_name(&session.call_id);
+        let media = state.media.clone();
+        tokio::spawn(async move { media.delete_room(&room).await });
+
+        // Neither side's current call is affected
+        let ended = WaitingCallEndedPayload { call_id: session.call_id.clone(), reason: format!("Held call ended by {from}") };
+        for uid in [&from, &to] {
+            routing::emit_to_side(&socket, &state.users, &session, uid, event::WAITING_CALL_ENDED, &ended).await;
+            routing::release_elsewhere(&socket, &state.users, &session, uid).await;
         }
+        info!("[☎] '{from}' ended held call with '{to}'");
+        return;
     }
 
-    let _ = socket.emit(event::CALL_ENDED,
-        &CallEndedPayload { reason: "Call ended".into() });
+    emit_error(&socket, "No active call to cut");
 }
 
-async fn get_user_for_socket(state: &AppState, socket_id: Sid) -> Option<String> {
-    let map = state.users.read().await;
-    for (uid, s) in map.iter() {
-        if s.socket_ids.contains(&socket_id) {
-            return Some(uid.clone());
-        }
-    }
-    None
+/// CALL_ENDED to the tab carrying each side; the parties' other tabs drop
+/// their "call on another device" state.
+async fn notify_both_sides(socket: &SocketRef, state: &AppState, session: &CallSession, by: &str) {
+    let other = routing::other_party(session, by);
+    routing::end_for(socket, &state.users, session, &other, &format!("Call ended by {by}")).await;
+    routing::end_for(socket, &state.users, session, by, "Call ended").await;
 }
 
 fn emit_error(socket: &SocketRef, message: &str) {
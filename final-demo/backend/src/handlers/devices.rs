@@ -0,0 +1,97 @@
+// src/handlers/devices.rs — Device registry: list / remove devices, prune stale ones.
+
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::info;
+
+use crate::types::{
+    event, AppState, DeviceListPayload, ErrorPayload, ListDevicesPayload, RemoveDevicePayload,
+    UserMap, DEVICE_STALE_DAYS,
+};
+
+// How often the stale-device sweep runs.
+const PRUNE_INTERVAL_SEC: u64 = 60 * 60;
+
+// ── list_devices ──────────────────────────────────────────────────────────────
+
+pub async fn on_list_devices(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<ListDevicesPayload>,
+) {
+    let ListDevicesPayload { user_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &user_id).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let devices = {
+        let users = state.users.read().await;
+        users.get(&user_id).map(|u| u.devices.clone()).unwrap_or_default()
+    };
+    let _ = socket.emit(event::DEVICE_LIST, &DeviceListPayload { devices });
+}
+
+// ── remove_device ─────────────────────────────────────────────────────────────
+
+pub async fn on_remove_device(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<RemoveDevicePayload>,
+) {
+    let RemoveDevicePayload { user_id, device_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &user_id).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let devices = {
+        let mut users = state.users.write().await;
+        let Some(user) = users.get_mut(&user_id) else { return; };
+        let before = user.devices.len();
+        user.devices.retain(|d| d.device_id != device_id);
+        if user.devices.len() == before {
+            emit_error(&socket, &format!("Device '{device_id}' not found"));
+            return;
+        }
+        user.devices.clone()
+    };
+
+    // Reply with the updated list so the settings screen can re-render
+    let _ = socket.emit(event::DEVICE_LIST, &DeviceListPayload { devices });
+
+    info!("[fcm] '{user_id}' removed device '{device_id}'");
+}
+
+// ── Stale-device pruning ──────────────────────────────────────────────────────
+
+// Spawns a task that periodically drops devices whose token has not been
+// refreshed for DEVICE_STALE_DAYS. Clients re-send their token on every start,
+// so a device that stays silent this long has almost certainly been abandoned.
+pub fn spawn_stale_device_pruner(users: UserMap) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(tokio::time::Duration::from_secs(PRUNE_INTERVAL_SEC));
+        loop {
+            tick.tick().await;
+
+            let cutoff = chrono::Utc::now() - chrono::Duration::days(DEVICE_STALE_DAYS);
+            let mut map = users.write().await;
+            let mut pruned = 0;
+            for user in map.values_mut() {
+                let before = user.devices.len();
+                user.devices.retain(|d| d.refreshed_at >= cutoff);
+                pruned += before - user.devices.len();
+            }
+            if pruned > 0 {
+                info!("[fcm] pruned {pruned} stale devices (not refreshed in {DEVICE_STALE_DAYS} days)");
+            }
+        }
+    });
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
@@ -1,12 +1,21 @@
 // src/handlers/disconnect.rs — Socket disconnect cleanup.
+//
+// Closing the tab that carries a 1-to-1 call ends that call even if the user
+// has other tabs open (crate::routing). Everything else is cleaned up once
+// the user's last tab is gone.
 
 use socketioxide::extract::{SocketRef, State};
 use socketioxide::socket::Sid;
 use tracing::info;
 
-use crate::types::{
-    event, AppState, CallCancelledPayload, CallEndedPayload, CallTarget,
-    GroupCallEndedPayload, GroupMemberLeftPayload, UserOfflinePayload,
+use crate::{
+    media::session_room_name,
+    routing, stats,
+    types::{
+        event, waiting_key, AppState, CallCancelledPayload, CallOutcome, CallSession, CallStatus,
+        CallTarget, GroupCallEndedPayload, GroupMemberLeftPayload, UserOfflinePayload,
+        WaitingCallEndedPayload,
+    },
 };
 
 pub async fn on_disconnect(socket: SocketRef, State(state): State<AppState>) {
@@ -42,125 +51,107 @@ pub async fn on_disconnect(socket: SocketRef, State(state): State<AppState>) {
         }
     };
 
+    // ── 1-to-1 calls ──────────────────────────────────────────────────────────
+    // With other tabs still open, only the calls this tab was carrying go
+    // (a held call stays: whichever tab resumes it carries it from then on).
+    let mut calls = state.calls.write().await;
+    let dropped: Vec<String> = calls.iter()
+        .filter(|(_, s)| matches!(s.target, CallTarget::User(_)) && (s.caller == uid || s.target.id() == uid))
+        .filter(|(_, s)| went_fully_offline
+            || (s.status != CallStatus::Held && routing::bound_socket(s, &uid) == Some(socket_id)))
+        .map(|(k, _)| k.clone())
+        .collect();
+    let dropped: Vec<_> = dropped.into_iter().filter_map(|k| calls.remove(&k).map(|s| (k, s))).collect();
+    drop(calls);
+
+    for (key, session) in dropped {
+        drop_dm(&socket, &state, &uid, &key, session).await;
+    }
+
+    // ── Call queues ───────────────────────────────────────────────────────────
+    super::queue::on_tab_closed(&socket, &state, &uid, socket_id, went_fully_offline).await;
+
     // If the user still has other live tabs open, no further action needed
     if !went_fully_offline {
         info!("[-] '{uid}' closed tab {socket_id} (still has other tabs)");
         return;
     }
 
-    // ── Fully offline — clean up any in-progress call ─────────────────────────
+    // ── Fully offline — clean up any in-progress group call ───────────────────
 
     let mut calls = state.calls.write().await;
 
-    // Case 1: user was the callee in a 1-to-1 call (call is keyed by callee's uid)
-    if let Some(session) = calls.remove(&uid) {
-        let caller_id = session.caller.clone();
+    // Case 1: user was the initiator of a group call (call is keyed by group_id)
+    let group_caller_key: Option<String> = calls.iter()
+        .find(|(_, s)| s.caller == uid && s.target.is_multi_party())
+        .map(|(k, _)| k.clone());
+
+    if let Some(group_id) = group_caller_key {
+        let session = calls.remove(&group_id).unwrap();
+        let members = super::group_call::members_of(&state, &session).await;
         drop(calls);
+        stats::ended(&state.history, &session.call_id, CallOutcome::Dropped).await;
 
         let users = state.users.read().await;
-        if let Some(cs) = users.get(&caller_id) {
-            for sid in &cs.socket_ids {
-                if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                    let _ = peer.emit(event::CALL_ENDED,
-                        &CallEndedPayload { reason: format!("'{uid}' disconnected") });
+        for member_id in &members {
+            if member_id == &uid { continue; }
+            if let Some(ms) = users.get(member_id) {
+                for sid in &ms.socket_ids {
+                    if let Some(peer) = socket.broadcast().get_socket(*sid) {
+                        let _ = peer.emit(event::GROUP_CALL_ENDED,
+                            &GroupCallEndedPayload {
+                                group_id: group_id.clone(),
+                                reason: format!("'{uid}' disconnected"),
+                            });
+                    }
                 }
             }
         }
+        // Dropping session aborts the ring-timeout task
     } else {
-        // Case 2: user was the caller in a 1-to-1 call (call is keyed by callee's uid)
-        let callee_key: Option<String> = calls.iter()
-            .find(|(_, s)| s.caller == uid && matches!(s.target, CallTarget::User(_)))
+        // Case 2: user was a non-caller participant in an active group call
+        let group_participant_key: Option<String> = calls.iter()
+            .find(|(_, s)| {
+                s.target.is_multi_party()
+                    && s.caller != uid
+                    && s.participants.contains(&uid)
+            })
             .map(|(k, _)| k.clone());
 
-        if let Some(callee_id) = callee_key {
-            calls.remove(&callee_id);
-            drop(calls);
+        if let Some(group_id) = group_participant_key {
+            let session = calls.get_mut(&group_id).unwrap();
 
-            let users = state.users.read().await;
-            if let Some(cs) = users.get(&callee_id) {
-                for sid in &cs.socket_ids {
-                    if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                        let _ = peer.emit(event::CALL_CANCELLED,
-                            &CallCancelledPayload { by: uid.clone() });
-                    }
-                }
+            session.participants.retain(|p| p != &uid);
+
+            // Remaining real participants (strip sentinels and reject markers)
+            let remaining: Vec<String> = session.participants.iter()
+                .filter(|p| !p.starts_with('-') && !p.starts_with('@'))
+                .cloned()
+                .collect();
+
+            let ended = remaining.is_empty().then(|| calls.remove(&group_id)).flatten();
+            drop(calls);
+            if let Some(session) = ended {
+                stats::ended(&state.history, &session.call_id, CallOutcome::Dropped).await;
             }
-        } else {
-            // Case 3: user was the initiator of a group call (call is keyed by group_id)
-            let group_caller_key: Option<String> = calls.iter()
-                .find(|(_, s)| s.caller == uid && matches!(s.target, CallTarget::Group(_)))
-                .map(|(k, _)| k.clone());
-
-            if let Some(group_id) = group_caller_key {
-                let _session = calls.remove(&group_id).unwrap();
-                drop(calls);
-
-                let groups = state.groups.read().await;
-                let users  = state.users.read().await;
-                if let Some(group) = groups.get(&group_id) {
-                    for member_id in &group.members {
-                        if member_id == &uid { continue; }
-                        if let Some(ms) = users.get(member_id) {
-                            for sid in &ms.socket_ids {
-                                if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                                    let _ = peer.emit(event::GROUP_CALL_ENDED,
-                                        &GroupCallEndedPayload {
-                                            group_id: group_id.clone(),
-                                            reason: format!("'{uid}' disconnected"),
-                                        });
-                                }
-                            }
-                        }
-                    }
-                }
-                // Dropping _session aborts the ring-timeout task
-            } else {
-                // Case 4: user was a non-caller participant in an active group call
-                // Group calls are keyed by group_id not uid, so Case 1 never catches this
-                let group_participant_key: Option<String> = calls.iter()
-                    .find(|(_, s)| {
-                        matches!(s.target, CallTarget::Group(_))
-                            && s.caller != uid
-                            && s.participants.contains(&uid)
-                    })
-                    .map(|(k, _)| k.clone());
-
-                if let Some(group_id) = group_participant_key {
-                    let session = calls.get_mut(&group_id).unwrap();
-
-                    session.participants.retain(|p| p != &uid);
-
-                    // Remaining real participants (strip sentinels and reject markers)
-                    let remaining: Vec<String> = session.participants.iter()
-                        .filter(|p| !p.starts_with('-') && !p.starts_with('@'))
-                        .cloned()
-                        .collect();
-
-                    if remaining.is_empty() {
-                        calls.remove(&group_id);
-                    }
 
-                    drop(calls);
-
-                    let users = state.users.read().await;
-                    let left = GroupMemberLeftPayload {
-                        group_id: group_id.clone(),
-                        user_id:  uid.clone(),
-                    };
-
-                    for participant_id in &remaining {
-                        if let Some(ms) = users.get(participant_id) {
-                            for sid in &ms.socket_ids {
-                                if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                                    let _ = peer.emit(event::GROUP_MEMBER_LEFT, &left);
-                                }
-                            }
+            let users = state.users.read().await;
+            let left = GroupMemberLeftPayload {
+                group_id: group_id.clone(),
+                user_id:  uid.clone(),
+            };
+
+            for participant_id in &remaining {
+                if let Some(ms) = users.get(participant_id) {
+                    for sid in &ms.socket_ids {
+                        if let Some(peer) = socket.broadcast().get_socket(*sid) {
+                            let _ = peer.emit(event::GROUP_MEMBER_LEFT, &left);
                         }
                     }
-                } else {
-                    drop(calls); // No active call — release lock
                 }
             }
+        } else {
+            drop(calls); // No active call — release lock
         }
     }
 
@@ -177,4 +168,40 @@ pub async fn on_disconnect(socket: SocketRef, State(state): State<AppState>) {
     }
 
     info!("[-] '{uid}' fully offline");
-}
\ No newline at end of file
+}
+
+/// Tells the other party that `uid` dropped out of the 1-to-1 `session`
+/// (already removed from CallMap under `key`), and `uid`'s remaining tabs
+/// that it is gone.
+async fn drop_dm(socket: &SocketRef, state: &AppState, uid: &str, key: &str, session: CallSession) {
+    stats::ended(&state.history, &session.call_id, CallOutcome::Dropped).await;
+    let reason  = format!("'{uid}' disconnected");
+    let other   = routing::other_party(&session, uid);
+    let waiting = key == waiting_key(session.target.id());
+
+    if session.status == CallStatus::Held {
+        let room  = session_room_name(&session);
+        let media = state.media.clone();
+        tokio::spawn(async move { media.delete_room(&room).await });
+    }
+
+    match session.status {
+        // The other party keeps whatever call they are on
+        CallStatus::Held => {
+            routing::emit_to_side(socket, &state.users, &session, &other, event::WAITING_CALL_ENDED,
+                &WaitingCallEndedPayload { call_id: session.call_id.clone(), reason }).await;
+        }
+        CallStatus::Ringing if session.caller == uid && waiting => {
+            routing::emit_to_side(socket, &state.users, &session, &other, event::WAITING_CALL_ENDED,
+                &WaitingCallEndedPayload { call_id: session.call_id.clone(), reason }).await;
+        }
+        // Still ringing the callee: every tab of theirs dismisses it
+        CallStatus::Ringing if session.caller == uid => {
+            routing::emit_to_side(socket, &state.users, &session, &other, event::CALL_CANCELLED,
+                &CallCancelledPayload { by: uid.to_owned() }).await;
+        }
+        // Under way, or ringing us: the other side's call is over
+        _ => routing::end_for(socket, &state.users, &session, &other, &reason).await,
+    }
+    routing::release_elsewhere(socket, &state.users, &session, uid).await;
+}
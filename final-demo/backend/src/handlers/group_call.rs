@@ -546,24 +546,25 @@
 
 use socketioxide::extract::{Data, SocketRef, State};
 use socketioxide::socket::Sid;
-use std::sync::Arc;
+use std::{collections::hash_map::Entry, sync::Arc};
 use tracing::{info, warn};
+use uuid::Uuid;
 
 use crate::{
-    fcm::send_fcm_notification,
-    handlers::group::broadcast_to_members,
-    livekit::{create_room, delete_room, generate_token, group_room_name},
+    fcm::Push,
+    push::{targets_for, PushTarget},
+    media::{group_room_name, session_room_name, ParticipantRole},
+    stats,
     types::{
-        event, AppState, CallMap, CallSession, CallStatus, CallTarget,
+        event, AppState, CallMap, CallOutcome, CallRecord, CallSession, CallStatus, CallTarget,
         ErrorPayload, GroupAcceptPayload, GroupCallEndedPayload,
-        GroupCallPayload, GroupCutPayload, GroupIncomingCallPayload,
+        GroupCallPayload, GroupCallQueryPayload, GroupCallRingPayload, GroupCallStatusPayload,
+        GroupCutPayload, GroupIncomingCallPayload,
         GroupLiveKitTokenPayload, GroupMemberJoinedPayload, GroupMemberLeftPayload,
-        GroupRejectPayload, UserMap, RING_TIMEOUT_SEC,
+        GroupRejectPayload, JoinGroupCallPayload, RingTimeouts, UserMap,
     },
 };
 
-const EV_GROUP_LIVEKIT_TOKEN: &str = "group_livekit_token";
-
 // ── group_call ────────────────────────────────────────────────────────────────
 
 pub async fn on_group_call(
@@ -571,74 +572,145 @@ pub async fn on_group_call(
     State(state): State<AppState>,
     Data(payload): Data<GroupCallPayload>,
 ) {
-    let GroupCallPayload { from, group_id, video } = payload;
-    let socket_id: Sid = socket.id;
+    let GroupCallPayload { from, group_id, video, ring_timeout_sec } = payload;
 
-    if !identity_matches(&state, socket_id, &from).await {
+    if !identity_matches(&state, socket.id, &from).await {
         emit_error(&socket, "Identity mismatch");
         return;
     }
 
+    let ring_sec = RingTimeouts::clamp(ring_timeout_sec, state.ring.group_sec);
+    if let Err(message) = start_group_call(&state, &socket, &from, &group_id, video.unwrap_or(false), ring_sec).await {
+        emit_error(&socket, &message);
+    }
+}
+
+/// Starts a call in `group_id` placed by `from` from the tab `socket`: room,
+/// caller credentials, GROUP_INCOMING_CALL and a push to every other member,
+/// and the Ringing session, which ends unanswered after `ring_sec`. Shared by
+/// `group_call` and scheduled calls. Returns the new call id, or why the call
+/// could not start.
+pub async fn start_group_call(
+    state:    &AppState,
+    socket:   &SocketRef,
+    from:     &str,
+    group_id: &str,
+    video:    bool,
+    ring_sec: u64,
+) -> Result<String, String> {
+    if crate::shutdown::draining(state) {
+        return Err("Server is restarting — try again shortly".into());
+    }
+    if !state.media.multi_party() {
+        return Err("Group calls need a media server".into());
+    }
+    let (from, group_id) = (from.to_owned(), group_id.to_owned());
+    let socket_id: Sid = socket.id;
+
     let (group_name, members) = {
         let groups = state.groups.read().await;
         let Some(group) = groups.get(&group_id) else {
-            emit_error(&socket, &format!("Group '{group_id}' not found"));
-            return;
+            return Err(format!("Group '{group_id}' not found"));
         };
         if !group.members.contains(&from) {
-            emit_error(&socket, "You are not a member of this group");
-            return;
+            return Err("You are not a member of this group".into());
         }
         (group.name.clone(), group.members.clone())
     };
 
+    let other_members: Vec<String> = members.iter()
+        .filter(|m| **m != from)
+        .cloned()
+        .collect();
+    let non_caller_count = other_members.len();
+    let call_id = Uuid::new_v4().to_string();
+    let target  = CallTarget::Group(group_id.clone());
+
+    // Take the group's key before the room exists or anyone rings; the ring
+    // timeout replaces the placeholder handle once it runs
     {
-        let calls = state.calls.read().await;
+        let mut calls = state.calls.write().await;
         let busy = calls.values().any(|s| {
             (s.caller == from || s.participants.contains(&from))
                 && s.status == CallStatus::Active
         });
         if busy {
-            emit_error(&socket, "You are already on a call");
-            return;
-        }
-        if calls.contains_key(&group_id) {
-            emit_error(&socket, "This group already has an active call");
-            return;
+            return Err("You are already on a call".into());
         }
+        let Entry::Vacant(slot) = calls.entry(group_id.clone()) else {
+            return Err("This group already has an active call".into());
+        };
+        slot.insert(CallSession {
+            call_id:          call_id.clone(),
+            caller:           from.clone(),
+            target:           target.clone(),
+            status:           CallStatus::Ringing,
+            caller_socket_id: socket_id,
+            callee_socket_id: None,
+            participants:     vec![from.clone(), format!("@total:{non_caller_count}")],
+            listeners:        Vec::new(),
+            removed:          Vec::new(),
+            locked:           false,
+            invited:          Vec::new(),
+            held_by:          None,
+            video,
+            recording:        None,
+            handoff:          None,
+            _timeout_handle:  Arc::new(tokio::spawn(async {}).abort_handle()),
+        });
     }
 
-    // ── LiveKit: pre-create the room so it exists before anyone tries to join ─
-    let room_name = group_room_name(&group_id);
-    let lk = state.livekit.clone();
-    let room_created = create_room(&lk, &room_name).await;
+    // ── Media: pre-create the room so it exists before anyone tries to join ───
+    let room_name = group_room_name(&group_id, &call_id);
+    let media = &state.media;
+    let room_created = media.create_room(&room_name).await;
+    let still_ours = {
+        let mut calls = state.calls.write().await;
+        let ours = calls.get(&group_id).is_some_and(|s| s.call_id == call_id);
+        if ours && !room_created { calls.remove(&group_id); }
+        ours
+    };
     if !room_created {
-        emit_error(&socket, "Failed to create call room, please try again");
-        return;
+        return Err("Failed to create call room, please try again".into());
     }
+    if !still_ours {
+        // Ended while the room was being created
+        media.delete_room(&room_name).await;
+        return Err("The call ended before it could ring".into());
+    }
+    state.history.write().await.insert(call_id.clone(),
+        CallRecord::new(&call_id, &from, &target, video));
 
     // ── Send token to the CALLER immediately so they can join right away ───────
-    if let Some(token) = generate_token(&lk, &room_name, &from) {
-        let _ = socket.emit(EV_GROUP_LIVEKIT_TOKEN, &GroupLiveKitTokenPayload {
+    if let Some(creds) = media.join_credentials(&room_name, &from, &call_id, ParticipantRole::Speaker, video) {
+        let _ = socket.emit(event::GROUP_LIVEKIT_TOKEN, &GroupLiveKitTokenPayload {
+            call_id:  call_id.clone(),
+            media:    creds.media.to_owned(),
             group_id: group_id.clone(),
             room:     room_name.clone(),
-            token,
-            url:      lk.url.clone(),
+            token:    creds.token,
+            url:      creds.url,
         });
     }
 
-    let other_members: Vec<String> = members.iter()
-        .filter(|m| **m != from)
-        .cloned()
-        .collect();
-
     let incoming = GroupIncomingCallPayload {
+        call_id:    call_id.clone(),
         from:       from.clone(),
         group_id:   group_id.clone(),
         group_name: group_name.clone(),
-        video:      video.unwrap_or(false),
+        video,
+    };
+
+    let push = Push::Call {
+        call_id: call_id.clone(),
+        from:    from.clone(),
+        to:      group_id.clone(),
+        video,
+        ring_sec,
     };
 
+    // Ring every open tab and collect every member's devices into one push batch
+    let mut fcm_targets: Vec<PushTarget> = Vec::new();
     let users_snap = state.users.read().await;
     for member_id in &other_members {
         if let Some(ms) = users_snap.get(member_id) {
@@ -647,45 +719,28 @@ pub async fn on_group_call(
                     let _ = peer.emit(event::GROUP_INCOMING_CALL, &incoming);
                 }
             }
-            if !ms.fcm_tokens.is_empty() {
-                let tokens: Vec<String> = ms.fcm_tokens.clone();
-                let (f, gid, http) = (from.clone(), group_id.clone(), state.http.clone());
-                let auth = state.auth.clone();
-                let is_video = video.unwrap_or(false);
-                tokio::spawn(async move {
-                    for token in tokens {
-                        send_fcm_notification(&token, &f, &gid, is_video, auth.as_ref(), &http).await;
-                    }
-                });
-            }
+            fcm_targets.extend(targets_for(ms, &push));
         }
     }
     drop(users_snap);
 
-    let non_caller_count = other_members.len();
+    state.push.dispatch(push, fcm_targets);
 
     let timeout_handle = spawn_group_ring_timeout(
-        from.clone(),
+        call_id.clone(),
         group_id.clone(),
+        ring_sec,
         members.clone(),
         socket.clone(),
-        state.calls.clone(),
-        state.users.clone(),
-        state.livekit.clone(),
+        state.clone(),
     );
-
-    let mut calls = state.calls.write().await;
-    calls.insert(group_id.clone(), CallSession {
-        caller:           from.clone(),
-        target:           CallTarget::Group(group_id.clone()),
-        status:           CallStatus::Ringing,
-        caller_socket_id: socket_id,
-        participants:     vec![from.clone(), format!("@total:{non_caller_count}")],
-        video:            video.unwrap_or(false),
-        _timeout_handle:  timeout_handle,
-    });
+    match state.calls.write().await.get_mut(&group_id) {
+        Some(s) if s.call_id == call_id => s._timeout_handle = timeout_handle,
+        _ => timeout_handle.abort(),   // the call is over already
+    }
 
     info!("[G~] Group call started: '{from}' → group '{group_id}' ({non_caller_count} invited)");
+    Ok(call_id)
 }
 
 // ── group_accept ──────────────────────────────────────────────────────────────
@@ -695,30 +750,56 @@ pub async fn on_group_accept(
     State(state): State<AppState>,
     Data(payload): Data<GroupAcceptPayload>,
 ) {
-    let GroupAcceptPayload { from, group_id } = payload;
-    let socket_id: Sid = socket.id;
+    let GroupAcceptPayload { from, group_id, listen_only } = payload;
 
-    if !identity_matches(&state, socket_id, &from).await {
+    if !identity_matches(&state, socket.id, &from).await {
         emit_error(&socket, "Identity mismatch");
         return;
     }
 
+    admit(&socket, &state, from, group_id, listen_only).await;
+}
+
+/// Adds `from` to the group call, hands them credentials and introduces them
+/// to everyone already in it. Shared by group_accept and join_group_call.
+#[allow(clippy::collapsible_if)]
+async fn admit(socket: &SocketRef, state: &AppState, from: String, group_id: String, listen_only: bool) {
+    let socket_id: Sid = socket.id;
+
     let mut calls = state.calls.write().await;
     let Some(session) = calls.get_mut(&group_id) else {
-        emit_error(&socket, "No active group call to accept");
+        emit_error(socket, "No active group call to accept");
         return;
     };
-    if !matches!(&session.target, CallTarget::Group(gid) if gid == &group_id) {
-        emit_error(&socket, "Call target mismatch");
+    if !(session.target.is_multi_party() && session.target.id() == group_id) {
+        emit_error(socket, "Call target mismatch");
         return;
     }
     if session.participants.contains(&from) {
         return;
     }
+    let refusal = if session.removed.contains(&from) {
+        Some("You were removed from this call")
+    } else if matches!(session.target, CallTarget::AdHoc(_)) && !session.invited.contains(&from) {
+        Some("You were not invited to this call")
+    } else if session.locked {
+        Some("The call is locked")
+    } else {
+        None
+    };
+    if let Some(reason) = refusal {
+        let _ = socket.emit(event::GROUP_CALL_ENDED,
+            &GroupCallEndedPayload { group_id: group_id.clone(), reason: reason.into() });
+        return;
+    }
 
     let reject_marker = format!("-{from}");
     session.participants.retain(|p| p != &reject_marker);
     session.participants.push(from.clone());
+    session.listeners.retain(|l| l != &from);
+    if listen_only {
+        session.listeners.push(from.clone());
+    }
 
     if session.status == CallStatus::Ringing {
         session.status = CallStatus::Active;
@@ -732,18 +813,23 @@ pub async fn on_group_accept(
         .cloned()
         .collect();
 
+    let call_id   = session.call_id.clone();
+    let video     = session.video;
+    let room_name = session_room_name(session);
     drop(calls);
+    stats::joined(&state.history, &call_id, &[&from]).await;
 
-    // ── LiveKit: generate a token for the new joiner ──────────────────────────
-    let room_name = group_room_name(&group_id);
-    let lk = &state.livekit;
+    // ── Media: credentials for the new joiner ─────────────────────────────────
+    let role = if listen_only { ParticipantRole::Listener } else { ParticipantRole::Speaker };
 
-    if let Some(token) = generate_token(lk, &room_name, &from) {
-        let _ = socket.emit(EV_GROUP_LIVEKIT_TOKEN, &GroupLiveKitTokenPayload {
+    if let Some(creds) = state.media.join_credentials(&room_name, &from, &call_id, role, video) {
+        let _ = socket.emit(event::GROUP_LIVEKIT_TOKEN, &GroupLiveKitTokenPayload {
+            call_id,
+            media:    creds.media.to_owned(),
             group_id: group_id.clone(),
             room:     room_name.clone(),
-            token,
-            url:      lk.url.clone(),
+            token:    creds.token,
+            url:      creds.url,
         });
     }
 
@@ -830,7 +916,8 @@ pub async fn on_group_reject(
             .filter(|p| p.starts_with('-'))
             .count();
 
-        reject_count >= total
+        // Only an unanswered call ends on rejections — a declined re-ring leaves it running
+        session.status == CallStatus::Ringing && reject_count >= total
     };
 
     // Dismiss ringing on all tabs of the rejecter
@@ -850,10 +937,7 @@ pub async fn on_group_reject(
 
     if all_rejected {
         // Everyone rejected — end the call and delete the room
-        end_group_call_fully(&socket, &state, &group_id, "Everyone rejected the call").await;
-        let lk = state.livekit.clone();
-        let room = group_room_name(&group_id);
-        tokio::spawn(async move { delete_room(&lk, &room).await });
+        end_group_call_fully(&socket, &state, &group_id, "Everyone rejected the call", CallOutcome::Rejected).await;
         info!("[G✗] All members rejected group call '{group_id}'");
     }
 }
@@ -873,7 +957,7 @@ pub async fn on_group_cut(
         return;
     }
 
-    let (is_caller, remaining) = {
+    let (is_caller, remaining, call_id, room, all_members) = {
         let mut calls = state.calls.write().await;
         let Some(session) = calls.get_mut(&group_id) else {
             let _ = socket.emit(event::GROUP_CALL_ENDED,
@@ -890,25 +974,23 @@ pub async fn on_group_cut(
             .cloned()
             .collect();
 
+        let call_id     = session.call_id.clone();
+        let room        = session_room_name(session);
+        let all_members = members_of(&state, session).await;
         if is_caller || remaining.is_empty() {
             calls.remove(&group_id);
         }
-        (is_caller, remaining)
+        (is_caller, remaining, call_id, room, all_members)
     };
 
-    let users  = state.users.read().await;
-    let groups = state.groups.read().await;
-
-    let all_members: Vec<String> = groups.get(&group_id)
-        .map(|g| g.members.clone())
-        .unwrap_or_default();
-    drop(groups);
+    let users = state.users.read().await;
 
     if is_caller || remaining.is_empty() {
+        stats::ended(&state.history, &call_id, CallOutcome::Completed).await;
+
         // Call fully over — delete LiveKit room
-        let lk = state.livekit.clone();
-        let room = group_room_name(&group_id);
-        tokio::spawn(async move { delete_room(&lk, &room).await });
+        let media = state.media.clone();
+        tokio::spawn(async move { media.delete_room(&room).await });
 
         for member_id in &all_members {
             if member_id == &from { continue; }
@@ -948,31 +1030,209 @@ pub async fn on_group_cut(
     }
 }
 
+// ── group_call_status ─────────────────────────────────────────────────────────
+
+pub async fn on_group_call_status(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<GroupCallQueryPayload>,
+) {
+    let GroupCallQueryPayload { from, group_id } = payload;
+
+    if !identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    if !is_group_member(&state, &group_id, &from).await {
+        emit_error(&socket, "You are not a member of this group");
+        return;
+    }
+
+    let calls = state.calls.read().await;
+    let room = calls.get(&group_id)
+        .filter(|s| s.status == CallStatus::Active)
+        .map(session_room_name);
+    let mut status = match calls.get(&group_id) {
+        Some(s) => GroupCallStatusPayload {
+            group_id:     group_id.clone(),
+            call_id:      Some(s.call_id.clone()),
+            caller:       Some(s.caller.clone()),
+            active:       s.status == CallStatus::Active,
+            video:        s.video,
+            locked:       s.locked,
+            participants: super::recording::participants_of(s),
+            connected:    None,
+        },
+        None => GroupCallStatusPayload {
+            group_id:     group_id.clone(),
+            call_id:      None,
+            caller:       None,
+            active:       false,
+            video:        false,
+            locked:       false,
+            participants: Vec::new(),
+            connected:    None,
+        },
+    };
+    drop(calls);
+
+    // Ask the media server who is actually in the room, outside the calls lock
+    if let Some(room) = room {
+        status.connected = state.media.list_participants(&room).await;
+    }
+
+    let _ = socket.emit(event::GROUP_CALL_STATUS, &status);
+}
+
+// ── join_group_call ───────────────────────────────────────────────────────────
+
+/// Late join: any group member may enter a running call, whether or not
+/// they rejected or missed the ring.
+pub async fn on_join_group_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<JoinGroupCallPayload>,
+) {
+    let JoinGroupCallPayload { from, group_id, listen_only } = payload;
+
+    if !identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    if !is_group_member(&state, &group_id, &from).await {
+        emit_error(&socket, "You are not a member of this group");
+        return;
+    }
+
+    {
+        let calls = state.calls.read().await;
+        if !calls.contains_key(&group_id) {
+            emit_error(&socket, "This group has no call to join");
+            return;
+        }
+        let busy = calls.iter().any(|(key, s)| {
+            key != &group_id && s.status == CallStatus::Active
+                && (s.caller == from || s.participants.contains(&from) || key == &from)
+        });
+        if busy {
+            emit_error(&socket, "You are already on a call");
+            return;
+        }
+    }
+
+    admit(&socket, &state, from, group_id, listen_only).await;
+}
+
+// ── group_call_ring ───────────────────────────────────────────────────────────
+
+/// Re-ring chosen members of a running call; each ring times out on its own.
+pub async fn on_group_call_ring(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<GroupCallRingPayload>,
+) {
+    let GroupCallRingPayload { from, group_id, user_ids } = payload;
+
+    if !identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let (call_id, caller, video, targets) = {
+        let calls = state.calls.read().await;
+        let Some(session) = calls.get(&group_id) else {
+            emit_error(&socket, "This group has no call to ring into");
+            return;
+        };
+        if !super::group_moderation::is_moderator(&state, session, &from).await {
+            emit_error(&socket, "Only the caller or a group admin can ring members");
+            return;
+        }
+        if session.locked {
+            emit_error(&socket, "Unlock the call to ring members");
+            return;
+        }
+        let members = state.groups.read().await
+            .get(&group_id)
+            .map(|g| g.members.clone())
+            .unwrap_or_default();
+        let targets: Vec<String> = user_ids.into_iter()
+            .filter(|u| members.contains(u))
+            .filter(|u| !session.participants.contains(u) && !session.removed.contains(u))
+            .collect();
+        (session.call_id.clone(), session.caller.clone(), session.video, targets)
+    };
+    if targets.is_empty() {
+        emit_error(&socket, "Nobody to ring — they are in the call or not in the group");
+        return;
+    }
+
+    let group_name = state.groups.read().await
+        .get(&group_id)
+        .map(|g| g.name.clone())
+        .unwrap_or_default();
+    let incoming = GroupIncomingCallPayload {
+        call_id:    call_id.clone(),
+        from:       caller.clone(),
+        group_id:   group_id.clone(),
+        group_name,
+        video,
+    };
+    let push = Push::Call {
+        call_id: call_id.clone(),
+        from:    caller,
+        to:      group_id.clone(),
+        video,
+        ring_sec: state.ring.group_sec,
+    };
+
+    let mut fcm_targets: Vec<PushTarget> = Vec::new();
+    let users = state.users.read().await;
+    for uid in &targets {
+        if let Some(ms) = users.get(uid) {
+            for sid in &ms.socket_ids {
+                if let Some(peer) = socket.broadcast().get_socket(*sid) {
+                    let _ = peer.emit(event::GROUP_INCOMING_CALL, &incoming);
+                }
+            }
+            fcm_targets.extend(targets_for(ms, &push));
+        }
+    }
+    drop(users);
+    state.push.dispatch(push, fcm_targets);
+
+    spawn_member_ring_timeout(call_id, group_id.clone(), targets.clone(), state.ring.group_sec,
+        socket.clone(), state.calls.clone(), state.users.clone());
+    info!("[G~] '{from}' re-rang {targets:?} into group call '{group_id}'");
+}
+
 // ── Ring-timeout ──────────────────────────────────────────────────────────────
 
+#[allow(clippy::collapsible_if)]
 fn spawn_group_ring_timeout(
-    caller_id: String,
+    call_id:   String,
     group_id:  String,
+    ring_sec:  u64,
     members:   Vec<String>,
     caller_socket: SocketRef,
-    calls:     CallMap,
-    users:     UserMap,
-    lk:        Arc<crate::livekit::LiveKitConfig>,
+    state:     AppState,
 ) -> Arc<tokio::task::AbortHandle> {
     let task = tokio::spawn(async move {
-        tokio::time::sleep(tokio::time::Duration::from_secs(RING_TIMEOUT_SEC)).await;
+        tokio::time::sleep(tokio::time::Duration::from_secs(ring_sec)).await;
 
-        let mut calls_w = calls.write().await;
+        let mut calls_w = state.calls.write().await;
         if let Some(s) = calls_w.get(&group_id) {
-            if s.status == CallStatus::Ringing && s.caller == caller_id {
+            if s.status == CallStatus::Ringing && s.call_id == call_id {
+                let caller_id = s.caller.clone();
                 calls_w.remove(&group_id);
                 drop(calls_w);
+                stats::ended(&state.history, &call_id, CallOutcome::Missed).await;
 
                 // Delete LiveKit room on timeout
-                let room = group_room_name(&group_id);
-                delete_room(&lk, &room).await;
+                let room = group_room_name(&group_id, &call_id);
+                state.media.delete_room(&room).await;
 
-                let users_r = users.read().await;
+                let users_r = state.users.read().await;
                 for member_id in &members {
                     if member_id == &caller_id { continue; }
                     if let Some(ms) = users_r.get(member_id) {
@@ -1001,6 +1261,46 @@ fn spawn_group_ring_timeout(
     Arc::new(task.abort_handle())
 }
 
+// Dismisses a re-ring on the tabs of members who have not joined by then.
+// The call itself is unaffected.
+pub fn spawn_member_ring_timeout(
+    call_id:  String,
+    group_id: String,
+    members:  Vec<String>,
+    ring_sec: u64,
+    socket:   SocketRef,
+    calls:    CallMap,
+    users:    UserMap,
+) {
+    tokio::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_secs(ring_sec)).await;
+
+        let missed: Vec<String> = {
+            let calls_r = calls.read().await;
+            let Some(s) = calls_r.get(&group_id).filter(|s| s.call_id == call_id) else { return; };
+            members.into_iter().filter(|m| !s.participants.contains(m)).collect()
+        };
+
+        let users_r = users.read().await;
+        for member_id in &missed {
+            if let Some(ms) = users_r.get(member_id) {
+                for sid in &ms.socket_ids {
+                    if let Some(peer) = socket.broadcast().get_socket(*sid) {
+                        let _ = peer.emit(event::GROUP_CALL_ENDED,
+                            &GroupCallEndedPayload {
+                                group_id: group_id.clone(),
+                                reason:   "No answer".into(),
+                            });
+                    }
+                }
+            }
+        }
+        if !missed.is_empty() {
+            info!("[⏱] Re-ring of {missed:?} into group call '{group_id}' timed out");
+        }
+    });
+}
+
 // ── end_group_call_fully ──────────────────────────────────────────────────────
 
 async fn end_group_call_fully(
@@ -1008,16 +1308,19 @@ async fn end_group_call_fully(
     state: &AppState,
     group_id: &str,
     reason: &str,
+    outcome: CallOutcome,
 ) {
     let mut calls = state.calls.write().await;
     let Some(session) = calls.remove(group_id) else { return; };
     let caller = session.caller.clone();
     drop(calls);
+    stats::ended(&state.history, &session.call_id, outcome).await;
 
-    let all_members: Vec<String> = {
-        let groups = state.groups.read().await;
-        groups.get(group_id).map(|g| g.members.clone()).unwrap_or_default()
-    };
+    let media = state.media.clone();
+    let room = session_room_name(&session);
+    tokio::spawn(async move { media.delete_room(&room).await });
+
+    let all_members = members_of(state, &session).await;
 
     let users  = state.users.read().await;
     let my_sid = socket.id;
@@ -1056,6 +1359,26 @@ fn emit_error(socket: &SocketRef, message: &str) {
     let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
 }
 
+/// Everyone a call concerns: the group's members, the invitees of an ad-hoc
+/// call, or both parties of a 1-to-1 call (just the caller while queued).
+pub async fn members_of(state: &AppState, session: &CallSession) -> Vec<String> {
+    match &session.target {
+        CallTarget::User(callee) => vec![session.caller.clone(), callee.clone()],
+        CallTarget::Queue(_)     => vec![session.caller.clone()],
+        CallTarget::AdHoc(_)     => session.invited.clone(),
+        CallTarget::Group(gid)   => state.groups.read().await
+            .get(gid)
+            .map(|g| g.members.clone())
+            .unwrap_or_default(),
+    }
+}
+
+async fn is_group_member(state: &AppState, group_id: &str, user_id: &str) -> bool {
+    state.groups.read().await
+        .get(group_id)
+        .is_some_and(|g| g.members.iter().any(|m| m == user_id))
+}
+
 async fn identity_matches(state: &AppState, socket_id: Sid, user_id: &str) -> bool {
     let map = state.users.read().await;
     map.get(user_id)
@@ -0,0 +1,159 @@
+// src/handlers/group_moderation.rs — Moderator controls for group calls.
+//
+// Only the caller and the group's admin (its creator, while in the call) may
+// moderate. Every action is broadcast to all participants:
+//
+// group_call_mute_participant   → server-side mute of the target's microphone
+// group_call_remove_participant → target disconnected and barred from rejoining this call
+// group_call_lock               → (un)lock the call; a locked call admits no new joiners
+
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::{info, warn};
+
+use super::recording::{emit_to_participants, participants_of};
+use crate::{
+    media::session_room_name,
+    types::{
+        event, AppState, CallSession, CallStatus, CallTarget, ErrorPayload, GroupCallEndedPayload,
+        GroupCallLockedPayload, GroupLockPayload, GroupModeratePayload,
+        GroupParticipantModeratedPayload,
+    },
+};
+
+// ── group_call_mute_participant ───────────────────────────────────────────────
+
+pub async fn on_group_call_mute_participant(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<GroupModeratePayload>,
+) {
+    let GroupModeratePayload { from, group_id, user_id } = payload;
+
+    let Some((room, participants)) = authorize(&socket, &state, &from, &group_id).await else { return; };
+    if !participants.contains(&user_id) {
+        emit_error(&socket, "That user is not in the call");
+        return;
+    }
+
+    if !state.media.mute_participant(&room, &user_id).await {
+        emit_error(&socket, "Failed to mute participant");
+        return;
+    }
+
+    emit_to_participants(&socket, &state, &participants, event::GROUP_PARTICIPANT_MUTED,
+        &GroupParticipantModeratedPayload { group_id: group_id.clone(), user_id: user_id.clone(), by: from.clone() }).await;
+    info!("[G🔇] '{from}' muted '{user_id}' in group call '{group_id}'");
+}
+
+// ── group_call_remove_participant ─────────────────────────────────────────────
+
+pub async fn on_group_call_remove_participant(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<GroupModeratePayload>,
+) {
+    let GroupModeratePayload { from, group_id, user_id } = payload;
+
+    let Some((room, participants)) = authorize(&socket, &state, &from, &group_id).await else { return; };
+    if user_id == from {
+        emit_error(&socket, "Use group_cut to leave the call");
+        return;
+    }
+    if !participants.contains(&user_id) {
+        emit_error(&socket, "That user is not in the call");
+        return;
+    }
+
+    {
+        let mut calls = state.calls.write().await;
+        let Some(session) = calls.get_mut(&group_id) else { return; };
+        if session.caller == user_id {
+            emit_error(&socket, "The caller cannot be removed");
+            return;
+        }
+        session.participants.retain(|p| p != &user_id);
+        session.listeners.retain(|l| l != &user_id);
+        if !session.removed.contains(&user_id) {
+            session.removed.push(user_id.clone());
+        }
+    }
+
+    // Barred before the disconnect, so a webhook for the leave finds nothing to clean up
+    if !state.media.remove_participant(&room, &user_id).await {
+        warn!("[G⛔] '{user_id}' removed from group call '{group_id}' but not from room '{room}'");
+    }
+
+    emit_to_participants(&socket, &state, &participants, event::GROUP_PARTICIPANT_REMOVED,
+        &GroupParticipantModeratedPayload { group_id: group_id.clone(), user_id: user_id.clone(), by: from.clone() }).await;
+    emit_to_participants(&socket, &state, std::slice::from_ref(&user_id), event::GROUP_CALL_ENDED,
+        &GroupCallEndedPayload { group_id: group_id.clone(), reason: format!("Removed from the call by {from}") }).await;
+    info!("[G⛔] '{from}' removed '{user_id}' from group call '{group_id}'");
+}
+
+// ── group_call_lock ───────────────────────────────────────────────────────────
+
+pub async fn on_group_call_lock(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<GroupLockPayload>,
+) {
+    let GroupLockPayload { from, group_id, locked } = payload;
+
+    let Some((_, participants)) = authorize(&socket, &state, &from, &group_id).await else { return; };
+
+    {
+        let mut calls = state.calls.write().await;
+        let Some(session) = calls.get_mut(&group_id) else { return; };
+        if session.locked == locked { return; }
+        session.locked = locked;
+    }
+
+    emit_to_participants(&socket, &state, &participants, event::GROUP_CALL_LOCKED,
+        &GroupCallLockedPayload { group_id: group_id.clone(), locked, by: from.clone() }).await;
+    info!("[G🔒] '{from}' {} group call '{group_id}'", if locked { "locked" } else { "unlocked" });
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// Caller, or the group admin while in the call.
+pub async fn is_moderator(state: &AppState, session: &CallSession, user_id: &str) -> bool {
+    if session.caller == user_id { return true; }
+    match &session.target {
+        CallTarget::User(_) | CallTarget::AdHoc(_) | CallTarget::Queue(_) => false,
+        CallTarget::Group(gid) => state.groups.read().await
+            .get(gid)
+            .is_some_and(|g| g.created_by == user_id && session.participants.iter().any(|p| p == user_id)),
+    }
+}
+
+/// Checks that `from` (on this socket) may moderate the active call of
+/// `group_id`; returns its room and current participants.
+async fn authorize(
+    socket:   &SocketRef,
+    state:    &AppState,
+    from:     &str,
+    group_id: &str,
+) -> Option<(String, Vec<String>)> {
+    if !super::call::identity_matches(state, socket.id, from).await {
+        emit_error(socket, "Identity mismatch");
+        return None;
+    }
+
+    let calls = state.calls.read().await;
+    let Some(session) = calls.get(group_id)
+        .filter(|s| s.target.is_multi_party() && s.target.id() == group_id)
+        .filter(|s| s.status == CallStatus::Active)
+    else {
+        emit_error(socket, "No active group call");
+        return None;
+    };
+    if !is_moderator(state, session, from).await {
+        emit_error(socket, "Only the caller or a group admin can do that");
+        return None;
+    }
+    Some((session_room_name(session), participants_of(session)))
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
@@ -0,0 +1,170 @@
+// src/handlers/invite.rs — Escalate a call by inviting another user into it.
+//
+// The first invite turns an active 1-to-1 session into an ad-hoc multi-party
+// call: it moves from the callee's key to "adhoc::{call_id}", keeps its media
+// room (so the two parties stay connected with the credentials they have) and
+// both are told via CALL_ESCALATED, on the tabs carrying it, to switch to the
+// group_* events. No Group record is created — the session's `invited` list
+// stands in for members.
+//
+// The invitee is rung like a group member (GROUP_INCOMING_CALL + push) and
+// joins with group_accept, which hands out their credentials.
+
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::info;
+
+use crate::{
+    fcm::Push,
+    push::{targets_for, PushTarget},
+    routing,
+    types::{
+        adhoc_key, event, AppState, CallEscalatedPayload, CallStatus, CallTarget, ErrorPayload,
+        GroupIncomingCallPayload, InviteToCallPayload,
+    },
+};
+
+pub async fn on_invite_to_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<InviteToCallPayload>,
+) {
+    let InviteToCallPayload { from, call_id, user_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    if user_id == from {
+        emit_error(&socket, "You are already in this call");
+        return;
+    }
+    if !state.media.multi_party() {
+        emit_error(&socket, "Calls with more than two people need a media server");
+        return;
+    }
+    if !state.users.read().await.contains_key(&user_id) {
+        emit_error(&socket, &format!("User '{user_id}' not found"));
+        return;
+    }
+
+    let mut calls = state.calls.write().await;
+
+    let Some(key) = calls.iter()
+        .find(|(_, s)| s.call_id == call_id && s.status == CallStatus::Active)
+        .map(|(k, _)| k.clone())
+    else {
+        emit_error(&socket, "No active call to invite into");
+        return;
+    };
+
+    let busy = calls.iter().any(|(k, s)| {
+        s.status == CallStatus::Active
+            && (s.caller == user_id || s.participants.contains(&user_id)
+                || (matches!(&s.target, CallTarget::User(_)) && k == &user_id))
+    });
+    if busy {
+        emit_error(&socket, &format!("'{user_id}' is already on a call"));
+        return;
+    }
+
+    let session = &calls[&key];
+    let in_call = match &session.target {
+        CallTarget::User(callee) => session.caller == from || callee == &from,
+        CallTarget::AdHoc(_)     => session.participants.contains(&from),
+        CallTarget::Queue(_)     => false,   // still waiting for an agent
+        CallTarget::Group(_)     => {
+            emit_error(&socket, "Use group_call_ring to ring group members");
+            return;
+        }
+    };
+    if !in_call {
+        emit_error(&socket, "You are not in this call");
+        return;
+    }
+    if session.locked || session.removed.contains(&user_id) {
+        emit_error(&socket, &format!("'{user_id}' cannot be invited into this call"));
+        return;
+    }
+
+    // ── First invite: 1-to-1 → ad-hoc ─────────────────────────────────────────
+    // The 1-to-1 session as it was, for routing the escalation
+    let escalated = if let CallTarget::User(callee) = session.target.clone() {
+        let mut session = calls.remove(&key).unwrap();
+        let dm = session.clone();
+        session.target       = CallTarget::AdHoc(adhoc_key(&call_id));
+        session.participants = vec![session.caller.clone(), callee.clone()];
+        session.invited      = vec![session.caller.clone(), callee];
+        calls.insert(adhoc_key(&call_id), session);
+        Some(dm)
+    } else {
+        None
+    };
+
+    let group_id = adhoc_key(&call_id);
+    let session = calls.get_mut(&group_id).unwrap();
+    session.participants.retain(|p| p != &format!("-{user_id}"));
+    if !session.invited.contains(&user_id) {
+        session.invited.push(user_id.clone());
+    }
+    let caller       = session.caller.clone();
+    let video        = session.video;
+    let participants = super::recording::participants_of(session);
+    drop(calls);
+
+    // ── Existing parties switch to group mode ─────────────────────────────────
+    if let Some(dm) = &escalated {
+        if let Some(record) = state.history.write().await.get_mut(&call_id) {
+            record.is_group = true;
+        }
+
+        let escalation = CallEscalatedPayload {
+            call_id:      call_id.clone(),
+            group_id:     group_id.clone(),
+            by:           from.clone(),
+            participants: participants.clone(),
+        };
+        routing::emit_to(&socket, &routing::bound_sockets(dm), event::CALL_ESCALATED, &escalation);
+        // No longer a call that can be moved between tabs
+        for uid in &participants {
+            routing::release_elsewhere(&socket, &state.users, dm, uid).await;
+        }
+    }
+
+    let users = state.users.read().await;
+
+    // ── Ring the invitee ──────────────────────────────────────────────────────
+    let incoming = GroupIncomingCallPayload {
+        call_id:    call_id.clone(),
+        from:       from.clone(),
+        group_id:   group_id.clone(),
+        group_name: format!("Call with {}", participants.join(", ")),
+        video,
+    };
+    let push = Push::Call {
+        call_id: call_id.clone(),
+        from:    caller,
+        to:      group_id.clone(),
+        video,
+        ring_sec: state.ring.group_sec,
+    };
+
+    let mut fcm_targets: Vec<PushTarget> = Vec::new();
+    if let Some(ms) = users.get(&user_id) {
+        for sid in &ms.socket_ids {
+            if let Some(peer) = socket.broadcast().get_socket(*sid) {
+                let _ = peer.emit(event::GROUP_INCOMING_CALL, &incoming);
+            }
+        }
+        fcm_targets.extend(targets_for(ms, &push));
+    }
+    drop(users);
+    state.push.dispatch(push, fcm_targets);
+
+    super::group_call::spawn_member_ring_timeout(call_id, group_id.clone(), vec![user_id.clone()],
+        state.ring.group_sec, socket.clone(), state.calls.clone(), state.users.clone());
+    info!("[+] '{from}' invited '{user_id}' into call '{group_id}'");
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
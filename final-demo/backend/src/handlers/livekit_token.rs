@@ -0,0 +1,89 @@
+// src/handlers/livekit_token.rs — Re-issue a LiveKit token for an ongoing call.
+//
+// Tokens live for TOKEN_TTL_SEC only. A client that needs to reconnect after
+// that asks for a fresh one; it is granted only while the user is still a
+// participant of an active session, with the same role as before. A 1-to-1
+// call's credentials only go to the tab carrying the user's side of it.
+
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::info;
+
+use crate::{
+    media::{session_room_name, ParticipantRole},
+    routing,
+    types::{
+        event, AppState, CallStatus, CallTarget, ErrorPayload, GroupLiveKitTokenPayload,
+        LiveKitTokenPayload, RefreshLiveKitTokenPayload,
+    },
+};
+
+pub async fn on_refresh_livekit_token(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<RefreshLiveKitTokenPayload>,
+) {
+    let RefreshLiveKitTokenPayload { from, group_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let calls = state.calls.read().await;
+    let session = match &group_id {
+        Some(gid) => calls.get(gid)
+            .filter(|s| s.participants.contains(&from)),
+        None => calls.values()
+            .find(|s| s.status == CallStatus::Active && matches!(&s.target, CallTarget::User(callee)
+                if callee == &from || s.caller == from)),
+    };
+    let Some(session) = session.filter(|s| s.status == CallStatus::Active) else {
+        emit_error(&socket, "You are not in an active call");
+        return;
+    };
+    if group_id.is_none() && routing::bound_socket(session, &from) != Some(socket.id) {
+        emit_error(&socket, "This call is connected on another tab");
+        return;
+    }
+
+    let role = if session.listeners.contains(&from) {
+        ParticipantRole::Listener
+    } else {
+        ParticipantRole::Speaker
+    };
+    let room_name = session_room_name(session);
+    let (call_id, video) = (session.call_id.clone(), session.video);
+    drop(calls);
+
+    let Some(creds) = state.media.join_credentials(&room_name, &from, &call_id, role, video) else {
+        emit_error(&socket, "Failed to issue a call token, please try again");
+        return;
+    };
+
+    match group_id {
+        Some(group_id) => {
+            let _ = socket.emit(event::GROUP_LIVEKIT_TOKEN, &GroupLiveKitTokenPayload {
+                call_id,
+                media:    creds.media.to_owned(),
+                group_id,
+                room:     room_name.clone(),
+                token:    creds.token,
+                url:      creds.url,
+            });
+        }
+        None => {
+            let _ = socket.emit(event::LIVEKIT_TOKEN, &LiveKitTokenPayload {
+                call_id,
+                media: creds.media.to_owned(),
+                room:  room_name.clone(),
+                token: creds.token,
+                url:   creds.url,
+            });
+        }
+    }
+    info!("[media] refreshed credentials for '{from}' in room '{room_name}'");
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
@@ -1,11 +1,22 @@
 pub mod register;       // User registration & presence
 pub mod store_fcm_token;// Save push notification token for offline delivery
+pub mod devices;        // Device registry (list / remove / stale pruning)
 pub mod call;           // Initiate a 1-to-1 call
+pub mod call_prefs;     // Per-user call preferences (DND / contacts only / leave a message)
 pub mod cancel;         // Caller cancels a ringing call
 pub mod accept;         // Callee accepts a ringing call
 pub mod reject;         // Callee rejects a ringing call
 pub mod cut_call;       // Either side ends an active call
+pub mod call_control;   // Hold / resume / blind transfer of a 1-to-1 call
+pub mod invite;         // Invite a third party — escalates a 1-to-1 into an ad-hoc group call
+pub mod livekit_token;  // Re-issue short-lived LiveKit tokens during a call
+pub mod recording;      // Start / stop server-side call recording (LiveKit Egress)
+pub mod quality;        // End-of-call quality reports (feeds GET /stats/calls)
+pub mod webrtc;         // SDP / ICE relay for the peer-to-peer media backend
 pub mod disconnect;     // Socket disconnect cleanup
 pub mod group;          // Group CRUD (create / add member / remove member)
 pub mod group_call;     // Group call lifecycle (start / accept / reject / leave)
+pub mod group_moderation; // Moderator controls in group calls (mute / remove / lock)
+pub mod schedule;       // Scheduled calls: booking, reminders and automatic start
+pub mod queue;          // Call queues: shared lines answered by a team of agents
 pub mod chat;           // 1-to-1 and group chat messaging
\ No newline at end of file
@@ -0,0 +1,59 @@
+// src/handlers/quality.rs — End-of-call quality reports.
+//
+// call_quality_report → stored on the call's history record, replacing any
+//                       earlier report from the same participant.
+//
+// Only users who took part in the call may report. The reports feed the
+// quality half of GET /stats/calls (see crate::stats).
+
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::info;
+
+use crate::types::{event, AppState, CallQualityReportPayload, ErrorPayload, QualityReport};
+
+pub async fn on_call_quality_report(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<CallQualityReportPayload>,
+) {
+    let CallQualityReportPayload { from, call_id, rtt_ms, jitter_ms, packet_loss_pct, mos, network_type } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let in_range = |v: Option<f64>, min: f64, max: f64| v.is_none_or(|v| (min..=max).contains(&v));
+    if !in_range(rtt_ms, 0.0, f64::MAX) || !in_range(jitter_ms, 0.0, f64::MAX)
+        || !in_range(packet_loss_pct, 0.0, 100.0) || !in_range(mos, 1.0, 5.0)
+    {
+        emit_error(&socket, "Quality report out of range");
+        return;
+    }
+
+    let mut history = state.history.write().await;
+    let Some(record) = history.get_mut(&call_id) else {
+        emit_error(&socket, "Unknown call");
+        return;
+    };
+    if !record.participants.contains(&from) {
+        emit_error(&socket, "You did not take part in this call");
+        return;
+    }
+
+    record.quality.retain(|q| q.user_id != from);
+    record.quality.push(QualityReport {
+        user_id:      from.clone(),
+        rtt_ms,
+        jitter_ms,
+        packet_loss_pct,
+        mos,
+        network_type: network_type.map(|n| n.trim().to_lowercase()).filter(|n| !n.is_empty()),
+        reported_at:  chrono::Utc::now(),
+    });
+    info!("[📶] '{from}' reported quality for call '{call_id}'");
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
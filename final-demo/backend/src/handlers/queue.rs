@@ -0,0 +1,501 @@
+// src/handlers/queue.rs — Call queues: shared lines answered by a team of agents.
+//
+// call_queue        → the caller joins a queue; queue_position to their tab
+// leave_queue       → the caller gives up while waiting
+// accept_queue_call → an agent takes the caller; it carries on as a 1-to-1 call
+// reject_queue_call → an agent passes; the next one is tried
+// list_queues       → queues, each with its free agents and how many wait
+//
+// A queued caller is a Ringing session keyed queue::{call_id} with target
+// CallTarget::Queue. While it waits, `invited` holds the agents it is ringing
+// and `participants` those already tried this round. Agents are offered the
+// caller at the head of the line, through INCOMING_CALL with `queue` set, as
+// the queue's strategy picks them among the available ones: members who are
+// online, take calls (no DND), are not on a call and are not ringing for
+// anything else. spawn_queue_dispatcher ticks every second to move
+// unanswered offers on and to note which agents are busy (longest idle).
+//
+// A caller overflows when the queue is full, when none of its agents is
+// online, or after `max_wait_sec` (the session's timeout task): the queue's
+// `overflow_to` user is rung as a new 1-to-1 call (CALL_TRANSFERRED), or,
+// without one, the call ends.
+
+use std::{collections::HashMap, sync::Arc};
+
+use socketioxide::{
+    extract::{Data, SocketRef, State},
+    socket::Sid,
+    SocketIo,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::{accept::connect, call::ring};
+use crate::{
+    routing::{self, Sockets},
+    stats,
+    types::{
+        event, queue_key, waiting_key, AppState, CallCancelledPayload, CallEndedPayload,
+        CallOutcome, CallQueuePayload, CallRecord, CallSession, CallStatus, CallTarget,
+        CallTransferredPayload, ErrorPayload, IncomingCallPayload, ListQueuesPayload, QueueCallPayload,
+        QueueDef, QueueInfo, QueuePositionPayload, QueuesPayload, UserState,
+    },
+};
+
+const TICK_SEC: u64 = 1;
+
+// ── call_queue ────────────────────────────────────────────────────────────────
+
+pub async fn on_call_queue(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<CallQueuePayload>,
+) {
+    let CallQueuePayload { from, queue_id, video } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    if crate::shutdown::draining(&state) {
+        emit_error(&socket, "Server is restarting — try again shortly");
+        return;
+    }
+    let Some(def) = state.queues.get(&queue_id) else {
+        emit_error(&socket, &format!("Unknown queue '{queue_id}'"));
+        return;
+    };
+
+    {
+        let calls = state.calls.read().await;
+        if super::call::on_active_call(&calls, &from) {
+            emit_error(&socket, "You are already on a call");
+            return;
+        }
+        if calls.values().any(|s| matches!(s.target, CallTarget::Queue(_)) && s.caller == from) {
+            emit_error(&socket, "You are already waiting in a queue");
+            return;
+        }
+    }
+
+    let call_id = Uuid::new_v4().to_string();
+    let target  = CallTarget::Queue(queue_id.clone());
+    let video   = video.unwrap_or(false);
+    state.history.write().await.insert(call_id.clone(),
+        CallRecord::new(&call_id, &from, &target, video));
+
+    let session = CallSession {
+        call_id:          call_id.clone(),
+        caller:           from.clone(),
+        target,
+        status:           CallStatus::Ringing,
+        caller_socket_id: socket.id,
+        callee_socket_id: None,
+        participants:     Vec::new(),
+        listeners:        Vec::new(),
+        removed:          Vec::new(),
+        locked:           false,
+        invited:          Vec::new(),
+        held_by:          None,
+        video,
+        recording:        None,
+        handoff:          None,
+        _timeout_handle:  spawn_max_wait(call_id.clone(), def.max_wait_sec, socket.clone(), state.clone()),
+    };
+
+    let staffed = {
+        let users = state.users.read().await;
+        def.members.iter().any(|m| *m != from && users.get(m).is_some_and(UserState::is_online))
+    };
+    if !staffed {
+        overflow(&state, &socket, session, def, &format!("Nobody is answering '{}' right now", def.name)).await;
+        return;
+    }
+    let Some(position) = state.queues.join(&queue_id, &call_id).await else {
+        overflow(&state, &socket, session, def, &format!("'{}' is full", def.name)).await;
+        return;
+    };
+
+    state.calls.write().await.insert(queue_key(&call_id), session);
+    let _ = socket.emit(event::QUEUE_POSITION, &QueuePositionPayload {
+        call_id:  call_id.clone(),
+        queue_id: queue_id.clone(),
+        position,
+        waiting:  position,
+    });
+    info!("[☎] '{from}' queued in '{queue_id}' at position {position}");
+
+    dispatch(&socket, &state, def).await;
+}
+
+// ── leave_queue ───────────────────────────────────────────────────────────────
+
+pub async fn on_leave_queue(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<QueueCallPayload>,
+) {
+    let QueueCallPayload { from, call_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let key = queue_key(&call_id);
+    let mut calls = state.calls.write().await;
+    if calls.get(&key).is_none_or(|s| s.caller != from) {
+        emit_error(&socket, "You are not waiting in that queue");
+        return;
+    }
+    let session = calls.remove(&key).unwrap();
+    drop(calls);
+
+    withdraw(&socket, &state, &session).await;
+    info!("[☎] '{from}' left queue '{}'", session.target.id());
+}
+
+// ── accept_queue_call ─────────────────────────────────────────────────────────
+
+pub async fn on_accept_queue_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<QueueCallPayload>,
+) {
+    let QueueCallPayload { from, call_id } = payload;   // from = agent
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let key = queue_key(&call_id);
+    let mut calls = state.calls.write().await;
+    if !calls.get(&key).is_some_and(|s| s.invited.contains(&from)) {
+        emit_error(&socket, "This call is no longer waiting for you");
+        return;
+    }
+    if calls.contains_key(&from) || super::call::on_active_call(&calls, &from) {
+        emit_error(&socket, "Finish your current call first");
+        return;
+    }
+
+    // From here on it is an ordinary 1-to-1 call with the agent
+    let mut session = calls.remove(&key).unwrap();
+    let queue_id = session.target.id().to_owned();
+    let others: Vec<String> = session.invited.drain(..).filter(|a| *a != from).collect();
+    session.participants.clear();
+    session.target           = CallTarget::User(from.clone());
+    session.status           = CallStatus::Active;
+    session.callee_socket_id = Some(socket.id);
+    calls.insert(from.clone(), session.clone());
+    drop(calls);
+
+    state.queues.leave(&queue_id, &call_id).await;
+    state.queues.busy(&from).await;
+    stats::joined(&state.history, &call_id, &[&from]).await;
+
+    let caller = session.caller.clone();
+    emit_to_agents(&socket, &state, &others, None, event::CALL_ENDED,
+        &CallEndedPayload { reason: format!("Answered by '{from}'") }).await;
+    connect(&socket, &state, &session, &from, &caller).await;
+    stats::taken_by(&state.history, &call_id, &from).await;
+    send_positions(&socket, &state, &queue_id).await;
+
+    info!("[✓] '{from}' took '{caller}' from queue '{queue_id}'");
+}
+
+// ── reject_queue_call ─────────────────────────────────────────────────────────
+
+pub async fn on_reject_queue_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<QueueCallPayload>,
+) {
+    let QueueCallPayload { from, call_id } = payload;   // from = agent
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let mut calls = state.calls.write().await;
+    let Some(session) = calls.get_mut(&queue_key(&call_id)).filter(|s| s.invited.contains(&from)) else {
+        emit_error(&socket, "No queued call to reject");
+        return;
+    };
+    session.invited.retain(|a| *a != from);
+    let queue_id = session.target.id().to_owned();
+    drop(calls);
+
+    emit_to_agents(&socket, &state, std::slice::from_ref(&from), Some(socket.id), event::CALL_ENDED,
+        &CallEndedPayload { reason: "Rejected on another tab".into() }).await;
+    info!("[✗] '{from}' passed on a caller in queue '{queue_id}'");
+
+    if let Some(def) = state.queues.get(&queue_id) {
+        dispatch(&socket, &state, def).await;
+    }
+}
+
+// ── list_queues ───────────────────────────────────────────────────────────────
+
+pub async fn on_list_queues(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<ListQueuesPayload>,
+) {
+    let ListQueuesPayload { from } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let mut queues = Vec::new();
+    for def in state.queues.all() {
+        let waiting = state.queues.waiting(&def.queue_id).await.len();
+        let available = {
+            let users = state.users.read().await;
+            let calls = state.calls.read().await;
+            def.members.iter().filter(|m| available(&users, &calls, m, &from)).cloned().collect()
+        };
+        queues.push(QueueInfo {
+            queue_id:  def.queue_id.clone(),
+            name:      def.name.clone(),
+            strategy:  def.strategy,
+            members:   def.members.clone(),
+            available,
+            waiting,
+        });
+    }
+    queues.sort_by(|a, b| a.queue_id.cmp(&b.queue_id));
+    let _ = socket.emit(event::QUEUES, &QueuesPayload { queues });
+}
+
+// ── Dispatcher ────────────────────────────────────────────────────────────────
+
+/// Every tick: agents on a call are marked busy, offers that rang out are
+/// taken back from their agents, and every queue is dispatched again.
+pub fn spawn_queue_dispatcher(state: AppState, io: SocketIo) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(tokio::time::Duration::from_secs(TICK_SEC));
+        loop {
+            tick.tick().await;
+
+            let busy: Vec<String> = state.calls.read().await.values()
+                .filter(|s| s.status != CallStatus::Ringing)
+                .flat_map(|s| std::iter::once(s.caller.clone())
+                    .chain(std::iter::once(s.target.id().to_owned()))
+                    .chain(s.participants.iter().cloned()))
+                .collect();
+            for agent in busy {
+                state.queues.busy(&agent).await;
+            }
+
+            for def in state.queues.all() {
+                for call_id in state.queues.waiting(&def.queue_id).await {
+                    if !state.queues.offer_expired(&call_id, def.agent_ring_sec).await { continue; }
+                    let missed = state.calls.write().await.get_mut(&queue_key(&call_id))
+                        .map(|s| std::mem::take(&mut s.invited))
+                        .unwrap_or_default();
+                    emit_to_agents(&io, &state, &missed, None, event::CALL_ENDED,
+                        &CallEndedPayload { reason: "No answer".into() }).await;
+                }
+                dispatch(&io, &state, def).await;
+            }
+        }
+    });
+}
+
+/// Offers the callers waiting in `def` to its available agents, head of the
+/// line first. A caller already ringing someone is left alone; once every
+/// agent has been tried, a new round starts.
+async fn dispatch(via: &impl Sockets, state: &AppState, def: &QueueDef) {
+    for call_id in state.queues.waiting(&def.queue_id).await {
+        let key = queue_key(&call_id);
+        let users = state.users.read().await;
+        let mut calls = state.calls.write().await;
+
+        let Some(session) = calls.get(&key).filter(|s| s.invited.is_empty()) else { continue; };
+        let caller = session.caller.clone();
+        let free: Vec<String> = def.members.iter()
+            .filter(|m| available(&users, &calls, m, &caller))
+            .cloned()
+            .collect();
+        if free.is_empty() { continue; }
+
+        let untried: Vec<String> = free.iter().filter(|m| !session.participants.contains(m)).cloned().collect();
+        let new_round = untried.is_empty();
+        let agents = state.queues.pick(def, if new_round { &free } else { &untried }).await;
+        if agents.is_empty() { continue; }
+
+        let session = calls.get_mut(&key).unwrap();
+        if new_round { session.participants.clear(); }
+        session.participants.extend(agents.iter().cloned());
+        session.invited = agents.clone();
+        let incoming = IncomingCallPayload {
+            call_id: call_id.clone(),
+            from:    caller.clone(),
+            video:   session.video,
+            waiting: false,
+            queue:   Some(def.queue_id.clone()),
+        };
+        drop(calls);
+        drop(users);
+
+        state.queues.offered(&call_id).await;
+        emit_to_agents(via, state, &agents, None, event::INCOMING_CALL, &incoming).await;
+        info!("[☎] offering '{caller}' in '{}' to {agents:?}", def.queue_id);
+    }
+}
+
+/// Whether `agent` can be offered a queued call from `caller` right now.
+fn available(users: &HashMap<String, UserState>, calls: &HashMap<String, CallSession>, agent: &str, caller: &str) -> bool {
+    agent != caller
+        && users.get(agent).is_some_and(|u| u.is_online() && u.call_prefs.refusal(agent, caller).is_none())
+        && !calls.contains_key(agent)
+        && !calls.contains_key(&waiting_key(agent))
+        && !calls.values().any(|s| match s.status {
+            CallStatus::Ringing => s.caller == agent
+                || (matches!(s.target, CallTarget::Queue(_)) && s.invited.iter().any(|a| a == agent)),
+            _ => s.caller == agent || s.target.id() == agent || s.participants.iter().any(|p| p == agent),
+        })
+}
+
+// ── Timeout / overflow ────────────────────────────────────────────────────────
+
+// Spawns a task that fires after `max_wait_sec`.
+// If the caller is still queued by then, they are taken out of the line and overflowed.
+fn spawn_max_wait(
+    call_id: String,
+    max_wait_sec: u64,
+    caller_socket: SocketRef,
+    state: AppState,
+) -> Arc<tokio::task::AbortHandle> {
+    let task = tokio::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_secs(max_wait_sec)).await;
+
+        let Some(session) = state.calls.write().await.remove(&queue_key(&call_id)) else { return; };
+        let queue_id = session.target.id().to_owned();
+        state.queues.leave(&queue_id, &call_id).await;
+        emit_to_agents(&caller_socket, &state, &session.invited, None, event::CALL_CANCELLED,
+            &CallCancelledPayload { by: session.caller.clone() }).await;
+        send_positions(&caller_socket, &state, &queue_id).await;
+
+        warn!("[⏱] '{}' waited out queue '{queue_id}'", session.caller);
+        if let Some(def) = state.queues.get(&queue_id) {
+            overflow(&state, &caller_socket, session, def, "No agent answered").await;
+        }
+    });
+    Arc::new(task.abort_handle())
+}
+
+/// Sends the caller of `session` (no longer queued) to the queue's
+/// `overflow_to` user, or ends their call with `reason`.
+async fn overflow(state: &AppState, caller_socket: &SocketRef, session: CallSession, def: &QueueDef, reason: &str) {
+    stats::ended(&state.history, &session.call_id, CallOutcome::Missed).await;
+    let (caller, video) = (session.caller.clone(), session.video);
+    drop(session);
+
+    if let Some(to) = def.overflow_to.as_deref().filter(|to| *to != caller) {
+        match ring(state, caller_socket, &caller, to, video, state.ring.direct_sec).await {
+            Ok(call_id) => {
+                let _ = caller_socket.emit(event::CALL_TRANSFERRED, &CallTransferredPayload {
+                    call_id,
+                    by: def.queue_id.clone(),
+                    to: to.to_owned(),
+                });
+                info!("[☎] '{caller}' overflowed from '{}' to '{to}' — {reason}", def.queue_id);
+                return;
+            }
+            Err(e) => warn!("[☎] overflow of '{}' to '{to}' failed: {e}", def.queue_id),
+        }
+    }
+    let _ = caller_socket.emit(event::CALL_ENDED, &CallEndedPayload { reason: reason.to_owned() });
+    info!("[☎] '{caller}' turned away from '{}' — {reason}", def.queue_id);
+}
+
+// ── Disconnect ────────────────────────────────────────────────────────────────
+
+/// Cleans up after `uid` closed the tab `sid`: a caller queued from that tab
+/// leaves the line, and once `uid` is fully offline, offers ringing them are
+/// taken back.
+pub async fn on_tab_closed(socket: &SocketRef, state: &AppState, uid: &str, sid: Sid, fully_offline: bool) {
+    let mut calls = state.calls.write().await;
+    let keys: Vec<String> = calls.iter()
+        .filter(|(_, s)| matches!(s.target, CallTarget::Queue(_)) && s.caller == uid && s.caller_socket_id == sid)
+        .map(|(k, _)| k.clone())
+        .collect();
+    let gone: Vec<CallSession> = keys.iter().filter_map(|k| calls.remove(k)).collect();
+
+    let mut queues = Vec::new();
+    if fully_offline {
+        for session in calls.values_mut().filter(|s| matches!(s.target, CallTarget::Queue(_))) {
+            if session.invited.iter().any(|a| a == uid) {
+                session.invited.retain(|a| a != uid);
+                queues.push(session.target.id().to_owned());
+            }
+        }
+    }
+    drop(calls);
+
+    for session in gone {
+        withdraw(socket, state, &session).await;
+        info!("[-] '{uid}' dropped out of queue '{}'", session.target.id());
+    }
+    for def in queues.iter().filter_map(|q| state.queues.get(q)) {
+        dispatch(socket, state, def).await;
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// Takes the caller of `session` (already out of CallMap) out of the line:
+/// the agents it rang stop ringing, and those behind it move up.
+async fn withdraw(via: &impl Sockets, state: &AppState, session: &CallSession) {
+    let queue_id = session.target.id();
+    state.queues.leave(queue_id, &session.call_id).await;
+    stats::ended(&state.history, &session.call_id, CallOutcome::Cancelled).await;
+    emit_to_agents(via, state, &session.invited, None, event::CALL_CANCELLED,
+        &CallCancelledPayload { by: session.caller.clone() }).await;
+    send_positions(via, state, queue_id).await;
+}
+
+/// QUEUE_POSITION to the tab of every caller waiting in `queue_id`.
+async fn send_positions(via: &impl Sockets, state: &AppState, queue_id: &str) {
+    let line = state.queues.waiting(queue_id).await;
+    let calls = state.calls.read().await;
+    for (i, call_id) in line.iter().enumerate() {
+        let Some(session) = calls.get(&queue_key(call_id)) else { continue; };
+        routing::emit_to(via, &[session.caller_socket_id], event::QUEUE_POSITION, &QueuePositionPayload {
+            call_id:  call_id.clone(),
+            queue_id: queue_id.to_owned(),
+            position: i + 1,
+            waiting:  line.len(),
+        });
+    }
+}
+
+/// Sends `ev` to every tab of `agents`, bar `skip`.
+async fn emit_to_agents<T: serde::Serialize>(
+    via:     &impl Sockets,
+    state:   &AppState,
+    agents:  &[String],
+    skip:    Option<Sid>,
+    ev:      &'static str,
+    payload: &T,
+) {
+    let sids: Vec<Sid> = {
+        let users = state.users.read().await;
+        agents.iter()
+            .filter_map(|a| users.get(a))
+            .flat_map(|u| u.socket_ids.iter().copied())
+            .filter(|sid| Some(*sid) != skip)
+            .collect()
+    };
+    routing::emit_to(via, &sids, ev, payload);
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
@@ -0,0 +1,331 @@
+// src/handlers/recording.rs — Server-side call recording through LiveKit Egress.
+//
+// start_recording / stop_recording may be sent by the caller, or for group
+// calls by the group's creator. Every participant receives recording_started /
+// recording_stopped so nobody is recorded without being told. Each recording is
+// kept in the call history and served by GET /recordings/:egress_id.
+
+use chrono::Utc;
+use serde::Serialize;
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::{info, warn};
+
+use crate::{
+    media::session_room_name,
+    types::{
+        event, AppState, CallSession, CallStatus, CallTarget, ErrorPayload, Recording,
+        RecordingPayload, RecordingStartedPayload, RecordingStoppedPayload,
+    },
+};
+
+// ── start_recording ───────────────────────────────────────────────────────────
+
+pub async fn on_start_recording(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<RecordingPayload>,
+) {
+    let RecordingPayload { from, call_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let (egress_id, participants) = match start_call_recording(&state, &call_id, &from).await {
+        Ok(started) => started,
+        Err(msg)    => { emit_error(&socket, msg); return; }
+    };
+
+    let started = RecordingStartedPayload { call_id: call_id.clone(), egress_id, by: from.clone() };
+    emit_to_participants(&socket, &state, &participants, event::RECORDING_STARTED, &started).await;
+    info!("[rec] '{from}' started recording call '{call_id}'");
+}
+
+/// Starts an Egress recording of `call_id` on behalf of `from` and adds it to
+/// the call history; returns the egress id and the participants to notify.
+pub async fn start_call_recording(
+    state:   &AppState,
+    call_id: &str,
+    from:    &str,
+) -> Result<(String, Vec<String>), &'static str> {
+    let (room_name, video) = {
+        let calls = state.calls.read().await;
+        let session = calls.values().find(|s| s.call_id == call_id).ok_or("No such call")?;
+        if session.status != CallStatus::Active {
+            return Err("The call has not been answered yet");
+        }
+        if !super::group_moderation::is_moderator(state, session, from).await {
+            return Err("Only the caller or a group admin can record");
+        }
+        if session.recording.is_some() {
+            return Err("This call is already being recorded");
+        }
+        (session_room_name(session), session.video)
+    };
+
+    let ext  = if video { "mp4" } else { "ogg" };
+    let file = format!("{call_id}-{}.{ext}", Utc::now().format("%Y%m%dT%H%M%S"));
+    let egress_id = state.media.start_recording(&room_name, &file, video).await
+        .ok_or("Failed to start recording, please try again")?;
+
+    // The lock was released during the Egress request — the call may have ended
+    // or someone else may have started a recording meanwhile
+    let participants = {
+        let mut calls = state.calls.write().await;
+        match calls.values_mut().find(|s| s.call_id == call_id) {
+            Some(session) if session.recording.is_none() => {
+                session.recording = Some(egress_id.clone());
+                participants_of(session)
+            }
+            _ => {
+                drop(calls);
+                warn!("[rec] call '{call_id}' changed while starting '{egress_id}' — stopping it");
+                state.media.stop_recording(&egress_id).await;
+                return Err("The call ended or is already being recorded");
+            }
+        }
+    };
+
+    if let Some(record) = state.history.write().await.get_mut(call_id) {
+        record.recordings.push(Recording {
+            egress_id:  egress_id.clone(),
+            file,
+            started_by: from.to_owned(),
+            started_at: Utc::now(),
+            stopped_at: None,
+        });
+    }
+
+    Ok((egress_id, participants))
+}
+
+// ── stop_recording ────────────────────────────────────────────────────────────
+
+pub async fn on_stop_recording(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<RecordingPayload>,
+) {
+    let RecordingPayload { from, call_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+
+    let (egress_id, participants) = match stop_call_recording(&state, &call_id, &from).await {
+        Ok(stopped) => stopped,
+        Err(msg)    => { emit_error(&socket, msg); return; }
+    };
+
+    let stopped = RecordingStoppedPayload {
+        call_id:      call_id.clone(),
+        download_url: format!("/recordings/{egress_id}"),
+        egress_id,
+        by:           from.clone(),
+    };
+    emit_to_participants(&socket, &state, &participants, event::RECORDING_STOPPED, &stopped).await;
+    info!("[rec] '{from}' stopped recording call '{call_id}'");
+}
+
+/// Stops the recording of `call_id` on behalf of `from`; returns the egress id
+/// and the participants to notify.
+pub async fn stop_call_recording(
+    state:   &AppState,
+    call_id: &str,
+    from:    &str,
+) -> Result<(String, Vec<String>), &'static str> {
+    let (egress_id, participants) = {
+        let mut calls = state.calls.write().await;
+        let session = calls.values_mut().find(|s| s.call_id == call_id).ok_or("No such call")?;
+        if !super::group_moderation::is_moderator(state, session, from).await {
+            return Err("Only the caller or a group admin can stop the recording");
+        }
+        let egress_id = session.recording.take().ok_or("This call is not being recorded")?;
+        (egress_id, participants_of(session))
+    };
+
+    // Even if the Egress request fails the recording is reported as stopped:
+    // it ends on its own when the room is deleted
+    state.media.stop_recording(&egress_id).await;
+    mark_recording_stopped(state, call_id, &egress_id).await;
+
+    Ok((egress_id, participants))
+}
+
+/// Sets `stopped_at` on a recording in the call history, if not set yet.
+pub async fn mark_recording_stopped(state: &AppState, call_id: &str, egress_id: &str) {
+    let mut history = state.history.write().await;
+    let recording = history.get_mut(call_id)
+        .and_then(|r| r.recordings.iter_mut().find(|rec| rec.egress_id == egress_id));
+    if let Some(rec) = recording {
+        rec.stopped_at.get_or_insert_with(Utc::now);
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// Everyone currently in the call (sentinels and reject markers stripped).
+pub fn participants_of(session: &CallSession) -> Vec<String> {
+    match &session.target {
+        CallTarget::User(callee) => vec![session.caller.clone(), callee.clone()],
+        CallTarget::Queue(_)     => vec![session.caller.clone()],
+        CallTarget::Group(_) | CallTarget::AdHoc(_) => session.participants.iter()
+            .filter(|p| !p.starts_with('-') && !p.starts_with('@'))
+            .cloned()
+            .collect(),
+    }
+}
+
+pub async fn emit_to_participants<T: Serialize>(
+    socket:       &SocketRef,
+    state:        &AppState,
+    participants: &[String],
+    ev:           &'static str,
+    payload:      &T,
+) {
+    let users = state.users.read().await;
+    for uid in participants {
+        let Some(s) = users.get(uid) else { continue; };
+        for sid in &s.socket_ids {
+            if *sid == socket.id {
+                let _ = socket.emit(ev, payload);
+            } else if let Some(peer) = socket.broadcast().get_socket(*sid) {
+                let _ = peer.emit(ev, payload);
+            }
+        }
+    }
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        body::Bytes,
+        extract::{Path, State as AxumState},
+        http::{header, HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::post,
+        Router,
+    };
+    use livekit_protocol as proto;
+    use prost::Message;
+
+    use super::*;
+    use crate::{
+        media::livekit::{LiveKitBackend, LiveKitConfig},
+        settings::LiveKitSettings,
+        test_support,
+    };
+
+    /// Twirp calls the stub received: method name and protobuf body.
+    type Seen = Arc<Mutex<Vec<(String, Bytes)>>>;
+
+    /// A local Egress service that answers every call with `EG_stub`, or with
+    /// a Twirp error when `fail` is set.
+    async fn egress_stub(fail: bool) -> (String, Seen) {
+        async fn handle(
+            AxumState((seen, fail)): AxumState<(Seen, bool)>,
+            Path(method): Path<String>,
+            headers: HeaderMap,
+            body: Bytes,
+        ) -> impl IntoResponse {
+            assert!(headers.get(header::AUTHORIZATION).is_some_and(|v| v.as_bytes().starts_with(b"Bearer ")));
+            seen.lock().unwrap().push((method, body));
+            if fail {
+                return (StatusCode::INTERNAL_SERVER_ERROR, r#"{"code":"internal","msg":"no egress workers"}"#.as_bytes().to_vec());
+            }
+            (StatusCode::OK, proto::EgressInfo { egress_id: "EG_stub".into(), ..Default::default() }.encode_to_vec())
+        }
+
+        let seen: Seen = Arc::default();
+        let app = Router::new()
+            .route("/twirp/livekit.Egress/:method", post(handle))
+            .with_state((Arc::clone(&seen), fail));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, seen)
+    }
+
+    /// LiveKit backend whose room API is unreachable and whose Egress API is `egress_url`.
+    fn livekit(egress_url: &str) -> Arc<LiveKitBackend> {
+        Arc::new(LiveKitBackend::new(LiveKitConfig::from_settings(&LiveKitSettings {
+            url:        Some("ws://livekit.test".into()),
+            api_url:    Some("http://livekit.test".into()),
+            egress_url: Some(egress_url.into()),
+            api_key:    Some("devkey".into()),
+            api_secret: Some("devsecret-devsecret-devsecret-32".into()),
+            egress_output_dir: "/out/".into(),
+            ..LiveKitSettings::default()
+        })))
+    }
+
+    #[tokio::test]
+    async fn records_through_egress_and_keeps_it_in_history() {
+        let (url, seen) = egress_stub(false).await;
+        let state = test_support::state_with_media(livekit(&url));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Active).await;
+
+        let (egress_id, participants) = start_call_recording(&state, "c1", "alice").await.unwrap();
+        assert_eq!(egress_id, "EG_stub");
+        assert_eq!(participants, ["alice", "bob"]);
+
+        let (method, body) = seen.lock().unwrap()[0].clone();
+        assert_eq!(method, "StartRoomCompositeEgress");
+        let req = proto::RoomCompositeEgressRequest::decode(body).unwrap();
+        assert_eq!(req.room_name, "call::c1");
+        assert_eq!(req.layout, "grid");
+        assert!(req.audio_only);
+        let [output] = req.file_outputs.as_slice() else { panic!("expected one file output") };
+        assert_eq!(output.file_type, proto::EncodedFileType::Ogg as i32);
+        assert!(output.filepath.starts_with("/out/c1-") && output.filepath.ends_with(".ogg"), "{}", output.filepath);
+
+        {
+            let history = state.history.read().await;
+            let [rec] = history["c1"].recordings.as_slice() else { panic!("expected one recording") };
+            assert_eq!(rec.egress_id, "EG_stub");
+            assert_eq!(format!("/out/{}", rec.file), output.filepath);
+            assert_eq!(rec.started_by, "alice");
+            assert!(rec.stopped_at.is_none());
+        }
+        assert_eq!(state.calls.read().await["bob"].recording.as_deref(), Some("EG_stub"));
+        assert_eq!(start_call_recording(&state, "c1", "alice").await, Err("This call is already being recorded"));
+
+        stop_call_recording(&state, "c1", "alice").await.unwrap();
+
+        let (method, body) = seen.lock().unwrap()[1].clone();
+        assert_eq!(method, "StopEgress");
+        assert_eq!(proto::StopEgressRequest::decode(body).unwrap().egress_id, "EG_stub");
+        assert!(state.history.read().await["c1"].recordings[0].stopped_at.is_some());
+        assert!(state.calls.read().await["bob"].recording.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_failed_egress_request_records_nothing() {
+        let (url, seen) = egress_stub(true).await;
+        let state = test_support::state_with_media(livekit(&url));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Active).await;
+
+        assert_eq!(start_call_recording(&state, "c1", "alice").await, Err("Failed to start recording, please try again"));
+        assert_eq!(seen.lock().unwrap().len(), 1);
+        assert!(state.history.read().await["c1"].recordings.is_empty());
+        assert!(state.calls.read().await["bob"].recording.is_none());
+    }
+
+    #[tokio::test]
+    async fn only_a_moderator_reaches_egress() {
+        let (url, seen) = egress_stub(false).await;
+        let state = test_support::state_with_media(livekit(&url));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Active).await;
+
+        assert_eq!(start_call_recording(&state, "c1", "bob").await, Err("Only the caller or a group admin can record"));
+        assert!(seen.lock().unwrap().is_empty());
+    }
+}
@@ -3,12 +3,16 @@
 use socketioxide::extract::{Data, SocketRef, State};
 use tracing::info;
 
-use crate::types::{
-    event, AppState, ErrorPayload, GroupPayload, MessageHistoryPayload,
-    RegisterPayload, RegisteredPayload, UserEntry, UserListPayload, UserOnlinePayload, UserState,
-    group_key,
+use crate::{
+    routing,
+    types::{
+        event, AppState, CallStatus, CallTarget, ErrorPayload, GroupPayload, MessageHistoryPayload,
+        RegisterPayload, RegisteredPayload, UserEntry, UserListPayload, UserOnlinePayload,
+        UserState, group_key,
+    },
 };
 
+#[allow(clippy::collapsible_if)]
 pub async fn on_register(
     socket: SocketRef,
     State(state): State<AppState>,
@@ -112,5 +116,18 @@ pub async fn on_register(
         }
     }
 
+    // 8. A call under way (or being placed) on another device shows up here
+    {
+        let calls = state.calls.read().await;
+        let elsewhere = calls.values().find(|s| matches!(s.target, CallTarget::User(_)) && match s.status {
+            CallStatus::Active  => s.caller == user_id || s.target.id() == user_id,
+            CallStatus::Ringing => s.caller == user_id,
+            CallStatus::Held    => false,
+        });
+        if let Some(s) = elsewhere {
+            let _ = socket.emit(event::CALL_ON_OTHER_DEVICE, &routing::elsewhere_payload(s, &user_id));
+        }
+    }
+
     info!("[+] '{user_id}' registered (socket {socket_id})");
 }
\ No newline at end of file
@@ -3,11 +3,13 @@
 use socketioxide::extract::{Data, SocketRef, State};
 use tracing::info;
 
-use crate::types::{
-    event, AppState, CallEndedPayload, CallRejectedPayload, CallStatus,
-    ErrorPayload, RejectPayload,
+use super::call::{dismiss_ringing, ringing_key};
+use crate::{
+    routing, stats,
+    types::{event, AppState, CallOutcome, CallRejectedPayload, ErrorPayload, RejectPayload},
 };
 
+#[allow(clippy::collapsible_if)]
 pub async fn on_reject(
     socket: SocketRef,
     State(state): State<AppState>,
@@ -23,41 +25,28 @@ pub async fn on_reject(
 
     let mut calls = state.calls.write().await;
     // Validate: call must exist, belong to this caller, and still be ringing
-    let valid = calls.get(&from)
-        .map(|s| s.caller == to && s.status == CallStatus::Ringing)
-        .unwrap_or(false);
-
-    if !valid {
+    let Some(key) = ringing_key(&calls, &from, &to) else {
         emit_error(&socket, "No ringing call to reject");
         return;
-    }
+    };
 
-    let caller_socket_id = calls[&from].caller_socket_id;
-    calls.remove(&from);
+    let session = calls.remove(&key).unwrap();
+    let call_id = session.call_id.clone();
     drop(calls);
-
-    let users = state.users.read().await;
+    stats::ended(&state.history, &call_id, CallOutcome::Rejected).await;
 
     // Notify only the specific caller tab that originated the call
-    if let Some(cs) = users.get(&to) {
-        for sid in &cs.socket_ids {
-            if *sid == caller_socket_id {
-                if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                    let _ = peer.emit(event::CALL_REJECTED,
-                        &CallRejectedPayload { by: from.clone() });
-                }
-                break;
-            }
-        }
-    }
+    routing::emit_to_side(&socket, &state.users, &session, &to, event::CALL_REJECTED,
+        &CallRejectedPayload { by: from.clone() }).await;
+    routing::release_elsewhere(&socket, &state.users, &session, &to).await;
 
     // Dismiss ringing on all other callee tabs
+    let users = state.users.read().await;
     if let Some(cs) = users.get(&from) {
         for sid in &cs.socket_ids {
             if *sid != socket_id {
                 if let Some(peer) = socket.broadcast().get_socket(*sid) {
-                    let _ = peer.emit(event::CALL_ENDED,
-                        &CallEndedPayload { reason: "Rejected on another tab".into() });
+                    dismiss_ringing(&peer, &key, &from, &call_id, "Rejected on another tab");
                 }
             }
         }
@@ -0,0 +1,258 @@
+// src/handlers/schedule.rs — Scheduled 1-to-1 and group calls.
+//
+// schedule_call         → call_scheduled to the creator and every attendee, plus a push
+// cancel_scheduled_call → scheduled_call_cancelled to the same people (creator only)
+// list_scheduled_calls  → scheduled_calls, everything the user created or is invited to
+//
+// spawn_schedule_timer ticks the wheel in crate::schedule. Reminders go out
+// `remind_before` minutes ahead (event + push). At start time the creator's
+// first open tab places the call, through group_call::start_group_call or
+// call::ring, exactly as if they had pressed call themselves.
+
+use socketioxide::{
+    extract::{Data, SocketRef, State},
+    SocketIo,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::{
+    call::{on_active_call, ring},
+    group_call::start_group_call,
+    recording::emit_to_participants,
+};
+use crate::{
+    fcm::Push,
+    push::{targets_for, PushTarget},
+    schedule::TimerKind,
+    types::{
+        event, AppState, CancelScheduledCallPayload, ErrorPayload, ListScheduledCallsPayload,
+        ScheduleCallPayload, ScheduledCall, ScheduledCallCancelledPayload,
+        ScheduledCallStartedPayload, ScheduledCallsPayload, DEFAULT_REMINDER_MIN,
+    },
+    webhook::emit_to_users,
+};
+
+const TICK_SEC: u64 = 1;
+/// A start missed by more than this (server down at the time) is dropped, not rung late.
+const MISSED_GRACE_SEC: i64 = 5 * 60;
+
+// ── schedule_call ─────────────────────────────────────────────────────────────
+
+pub async fn on_schedule_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<ScheduleCallPayload>,
+) {
+    let ScheduleCallPayload { from, to, group_id, start_at, title, remind_before, video } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    if start_at <= chrono::Utc::now() {
+        emit_error(&socket, "The start time must be in the future");
+        return;
+    }
+
+    let (title, attendees) = match (&to, &group_id) {
+        (Some(to), None) => {
+            if to == &from {
+                emit_error(&socket, "Cannot schedule a call with yourself");
+                return;
+            }
+            if !state.users.read().await.contains_key(to) {
+                emit_error(&socket, &format!("User '{to}' is not registered"));
+                return;
+            }
+            (title.unwrap_or_else(|| format!("Call between {from} and {to}")), vec![to.clone()])
+        }
+        (None, Some(gid)) => {
+            let groups = state.groups.read().await;
+            let Some(group) = groups.get(gid) else {
+                emit_error(&socket, &format!("Group '{gid}' not found"));
+                return;
+            };
+            if !group.members.contains(&from) {
+                emit_error(&socket, "You are not a member of this group");
+                return;
+            }
+            let attendees = group.members.iter().filter(|m| **m != from).cloned().collect();
+            (title.unwrap_or_else(|| group.name.clone()), attendees)
+        }
+        _ => {
+            emit_error(&socket, "Schedule a call with either a user or a group");
+            return;
+        }
+    };
+
+    let call = ScheduledCall {
+        schedule_id:   Uuid::new_v4().to_string(),
+        created_by:    from.clone(),
+        title,
+        to,
+        group_id,
+        attendees,
+        start_at,
+        remind_before: remind_before.unwrap_or(DEFAULT_REMINDER_MIN),
+        video:         video.unwrap_or(false),
+        reminded:      false,
+    };
+    state.schedule.insert(call.clone()).await;
+
+    emit_to_participants(&socket, &state, &call.everyone(), event::CALL_SCHEDULED, &call).await;
+    push_to(&state, &call.attendees, Push::Schedule { call: call.clone(), reminder: false }).await;
+    info!("[📅] '{from}' scheduled '{}' for {} ({} attendees)", call.title, call.start_at, call.attendees.len());
+}
+
+// ── cancel_scheduled_call ─────────────────────────────────────────────────────
+
+pub async fn on_cancel_scheduled_call(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<CancelScheduledCallPayload>,
+) {
+    let CancelScheduledCallPayload { from, schedule_id } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    let Some(call) = state.schedule.get(&schedule_id).await else {
+        emit_error(&socket, "No such scheduled call");
+        return;
+    };
+    if call.created_by != from {
+        emit_error(&socket, "Only the creator can cancel a scheduled call");
+        return;
+    }
+    // Its timers stay in the wheel and are dropped when they fire
+    state.schedule.remove(&schedule_id).await;
+
+    emit_to_participants(&socket, &state, &call.everyone(), event::SCHEDULED_CALL_CANCELLED,
+        &ScheduledCallCancelledPayload { schedule_id, by: from.clone(), reason: "Cancelled".into() }).await;
+    info!("[📅] '{from}' cancelled '{}'", call.title);
+}
+
+// ── list_scheduled_calls ──────────────────────────────────────────────────────
+
+pub async fn on_list_scheduled_calls(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<ListScheduledCallsPayload>,
+) {
+    let ListScheduledCallsPayload { from } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    let calls = state.schedule.for_user(&from).await;
+    let _ = socket.emit(event::SCHEDULED_CALLS, &ScheduledCallsPayload { calls });
+}
+
+// ── Timer ─────────────────────────────────────────────────────────────────────
+
+// Spawns the task that fires reminders and starts scheduled calls. Starts run
+// in their own task so a slow media server does not delay other timers.
+pub fn spawn_schedule_timer(state: AppState, io: SocketIo) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(tokio::time::Duration::from_secs(TICK_SEC));
+        loop {
+            tick.tick().await;
+            // Left armed for the next instance, which rebuilds the wheel from storage.schedule_file
+            if crate::shutdown::draining(&state) { continue; }
+
+            for timer in state.schedule.due(chrono::Utc::now().timestamp()).await {
+                match timer.kind {
+                    TimerKind::Reminder => remind(&state, &io, &timer.schedule_id).await,
+                    TimerKind::Start    => {
+                        tokio::spawn(start(state.clone(), io.clone(), timer.schedule_id));
+                    }
+                }
+            }
+        }
+    });
+}
+
+async fn remind(state: &AppState, io: &SocketIo, schedule_id: &str) {
+    let Some(call) = state.schedule.mark_reminded(schedule_id).await else { return; };
+    // Came due while the server was down and the start is already here
+    if call.start_at <= chrono::Utc::now() { return; }
+
+    let everyone = call.everyone();
+    emit_to_users(io, &state.users, everyone.iter().map(String::as_str),
+        event::SCHEDULED_CALL_REMINDER, &call).await;
+    push_to(state, &everyone, Push::Schedule { call: call.clone(), reminder: true }).await;
+    info!("[⏰] reminded {} people of '{}'", everyone.len(), call.title);
+}
+
+async fn start(state: AppState, io: SocketIo, schedule_id: String) {
+    // Gone → cancelled after the timer was armed
+    let Some(call) = state.schedule.remove(&schedule_id).await else { return; };
+
+    if (chrono::Utc::now() - call.start_at).num_seconds() > MISSED_GRACE_SEC {
+        give_up(&state, &io, &call, "Missed while the server was down").await;
+        return;
+    }
+
+    // The creator's first open tab places the call
+    let socket = state.users.read().await.get(&call.created_by)
+        .and_then(|u| u.socket_ids.iter().find_map(|sid| io.get_socket(*sid)));
+    let Some(socket) = socket else {
+        give_up(&state, &io, &call, &format!("{} is offline", call.created_by)).await;
+        return;
+    };
+
+    let _ = socket.emit(event::SCHEDULED_CALL_STARTED, &ScheduledCallStartedPayload {
+        schedule_id: call.schedule_id.clone(),
+        to:          call.to.clone(),
+        group_id:    call.group_id.clone(),
+        video:       call.video,
+    });
+
+    let from = &call.created_by;
+    let result = match (&call.to, &call.group_id) {
+        (_, Some(group_id)) => start_group_call(&state, &socket, from, group_id, call.video, state.ring.group_sec).await,
+        (Some(to), None) => {
+            if on_active_call(&*state.calls.read().await, from) {
+                Err(format!("{from} is already on a call"))
+            } else {
+                ring(&state, &socket, from, to, call.video, state.ring.direct_sec).await
+            }
+        }
+        (None, None) => Err("Nobody to call".into()),
+    };
+
+    match result {
+        Ok(call_id) => info!("[📅] '{}' started as call '{call_id}'", call.title),
+        Err(reason) => give_up(&state, &io, &call, &reason).await,
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+async fn give_up(state: &AppState, io: &SocketIo, call: &ScheduledCall, reason: &str) {
+    let everyone = call.everyone();
+    emit_to_users(io, &state.users, everyone.iter().map(String::as_str), event::SCHEDULED_CALL_CANCELLED,
+        &ScheduledCallCancelledPayload {
+            schedule_id: call.schedule_id.clone(),
+            by:          call.created_by.clone(),
+            reason:      reason.to_owned(),
+        }).await;
+    warn!("[📅] '{}' did not start: {reason}", call.title);
+}
+
+async fn push_to(state: &AppState, user_ids: &[String], push: Push) {
+    let users = state.users.read().await;
+    let targets: Vec<PushTarget> = user_ids.iter()
+        .filter_map(|uid| users.get(uid))
+        .flat_map(|u| targets_for(u, &push))
+        .collect();
+    drop(users);
+    state.push.dispatch(push, targets);
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
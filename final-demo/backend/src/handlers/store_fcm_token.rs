@@ -1,19 +1,155 @@
 // src/handlers/store_fcm_token.rs
 
-use socketioxide::extract::{Data, State};
-use tracing::info;
+use std::collections::HashMap;
 
-use crate::types::{AppState, StoreFcmTokenPayload, UserState};
+use socketioxide::extract::{Data, SocketRef, State};
+use tracing::{info, warn};
+
+use crate::types::{
+    event, new_api_key, AppState, Device, DeviceRegisteredPayload, ErrorPayload, Platform,
+    StoreFcmTokenPayload, UserState,
+};
 
 pub async fn on_store_fcm_token(
+    socket: SocketRef,
     State(state): State<AppState>,
     Data(payload): Data<StoreFcmTokenPayload>,
 ) {
-    let mut map = state.users.write().await;
-    let entry = map.entry(payload.user_id.clone())
-        .or_insert_with(|| UserState::new(&payload.user_id));
-    if !entry.fcm_tokens.contains(&payload.token) {
-        entry.fcm_tokens.push(payload.token);
-    }
-    info!("[fcm] token stored for '{}' ({} total)", payload.user_id, entry.fcm_tokens.len());
-}
\ No newline at end of file
+    if !super::call::identity_matches(&state, socket.id, &payload.user_id).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    if payload.token.trim().is_empty() {
+        emit_error(&socket, "Empty push token");
+        return;
+    }
+
+    let (device_id, api_key) = store(&mut *state.users.write().await, payload);
+    let _ = socket.emit(event::DEVICE_REGISTERED, &DeviceRegisteredPayload { device_id, api_key });
+}
+
+/// Records the token on the user's device; returns the device id and its API key.
+fn store(map: &mut HashMap<String, UserState>, payload: StoreFcmTokenPayload) -> (String, String) {
+    let StoreFcmTokenPayload { user_id, token, device_id, platform, push_kind, app_version, prefs } = payload;
+
+    let device_id = device_id.filter(|d| !d.trim().is_empty())
+        .unwrap_or_else(|| legacy_device_id(platform));
+    let now = chrono::Utc::now();
+
+    // A browser token outlives logins: whoever registers it now owns it, and
+    // the previous user's device holding it goes so they stop getting its pushes
+    for other in map.values_mut().filter(|u| u.user_id != user_id) {
+        let before = other.devices.len();
+        other.devices.retain(|d| d.token != token);
+        if other.devices.len() < before {
+            warn!("[fcm] token moved from '{}' to '{user_id}'", other.user_id);
+        }
+    }
+
+    let entry = map.entry(user_id.clone())
+        .or_insert_with(|| UserState::new(&user_id));
+
+    // A token belongs to exactly one device — drop it from any other device that still holds it
+    entry.devices.retain(|d| d.device_id == device_id || d.token != token);
+
+    let api_key = match entry.devices.iter_mut().find(|d| d.device_id == device_id) {
+        // Token refresh: the new token replaces the previous one for this device; the API key stays
+        Some(device) => {
+            device.token        = token;
+            device.platform     = platform;
+            device.push_kind    = push_kind;
+            device.refreshed_at = now;
+            if app_version.is_some() { device.app_version = app_version; }
+            if let Some(prefs) = prefs { device.prefs = prefs; }
+            device.api_key.clone()
+        }
+        None => {
+            let api_key = new_api_key();
+            entry.devices.push(Device {
+                device_id:    device_id.clone(),
+                platform,
+                push_kind,
+                token,
+                api_key:      api_key.clone(),
+                app_version,
+                prefs:        prefs.unwrap_or_default(),
+                created_at:   now,
+                refreshed_at: now,
+            });
+            api_key
+        }
+    };
+    info!("[fcm] {platform:?} device '{device_id}' stored for '{user_id}' ({} total)", entry.devices.len());
+    (device_id, api_key)
+}
+
+/// Clients that predate `device_id` get one device per platform, so each of
+/// their token refreshes replaces the last instead of adding a device.
+fn legacy_device_id(platform: Platform) -> String {
+    match platform {
+        Platform::Android => "legacy-android",
+        Platform::Ios     => "legacy-ios",
+        Platform::Web     => "legacy-web",
+    }.to_owned()
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register(
+        map:       &mut HashMap<String, UserState>,
+        user_id:   &str,
+        token:     &str,
+        device_id: Option<&str>,
+    ) -> (String, String) {
+        store(map, StoreFcmTokenPayload {
+            user_id:     user_id.into(),
+            token:       token.into(),
+            device_id:   device_id.map(str::to_owned),
+            platform:    Platform::Web,
+            push_kind:   Default::default(),
+            app_version: None,
+            prefs:       None,
+        })
+    }
+
+    fn tokens(map: &HashMap<String, UserState>, user_id: &str) -> Vec<String> {
+        map.get(user_id).map(|u| u.devices.iter().map(|d| d.token.clone()).collect()).unwrap_or_default()
+    }
+
+    #[test]
+    fn refreshed_tokens_replace_the_device_token() {
+        let mut map = HashMap::new();
+        let (id, key) = register(&mut map, "alice", "t1", Some("d1"));
+        assert_eq!(register(&mut map, "alice", "t2", Some("d1")), (id, key));
+        assert_eq!(tokens(&map, "alice"), ["t2"]);
+    }
+
+    #[test]
+    fn clients_without_a_device_id_keep_one_device() {
+        let mut map = HashMap::new();
+        let (id, key) = register(&mut map, "alice", "t1", None);
+        assert_eq!(id, "legacy-web");
+        assert_eq!(register(&mut map, "alice", "t2", None), (id, key));
+        assert_eq!(register(&mut map, "alice", "t3", Some(" ")).0, "legacy-web");
+        assert_eq!(tokens(&map, "alice"), ["t3"]);
+    }
+
+    #[test]
+    fn a_token_moves_to_the_user_registering_it() {
+        let mut map = HashMap::new();
+        register(&mut map, "alice", "shared", Some("browser"));
+        register(&mut map, "alice", "phone", Some("pixel"));
+
+        // alice logs out, bob logs in on the same browser profile
+        let (_, key) = register(&mut map, "bob", "shared", Some("browser"));
+        assert!(!key.is_empty());
+        assert_eq!(tokens(&map, "bob"), ["shared"]);
+        assert_eq!(tokens(&map, "alice"), ["phone"]);
+    }
+}
@@ -0,0 +1,171 @@
+// src/handlers/webrtc.rs — SDP / ICE relay for the peer-to-peer media backend.
+//
+// With MEDIA_BACKEND=p2p there is no media server: once a 1-to-1 call is
+// accepted the caller sends webrtc_offer, the callee answers with
+// webrtc_answer, and both trickle webrtc_ice_candidate. The server only
+// forwards them, and only between the two tabs bound to an active session:
+// the one that placed the call and the one that accepted it. Other tabs of
+// the same users never see the SDP.
+//
+// request_turn_credentials hands out time-limited TURN credentials
+// (media::turn) to either party of a 1-to-1 call, including one waiting or held.
+
+use std::collections::HashMap;
+
+use socketioxide::{
+    extract::{Data, SocketRef, State},
+    socket::Sid,
+};
+use tracing::debug;
+
+use crate::{
+    routing,
+    types::{
+        event, AppState, CallSession, CallStatus, CallTarget, ErrorPayload, TurnCredentialsPayload,
+        TurnCredentialsRequestPayload, WebRtcIcePayload, WebRtcIceRelayPayload, WebRtcSdpPayload,
+        WebRtcSdpRelayPayload,
+    },
+};
+
+pub async fn on_webrtc_offer(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<WebRtcSdpPayload>,
+) {
+    relay_sdp(&socket, &state, event::WEBRTC_OFFER, payload).await;
+}
+
+pub async fn on_webrtc_answer(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<WebRtcSdpPayload>,
+) {
+    relay_sdp(&socket, &state, event::WEBRTC_ANSWER, payload).await;
+}
+
+pub async fn on_webrtc_ice_candidate(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<WebRtcIcePayload>,
+) {
+    let WebRtcIcePayload { from, to, candidate } = payload;
+    let Some((call_id, peer_sid)) = validate(&socket, &state, &from, &to).await else { return; };
+
+    let relay = WebRtcIceRelayPayload { call_id, from, candidate };
+    emit_to_socket(&socket, peer_sid, event::WEBRTC_ICE, &relay);
+}
+
+pub async fn on_request_turn_credentials(
+    socket: SocketRef,
+    State(state): State<AppState>,
+    Data(payload): Data<TurnCredentialsRequestPayload>,
+) {
+    let TurnCredentialsRequestPayload { from } = payload;
+
+    if !super::call::identity_matches(&state, socket.id, &from).await {
+        emit_error(&socket, "Identity mismatch");
+        return;
+    }
+    let Some(turn) = state.turn.clone() else {
+        emit_error(&socket, "TURN is not configured on this server");
+        return;
+    };
+
+    let in_call = in_dm_call(&*state.calls.read().await, &from);
+    if !in_call {
+        emit_error(&socket, "You are not in a call");
+        return;
+    }
+
+    let _ = socket.emit(event::TURN_CREDENTIALS, &TurnCredentialsPayload {
+        ice_servers: turn.ice_servers_for(&from),
+        ttl:         turn.ttl_sec,
+    });
+    debug!("[turn] issued credentials to '{from}'");
+}
+
+/// Whether `user` is either party of a 1-to-1 call, ringing, active, waiting
+/// or held. Goes by target rather than key, which is only the callee's id
+/// for the plain case.
+fn in_dm_call(calls: &HashMap<String, CallSession>, user: &str) -> bool {
+    calls.values().any(|s| match &s.target {
+        CallTarget::User(callee) => callee == user || s.caller == user,
+        _                        => false,
+    })
+}
+
+async fn relay_sdp(socket: &SocketRef, state: &AppState, ev: &'static str, payload: WebRtcSdpPayload) {
+    let WebRtcSdpPayload { from, to, sdp } = payload;
+    let Some((call_id, peer_sid)) = validate(socket, state, &from, &to).await else { return; };
+
+    debug!("[webrtc] {ev} '{from}' → '{to}'");
+    let relay = WebRtcSdpRelayPayload { call_id, from, sdp };
+    emit_to_socket(socket, peer_sid, ev, &relay);
+}
+
+/// The call id of the active 1-to-1 call between `from` and `to` plus the
+/// socket of the other party, or None (after telling the sender why) if this
+/// socket is not one end of such a call.
+async fn validate(socket: &SocketRef, state: &AppState, from: &str, to: &str) -> Option<(String, Sid)> {
+    if !state.media.relays_signaling() {
+        emit_error(socket, "Peer-to-peer signaling is not enabled on this server");
+        return None;
+    }
+    if !super::call::identity_matches(state, socket.id, from).await {
+        emit_error(socket, "Identity mismatch");
+        return None;
+    }
+
+    let calls = state.calls.read().await;
+    let session = [(to, from), (from, to)].into_iter().find_map(|(callee, caller)| {
+        calls.get(callee).filter(|s| s.caller == caller && s.status == CallStatus::Active
+            && matches!(&s.target, CallTarget::User(_)))
+    });
+    let Some(session) = session else {
+        emit_error(socket, "No active call with this user");
+        return None;
+    };
+
+    // Only the two tabs bound to the call may signal, each to the other.
+    if routing::bound_socket(session, from) != Some(socket.id) {
+        emit_error(socket, "This call is connected on another tab");
+        return None;
+    }
+    let peer_sid = routing::bound_socket(session, to)?;
+    Some((session.call_id.clone(), peer_sid))
+}
+
+fn emit_to_socket<T: serde::Serialize>(socket: &SocketRef, sid: Sid, ev: &'static str, payload: &T) {
+    if let Some(peer) = socket.broadcast().get_socket(sid) {
+        let _ = peer.emit(ev, payload);
+    }
+}
+
+fn emit_error(socket: &SocketRef, message: &str) {
+    let _ = socket.emit(event::ERROR, &ErrorPayload { message: message.to_owned() });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{media::p2p::P2pBackend, test_support};
+
+    #[tokio::test]
+    async fn either_party_counts_under_any_key() {
+        let state = test_support::state_with_media(Arc::new(P2pBackend));
+        let user = |id: &str| CallTarget::User(id.into());
+        test_support::add_call(&state, "bob",           "c1", "alice", user("bob"),   CallStatus::Active).await;
+        test_support::add_call(&state, "waiting::bob",  "c2", "carol", user("bob"),   CallStatus::Ringing).await;
+        test_support::add_call(&state, "held::c3",      "c3", "dave",  user("erin"),  CallStatus::Active).await;
+        test_support::add_call(&state, "team",          "c4", "frank", CallTarget::Group("team".into()), CallStatus::Active).await;
+
+        let calls = state.calls.read().await;
+        for party in ["alice", "bob", "carol", "dave", "erin"] {
+            assert!(in_dm_call(&calls, party), "{party}");
+        }
+        assert!(!in_dm_call(&calls, "frank"));   // group calls get no TURN credentials
+        assert!(!in_dm_call(&calls, "grace"));
+    }
+}
@@ -133,14 +133,38 @@
 //     Json(serde_json::json!({ "message": "pong" }))
 // }
 // src/main.rs
+
+mod api;
 mod fcm;
 mod handlers;
-mod livekit;   // <-- ADD THIS
+mod media;
+mod push;
+mod queue;
+mod routing;
+mod schedule;
+mod settings;
+mod shutdown;
+mod snapshot;
+mod stats;
+#[cfg(test)]
+mod test_support;
 mod types;
+mod voicemail;
+mod webhook;
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
 
-use axum::{http::Method, response::IntoResponse, routing::get, Json, Router};
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::Method,
+    response::IntoResponse,
+    routing::{get, post},
+    Extension, Json, Router,
+};
 use gcp_auth::CustomServiceAccount;
 use socketioxide::{extract::SocketRef, SocketIo};
 use tower_http::cors::{Any, CorsLayer};
@@ -150,25 +174,63 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilte
 use handlers::{
     accept::on_accept,
     call::on_call,
+    call_prefs::{on_get_call_prefs, on_set_call_prefs},
+    call_control::{on_hold_call, on_resume_call, on_transfer_call, on_transfer_call_to_device},
     cancel::on_cancel,
     chat::{on_send_message, on_send_group_message},
     cut_call::on_cut_call,
+    devices::{on_list_devices, on_remove_device, spawn_stale_device_pruner},
     disconnect::on_disconnect,
     group::{on_add_group_member, on_create_group, on_remove_group_member},
-    group_call::{on_group_accept, on_group_call, on_group_cut, on_group_reject},
+    group_call::{
+        on_group_accept, on_group_call, on_group_call_ring, on_group_call_status, on_group_cut,
+        on_group_reject, on_join_group_call,
+    },
+    group_moderation::{on_group_call_lock, on_group_call_mute_participant, on_group_call_remove_participant},
+    invite::on_invite_to_call,
+    livekit_token::on_refresh_livekit_token,
+    quality::on_call_quality_report,
+    queue::{
+        on_accept_queue_call, on_call_queue, on_leave_queue, on_list_queues, on_reject_queue_call,
+        spawn_queue_dispatcher,
+    },
+    recording::{on_start_recording, on_stop_recording},
     register::on_register,
     reject::on_reject,
+    schedule::{
+        on_cancel_scheduled_call, on_list_scheduled_calls, on_schedule_call, spawn_schedule_timer,
+    },
     store_fcm_token::on_store_fcm_token,
+    webrtc::{on_request_turn_credentials, on_webrtc_answer, on_webrtc_ice_candidate, on_webrtc_offer},
 };
-use types::AppState;
+use push::PushDispatcher;
+use settings::{Cli, Settings};
+use types::{AppState, RingTimeouts, VOICEMAIL_MAX_BYTES};
 
 const EV_REGISTER:            &str = "register";
 const EV_STORE_FCM:           &str = "store_fcm_token";
+const EV_LIST_DEVICES:        &str = "list_devices";
+const EV_REMOVE_DEVICE:       &str = "remove_device";
+const EV_GET_CALL_PREFS:      &str = "get_call_prefs";
+const EV_SET_CALL_PREFS:      &str = "set_call_prefs";
 const EV_CALL:                &str = "call";
 const EV_CANCEL:              &str = "cancel";
 const EV_ACCEPT:              &str = "accept";
 const EV_REJECT:              &str = "reject";
 const EV_CUT_CALL:            &str = "cut_call";
+const EV_INVITE_TO_CALL:      &str = "invite_to_call";
+const EV_HOLD_CALL:           &str = "hold_call";
+const EV_RESUME_CALL:         &str = "resume_call";
+const EV_TRANSFER_CALL:       &str = "transfer_call";
+const EV_TRANSFER_TO_DEVICE:  &str = "transfer_call_to_device";
+const EV_REFRESH_LK_TOKEN:    &str = "refresh_livekit_token";
+const EV_START_RECORDING:     &str = "start_recording";
+const EV_STOP_RECORDING:      &str = "stop_recording";
+const EV_QUALITY_REPORT:      &str = "call_quality_report";
+const EV_WEBRTC_OFFER:        &str = "webrtc_offer";
+const EV_WEBRTC_ANSWER:       &str = "webrtc_answer";
+const EV_WEBRTC_ICE:          &str = "webrtc_ice_candidate";
+const EV_REQUEST_TURN:        &str = "request_turn_credentials";
 const EV_CREATE_GROUP:        &str = "create_group";
 const EV_ADD_GROUP_MEMBER:    &str = "add_group_member";
 const EV_REMOVE_GROUP_MEMBER: &str = "remove_group_member";
@@ -176,6 +238,20 @@ const EV_GROUP_CALL:          &str = "group_call";
 const EV_GROUP_ACCEPT:        &str = "group_accept";
 const EV_GROUP_REJECT:        &str = "group_reject";
 const EV_GROUP_CUT:           &str = "group_cut";
+const EV_GROUP_MUTE:          &str = "group_call_mute_participant";
+const EV_GROUP_REMOVE:        &str = "group_call_remove_participant";
+const EV_GROUP_LOCK:          &str = "group_call_lock";
+const EV_GROUP_CALL_STATUS:   &str = "group_call_status";
+const EV_JOIN_GROUP_CALL:     &str = "join_group_call";
+const EV_GROUP_CALL_RING:     &str = "group_call_ring";
+const EV_SCHEDULE_CALL:       &str = "schedule_call";
+const EV_CANCEL_SCHEDULED:    &str = "cancel_scheduled_call";
+const EV_LIST_SCHEDULED:      &str = "list_scheduled_calls";
+const EV_CALL_QUEUE:          &str = "call_queue";
+const EV_LEAVE_QUEUE:         &str = "leave_queue";
+const EV_ACCEPT_QUEUE_CALL:   &str = "accept_queue_call";
+const EV_REJECT_QUEUE_CALL:   &str = "reject_queue_call";
+const EV_LIST_QUEUES:         &str = "list_queues";
 const EV_SEND_MESSAGE:        &str = "send_message";
 const EV_SEND_GROUP_MESSAGE:  &str = "send_group_message";
 
@@ -188,12 +264,38 @@ async fn main() {
 
     dotenvy::dotenv().ok();
 
-    // ── GCP / FCM ─────────────────────────────────────────────────────────────
-    let sa_path = std::env::var("GOOGLE_APPLICATION_CREDENTIALS")
-        .expect("GOOGLE_APPLICATION_CREDENTIALS must be set");
+    // ── Settings ──────────────────────────────────────────────────────────────
+    let cli = Cli::parse().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let config = match Settings::load(cli.config.as_deref()) {
+        Ok((settings, file)) => {
+            let source = file.map_or("defaults and environment".into(), |f| f.display().to_string());
+            if cli.check {
+                println!("# configuration OK — {source}\n");
+                print!("{}", settings.to_toml());
+                return;
+            }
+            info!("[settings] loaded from {source}");
+            settings::init(settings)
+        }
+        Err(errors) => {
+            eprintln!("invalid configuration:");
+            for e in &errors {
+                eprintln!("  - {e}");
+            }
+            std::process::exit(1);
+        }
+    };
 
-    let service_account = CustomServiceAccount::from_file(PathBuf::from(&sa_path))
-        .expect("Failed to load service-account JSON");
+    // ── GCP / FCM ─────────────────────────────────────────────────────────────
+    // Settings validation has already parsed this file once.
+    let sa_path = config.fcm.credentials.clone().unwrap_or_default();
+    let service_account = CustomServiceAccount::from_file(sa_path).unwrap_or_else(|e| {
+        eprintln!("fcm.credentials: {e}");
+        std::process::exit(1);
+    });
 
     let auth: Arc<dyn gcp_auth::TokenProvider> = Arc::new(service_account);
 
@@ -203,36 +305,87 @@ async fn main() {
         Err(e) => tracing::warn!("[fcm] startup credential check failed: {e}"),
     }
 
-    // ── LiveKit ───────────────────────────────────────────────────────────────
-    let livekit_config = livekit::LiveKitConfig::from_env();
-    info!("[livekit] config loaded — url: {}", livekit_config.url);
+    // ── Media backend (LiveKit / p2p / none) ──────────────────────────────────
+    let media = media::from_settings(&config.media);
+    let turn  = media::turn::TurnConfig::from_settings(&config.media.turn).map(Arc::new);
+
+    // ── Ring timeouts ─────────────────────────────────────────────────────────
+    let ring = RingTimeouts::from_settings(&config.calls);
+    info!("[call] ring timeouts: {}s 1-to-1, {}s group", ring.direct_sec, ring.group_sec);
+
+    // ── Push dispatcher ───────────────────────────────────────────────────────
+    // One pooled client for every push: FCM negotiates HTTP/2, so keeping the
+    // connection alive lets concurrent sends multiplex over a single socket.
+    let http = reqwest::Client::builder()
+        .pool_idle_timeout(Duration::from_secs(90))
+        .http2_keep_alive_interval(Duration::from_secs(30))
+        .http2_keep_alive_while_idle(true)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    // ── State saved by the last graceful shutdown ─────────────────────────────
+    let mut saved = snapshot::Snapshot::load();
+
+    let users = Arc::new(tokio::sync::RwLock::new(saved.take_users()));
+    let max_concurrency = config.fcm.max_concurrency;
+    let push = Arc::new(PushDispatcher::new(auth, http, users.clone(), max_concurrency));
+    info!("[push] dispatcher ready — max {max_concurrency} concurrent sends");
 
     let state = AppState {
-        users:    Arc::new(tokio::sync::RwLock::new(HashMap::new())),
-        groups:   Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+        users,
+        groups:   Arc::new(tokio::sync::RwLock::new(saved.take_groups())),
         calls:    Arc::new(tokio::sync::RwLock::new(HashMap::new())),
-        messages: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
-        auth,
-        http:     reqwest::Client::new(),
-        livekit:  Arc::new(livekit_config),  
+        messages: Arc::new(tokio::sync::RwLock::new(std::mem::take(&mut saved.messages))),
+        history:  Arc::new(tokio::sync::RwLock::new(saved.take_history())),
+        push:     push.clone(),
+        media,
+        turn,
+        schedule: Arc::new(schedule::Scheduler::open(config.storage.schedule_file.clone())),
+        ring,
+        voicemail: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+        queues:   Arc::new(queue::Queues::load(&config.storage.queues_file)),
+        draining: Arc::new(AtomicBool::new(false)),
     };
 
+    spawn_stale_device_pruner(state.users.clone());
+
     // ── Socket.IO ─────────────────────────────────────────────────────────────
     let (sio_layer, io) = SocketIo::builder()
-        .with_state(state)
+        .with_state(state.clone())
         .build_layer();
 
+    spawn_schedule_timer(state.clone(), io.clone());
+    spawn_queue_dispatcher(state.clone(), io.clone());
+    let shutdown = shutdown::run(state.clone(), io.clone());
+
     io.ns("/", |socket: SocketRef| {
         tracing::info!(socket_id = %socket.id, "New socket connected");
 
         socket.on(EV_REGISTER,  on_register);
         socket.on(EV_STORE_FCM, on_store_fcm_token);
+        socket.on(EV_LIST_DEVICES,  on_list_devices);
+        socket.on(EV_REMOVE_DEVICE, on_remove_device);
+        socket.on(EV_GET_CALL_PREFS, on_get_call_prefs);
+        socket.on(EV_SET_CALL_PREFS, on_set_call_prefs);
 
         socket.on(EV_CALL,      on_call);
         socket.on(EV_CANCEL,    on_cancel);
         socket.on(EV_ACCEPT,    on_accept);
         socket.on(EV_REJECT,    on_reject);
         socket.on(EV_CUT_CALL,  on_cut_call);
+        socket.on(EV_INVITE_TO_CALL,   on_invite_to_call);
+        socket.on(EV_HOLD_CALL,        on_hold_call);
+        socket.on(EV_RESUME_CALL,      on_resume_call);
+        socket.on(EV_TRANSFER_CALL,    on_transfer_call);
+        socket.on(EV_TRANSFER_TO_DEVICE, on_transfer_call_to_device);
+        socket.on(EV_REFRESH_LK_TOKEN, on_refresh_livekit_token);
+        socket.on(EV_START_RECORDING,  on_start_recording);
+        socket.on(EV_STOP_RECORDING,   on_stop_recording);
+        socket.on(EV_QUALITY_REPORT,   on_call_quality_report);
+        socket.on(EV_WEBRTC_OFFER,     on_webrtc_offer);
+        socket.on(EV_WEBRTC_ANSWER,    on_webrtc_answer);
+        socket.on(EV_WEBRTC_ICE,       on_webrtc_ice_candidate);
+        socket.on(EV_REQUEST_TURN,     on_request_turn_credentials);
 
         socket.on(EV_CREATE_GROUP,        on_create_group);
         socket.on(EV_ADD_GROUP_MEMBER,    on_add_group_member);
@@ -242,6 +395,22 @@ async fn main() {
         socket.on(EV_GROUP_ACCEPT, on_group_accept);
         socket.on(EV_GROUP_REJECT, on_group_reject);
         socket.on(EV_GROUP_CUT,    on_group_cut);
+        socket.on(EV_GROUP_CALL_STATUS, on_group_call_status);
+        socket.on(EV_JOIN_GROUP_CALL,   on_join_group_call);
+        socket.on(EV_GROUP_CALL_RING,   on_group_call_ring);
+        socket.on(EV_GROUP_MUTE,   on_group_call_mute_participant);
+        socket.on(EV_GROUP_REMOVE, on_group_call_remove_participant);
+        socket.on(EV_GROUP_LOCK,   on_group_call_lock);
+
+        socket.on(EV_SCHEDULE_CALL,    on_schedule_call);
+        socket.on(EV_CANCEL_SCHEDULED, on_cancel_scheduled_call);
+        socket.on(EV_LIST_SCHEDULED,   on_list_scheduled_calls);
+
+        socket.on(EV_CALL_QUEUE,        on_call_queue);
+        socket.on(EV_LEAVE_QUEUE,       on_leave_queue);
+        socket.on(EV_ACCEPT_QUEUE_CALL, on_accept_queue_call);
+        socket.on(EV_REJECT_QUEUE_CALL, on_reject_queue_call);
+        socket.on(EV_LIST_QUEUES,       on_list_queues);
 
         socket.on(EV_SEND_MESSAGE,       on_send_message);
         socket.on(EV_SEND_GROUP_MESSAGE, on_send_group_message);
@@ -257,14 +426,32 @@ async fn main() {
 
     let app = Router::new()
         .route("/ping", get(ping_handler))
+        .route("/push/metrics", get(push_metrics_handler))
+        .route("/messages", get(api::get_message))
+        .route("/recordings/:egress_id", get(api::get_recording))
+        .route("/stats/calls", get(api::get_call_stats))
+        .route("/voicemail/:call_id", post(api::post_voicemail)
+            .layer(DefaultBodyLimit::max(VOICEMAIL_MAX_BYTES))
+            .get(api::get_voicemail))
+        .route("/livekit/webhook", post(webhook::on_media_webhook))
+        .with_state(state)
+        .layer(Extension(io))
         .layer(sio_layer)
         .layer(cors);
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3001").await.unwrap();
-    info!("Server listening on http://127.0.0.1:3001");
-    axum::serve(listener, app).await.unwrap();
+    let bind = config.server.bind;
+    let listener = tokio::net::TcpListener::bind(bind).await.unwrap_or_else(|e| {
+        eprintln!("server.bind: cannot listen on {bind}: {e}");
+        std::process::exit(1);
+    });
+    info!("Server listening on http://{bind}");
+    axum::serve(listener, app).with_graceful_shutdown(shutdown).await.unwrap();
 }
 
 async fn ping_handler() -> impl IntoResponse {
     Json(serde_json::json!({ "message": "pong" }))
+}
+
+async fn push_metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.push.metrics())
 }
\ No newline at end of file
@@ -0,0 +1,285 @@
+// src/media/livekit.rs — LiveKit media backend: rooms, tokens, moderation, egress, webhooks
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use livekit_api::{
+    access_token::{AccessToken, TokenVerifier, VideoGrants},
+    services::{
+        egress::{EgressClient, EgressOutput, RoomCompositeOptions},
+        room::{CreateRoomOptions, RoomClient, UpdateParticipantOptions},
+    },
+    webhooks::WebhookReceiver,
+};
+use livekit_protocol as proto;
+use tracing::{error, warn};
+
+use super::{JoinCredentials, MediaBackend, MediaEvent, ParticipantRole};
+use crate::settings::LiveKitSettings;
+
+/// Token lifetime. LiveKit only checks it on (re)connect, so clients call
+/// `refresh_livekit_token` before it runs out to survive a reconnect.
+pub const TOKEN_TTL_SEC: u64 = 10 * 60;
+
+/// LiveKit config from `media.livekit` in the settings.
+/// Only built when the LiveKit backend is selected.
+pub struct LiveKitConfig {
+    pub url:               String,   // ws:// — sent to clients for SDK connection
+    pub api_url:           String,   // http:// — used by Rust for room management API
+    pub api_key:           String,
+    pub api_secret:        String,
+    pub egress_url:        String,   // http:// — Egress API, defaults to api_url
+    pub egress_output_dir: String,   // where the egress service writes files
+    pub recordings_dir:    String,   // the same directory as mounted on this host
+    pub empty_timeout_sec: u32,      // an empty room is deleted after this long
+    pub max_participants:  u32,
+}
+
+impl LiveKitConfig {
+    /// Settings validation has already required url, api_url, api_key and api_secret.
+    pub fn from_settings(lk: &LiveKitSettings) -> Self {
+        let api_url = lk.api_url.clone().unwrap_or_default();
+        Self {
+            url:               lk.url.clone().unwrap_or_default(),
+            egress_url:        lk.egress_url.clone().unwrap_or_else(|| api_url.clone()),
+            api_url,
+            api_key:           lk.api_key.clone().unwrap_or_default(),
+            api_secret:        lk.api_secret.clone().unwrap_or_default(),
+            egress_output_dir: lk.egress_output_dir.clone(),
+            recordings_dir:    lk.recordings_dir.clone(),
+            empty_timeout_sec: lk.empty_timeout_sec,
+            max_participants:  lk.max_participants,
+        }
+    }
+}
+
+pub struct LiveKitBackend {
+    config: LiveKitConfig,
+}
+
+impl LiveKitBackend {
+    pub fn new(config: LiveKitConfig) -> Self {
+        tracing::info!("[livekit] config loaded — url: {}", config.url);
+        Self { config }
+    }
+
+    fn rooms(&self) -> RoomClient {
+        RoomClient::with_api_key(&self.config.api_url, &self.config.api_key, &self.config.api_secret)
+    }
+
+    fn egress(&self) -> EgressClient {
+        EgressClient::with_api_key(&self.config.egress_url, &self.config.api_key, &self.config.api_secret)
+    }
+}
+
+#[async_trait]
+impl MediaBackend for LiveKitBackend {
+    fn name(&self) -> &'static str { "livekit" }
+    fn multi_party(&self) -> bool { true }
+    fn relays_signaling(&self) -> bool { false }
+
+    // ── Room management ───────────────────────────────────────────────────────
+
+    /// For 1-to-1 calls:  room = "call::{call_id}"
+    /// For group calls:   room = "group::{group_id}::{call_id}"
+    async fn create_room(&self, room: &str) -> bool {
+        match self.rooms().create_room(room, CreateRoomOptions {
+            empty_timeout:        self.config.empty_timeout_sec,
+            max_participants:     self.config.max_participants,
+            ..Default::default()
+        }).await {
+            Ok(_)  => { tracing::info!("[livekit] Room '{}' created", room); true }
+            Err(e) => { error!("[livekit] Failed to create room '{}': {e}", room); false }
+        }
+    }
+
+    async fn delete_room(&self, room: &str) {
+        if let Err(e) = self.rooms().delete_room(room).await {
+            error!("[livekit] Failed to delete room '{}': {e}", room);
+        } else {
+            tracing::info!("[livekit] Room '{}' deleted", room);
+        }
+    }
+
+    /// A short-lived JWT; the call id and role travel as participant metadata
+    /// so other clients can read them.
+    fn join_credentials(
+        &self,
+        room:     &str,
+        identity: &str,
+        call_id:  &str,
+        role:     ParticipantRole,
+        video:    bool,
+    ) -> Option<JoinCredentials> {
+        let metadata = serde_json::json!({ "call_id": call_id, "role": role }).to_string();
+
+        match AccessToken::with_api_key(&self.config.api_key, &self.config.api_secret)
+            .with_identity(identity)
+            .with_name(identity)
+            .with_metadata(&metadata)
+            .with_ttl(std::time::Duration::from_secs(TOKEN_TTL_SEC))
+            .with_grants(grants_for(room, role, video))
+            .to_jwt()
+        {
+            Ok(token) => {
+                tracing::info!("[livekit] {role:?} token generated for '{}' in room '{}'", identity, room);
+                Some(JoinCredentials { media: self.name(), url: self.config.url.clone(), token })
+            }
+            Err(e) => {
+                error!("[livekit] Token generation failed for '{}': {e}", identity);
+                None
+            }
+        }
+    }
+
+    // ── Moderation ────────────────────────────────────────────────────────────
+
+    async fn list_participants(&self, room: &str) -> Option<Vec<String>> {
+        match self.rooms().list_participants(room).await {
+            Ok(list) => Some(list.into_iter()
+                .filter(|p| !p.permission.as_ref().is_some_and(|perm| perm.hidden))
+                .map(|p| p.identity)
+                .collect()),
+            Err(e)   => { error!("[livekit] Failed to list participants of '{}': {e}", room); None }
+        }
+    }
+
+    async fn mute_participant(&self, room: &str, identity: &str) -> bool {
+        let rooms = self.rooms();
+        let info = match rooms.get_participant(room, identity).await {
+            Ok(info) => info,
+            Err(e)   => { error!("[livekit] '{}' not found in '{}': {e}", identity, room); return false; }
+        };
+        let mut ok = true;
+        for track in info.tracks.iter().filter(|t| t.r#type == proto::TrackType::Audio as i32) {
+            if let Err(e) = rooms.mute_published_track(room, identity, &track.sid, true).await {
+                error!("[livekit] Failed to mute '{}' of '{}': {e}", track.sid, identity);
+                ok = false;
+            }
+        }
+        ok
+    }
+
+    async fn remove_participant(&self, room: &str, identity: &str) -> bool {
+        match self.rooms().remove_participant(room, identity).await {
+            Ok(())  => { tracing::info!("[livekit] '{}' removed from '{}'", identity, room); true }
+            Err(e)  => { error!("[livekit] Failed to remove '{}' from '{}': {e}", identity, room); false }
+        }
+    }
+
+    async fn set_held(&self, room: &str, identity: &str, held: bool, video: bool) -> bool {
+        let permission = if held {
+            proto::ParticipantPermission::default()
+        } else {
+            let sources: &[proto::TrackSource] = if video {
+                &[proto::TrackSource::Microphone, proto::TrackSource::Camera,
+                  proto::TrackSource::ScreenShare, proto::TrackSource::ScreenShareAudio]
+            } else {
+                &[proto::TrackSource::Microphone]
+            };
+            proto::ParticipantPermission {
+                can_subscribe:       true,
+                can_publish:         true,
+                can_publish_data:    true,
+                can_publish_sources: sources.iter().map(|s| *s as i32).collect(),
+                ..Default::default()
+            }
+        };
+        match self.rooms().update_participant(room, identity, UpdateParticipantOptions {
+            permission: Some(permission),
+            ..Default::default()
+        }).await {
+            Ok(_)  => { tracing::info!("[livekit] '{}' in '{}' held={held}", identity, room); true }
+            Err(e) => { error!("[livekit] Failed to update '{}' in '{}': {e}", identity, room); false }
+        }
+    }
+
+    // ── Recording (Egress) ────────────────────────────────────────────────────
+
+    /// Room-composite recording to a single file; `file` is relative to the output directory.
+    async fn start_recording(&self, room: &str, file: &str, video: bool) -> Option<String> {
+        let file_type = if video { proto::EncodedFileType::Mp4 } else { proto::EncodedFileType::Ogg };
+        let output = proto::EncodedFileOutput {
+            file_type: file_type as i32,
+            filepath:  format!("{}/{file}", self.config.egress_output_dir.trim_end_matches('/')),
+            ..Default::default()
+        };
+
+        match self.egress().start_room_composite_egress(room, vec![EgressOutput::File(output)], RoomCompositeOptions {
+            layout:     "grid".into(),
+            audio_only: !video,
+            ..Default::default()
+        }).await {
+            Ok(info) => { tracing::info!("[livekit] Recording '{}' started in '{}'", info.egress_id, room); Some(info.egress_id) }
+            Err(e)   => { error!("[livekit] Failed to start recording in '{}': {e}", room); None }
+        }
+    }
+
+    async fn stop_recording(&self, egress_id: &str) -> bool {
+        match self.egress().stop_egress(egress_id).await {
+            Ok(_)  => { tracing::info!("[livekit] Recording '{}' stopped", egress_id); true }
+            Err(e) => { error!("[livekit] Failed to stop recording '{}': {e}", egress_id); false }
+        }
+    }
+
+    fn recording_path(&self, file: &str) -> Option<PathBuf> {
+        Some(PathBuf::from(&self.config.recordings_dir).join(file))
+    }
+
+    // ── Webhooks ──────────────────────────────────────────────────────────────
+
+    /// LiveKit signs each delivery with a JWT (API key/secret) carrying the body's SHA-256.
+    fn parse_webhook(&self, body: &str, auth: &str) -> Option<MediaEvent> {
+        let receiver = WebhookReceiver::new(
+            TokenVerifier::with_api_key(&self.config.api_key, &self.config.api_secret),
+        );
+        let ev = match receiver.receive(body, auth) {
+            Ok(ev) => ev,
+            Err(e) => {
+                warn!("[livekit/webhook] rejected delivery: {e}");
+                return None;
+            }
+        };
+
+        let room     = ev.room.as_ref().map(|r| r.name.clone()).unwrap_or_default();
+        let identity = ev.participant.as_ref().map(|p| p.identity.clone()).unwrap_or_default();
+        Some(match ev.event.as_str() {
+            "room_started"       => MediaEvent::RoomStarted { room },
+            "participant_joined" => MediaEvent::ParticipantJoined { room, identity },
+            "participant_left"   => MediaEvent::ParticipantLeft { room, identity },
+            "room_finished"      => MediaEvent::RoomFinished { room },
+            "egress_ended"       => MediaEvent::RecordingEnded {
+                recording_id: ev.egress_info.map(|e| e.egress_id).unwrap_or_default(),
+            },
+            other                => MediaEvent::Other { kind: other.to_owned() },
+        })
+    }
+}
+
+// ── Token grants ──────────────────────────────────────────────────────────────
+
+/// Grants for `role`; everything not listed is denied.
+fn grants_for(room_name: &str, role: ParticipantRole, video: bool) -> VideoGrants {
+    let mut grants = VideoGrants {
+        room:             room_name.to_owned(),
+        room_join:        true,
+        can_publish:      false,
+        can_subscribe:    true,
+        can_publish_data: false,
+        ..Default::default()
+    };
+    match role {
+        ParticipantRole::Speaker => {
+            let sources: &[&str] = if video {
+                &["microphone", "camera", "screen_share", "screen_share_audio"]
+            } else {
+                &["microphone"]
+            };
+            grants.can_publish         = true;
+            grants.can_publish_data    = true;
+            grants.can_publish_sources = sources.iter().map(|s| s.to_string()).collect();
+        }
+        ParticipantRole::Listener => {}
+    }
+    grants
+}
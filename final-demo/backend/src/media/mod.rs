@@ -0,0 +1,151 @@
+// src/media/mod.rs — Media backend abstraction.
+//
+// Signaling (ringing, accept, cut…) lives in the handlers; where the audio and
+// video actually flow is up to a MediaBackend:
+//
+//   livekit — SFU rooms on a LiveKit deployment (production)
+//   p2p     — no media server; clients connect directly, SDP/ICE relayed by handlers::webrtc
+//   none    — no-op backend for local dev and tests: every operation succeeds, nothing is sent
+//
+// Selected with media.backend (MEDIA_BACKEND); when unset, LiveKit is used if
+// media.livekit.url is set. See crate::settings.
+
+pub mod livekit;
+pub mod noop;
+pub mod p2p;
+pub mod turn;
+
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    settings::{MediaKind, MediaSettings},
+    types::{CallSession, CallTarget},
+};
+
+// ── Shared types ──────────────────────────────────────────────────────────────
+
+/// What a participant may do in a room. Recording and moderation need no
+/// identity of ours: Egress joins as its own hidden recorder, and mute/kick go
+/// through the server API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticipantRole {
+    /// Publishes the microphone, plus camera and screen share on video calls.
+    Speaker,
+    /// Subscribe-only.
+    Listener,
+}
+
+/// What a client needs to join a room. `media` tells it which transport to use.
+#[derive(Debug, Clone)]
+pub struct JoinCredentials {
+    pub media: &'static str,
+    pub url:   String,
+    pub token: String,
+}
+
+/// Room lifecycle events reported by the media server (see webhook.rs).
+#[derive(Debug, Clone)]
+pub enum MediaEvent {
+    RoomStarted       { room: String },
+    ParticipantJoined { room: String, identity: String },
+    ParticipantLeft   { room: String, identity: String },
+    RoomFinished      { room: String },
+    RecordingEnded    { recording_id: String },
+    Other             { kind: String },
+}
+
+// ── Trait ─────────────────────────────────────────────────────────────────────
+
+#[async_trait]
+pub trait MediaBackend: Send + Sync {
+    /// Short name sent to clients as `media` ("livekit", "p2p", "none").
+    fn name(&self) -> &'static str;
+
+    /// Whether calls may have more than two people (group and ad-hoc calls).
+    fn multi_party(&self) -> bool;
+
+    /// Whether clients exchange SDP / ICE through the webrtc_* socket events.
+    fn relays_signaling(&self) -> bool;
+
+    /// Create a room before anyone joins. Returns false if calls cannot proceed.
+    async fn create_room(&self, room: &str) -> bool;
+
+    /// Tear a room down once its call has ended.
+    async fn delete_room(&self, room: &str);
+
+    /// Credentials for `identity` (user_id) to join `room`.
+    fn join_credentials(
+        &self,
+        room:     &str,
+        identity: &str,
+        call_id:  &str,
+        role:     ParticipantRole,
+        video:    bool,
+    ) -> Option<JoinCredentials>;
+
+    /// Identities currently connected to `room`, hidden participants such as
+    /// the recorder left out. `None` when the backend cannot see the room.
+    async fn list_participants(&self, room: &str) -> Option<Vec<String>>;
+
+    /// Server-side mute of every audio track `identity` publishes.
+    async fn mute_participant(&self, room: &str, identity: &str) -> bool;
+
+    /// Disconnect `identity` from `room`.
+    async fn remove_participant(&self, room: &str, identity: &str) -> bool;
+
+    /// Hold: `identity` may neither publish nor subscribe. Resume restores speaker rights.
+    async fn set_held(&self, room: &str, identity: &str, held: bool, video: bool) -> bool;
+
+    /// Start recording `room` to `file`. Returns a recording id; None if unsupported or failed.
+    async fn start_recording(&self, _room: &str, _file: &str, _video: bool) -> Option<String> { None }
+
+    /// Stop a recording started with `start_recording`.
+    async fn stop_recording(&self, _recording_id: &str) -> bool { false }
+
+    /// Where a finished recording file can be read on this host.
+    fn recording_path(&self, _file: &str) -> Option<PathBuf> { None }
+
+    /// Verify and decode a webhook delivery. None if unsigned, invalid or unsupported.
+    fn parse_webhook(&self, _body: &str, _auth: &str) -> Option<MediaEvent> { None }
+}
+
+/// Build the backend selected by `media.backend`.
+pub fn from_settings(media: &MediaSettings) -> Arc<dyn MediaBackend> {
+    let backend: Arc<dyn MediaBackend> = match media.backend() {
+        MediaKind::LiveKit => Arc::new(livekit::LiveKitBackend::new(livekit::LiveKitConfig::from_settings(&media.livekit))),
+        MediaKind::P2p     => Arc::new(p2p::P2pBackend),
+        MediaKind::Noop    => Arc::new(noop::NoopBackend),
+    };
+    info!("[media] using '{}' backend", backend.name());
+    backend
+}
+
+// ── Room name helpers ─────────────────────────────────────────────────────────
+
+// Every call gets its own room, so a new call between the same people never
+// lands among stragglers of the previous one (rooms linger for empty_timeout).
+
+/// Room name for a 1-to-1 call.
+pub fn dm_room_name(call_id: &str) -> String {
+    format!("call::{call_id}")
+}
+
+/// Room name for a group call.
+pub fn group_room_name(group_id: &str, call_id: &str) -> String {
+    format!("group::{group_id}::{call_id}")
+}
+
+/// Room name of an existing session.
+pub fn session_room_name(session: &CallSession) -> String {
+    match &session.target {
+        // An ad-hoc call keeps the room of the 1-to-1 call it grew out of
+        // A queued call keeps its id once an agent answers, so it gets that room too
+        CallTarget::User(_) | CallTarget::AdHoc(_) | CallTarget::Queue(_) => dm_room_name(&session.call_id),
+        CallTarget::Group(gid) => group_room_name(gid, &session.call_id),
+    }
+}
@@ -0,0 +1,63 @@
+// src/media/noop.rs — Media backend that does nothing.
+//
+// For local development and tests without a media server: rooms are never
+// created, credentials are placeholders and every operation reports success,
+// so the whole signaling flow (ring, accept, cut, moderation) can be exercised.
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use super::{JoinCredentials, MediaBackend, ParticipantRole};
+
+pub struct NoopBackend;
+
+#[async_trait]
+impl MediaBackend for NoopBackend {
+    fn name(&self) -> &'static str { "none" }
+    fn multi_party(&self) -> bool { true }
+    fn relays_signaling(&self) -> bool { false }
+
+    async fn create_room(&self, room: &str) -> bool {
+        debug!("[media/none] create room '{room}'");
+        true
+    }
+
+    async fn delete_room(&self, room: &str) {
+        debug!("[media/none] delete room '{room}'");
+    }
+
+    fn join_credentials(
+        &self,
+        room:     &str,
+        identity: &str,
+        call_id:  &str,
+        role:     ParticipantRole,
+        _video:   bool,
+    ) -> Option<JoinCredentials> {
+        debug!("[media/none] {role:?} credentials for '{identity}' in '{room}'");
+        Some(JoinCredentials {
+            media: self.name(),
+            url:   String::new(),
+            token: format!("noop::{call_id}::{identity}"),
+        })
+    }
+
+    async fn list_participants(&self, _room: &str) -> Option<Vec<String>> {
+        None
+    }
+
+    async fn mute_participant(&self, room: &str, identity: &str) -> bool {
+        debug!("[media/none] mute '{identity}' in '{room}'");
+        true
+    }
+
+    async fn remove_participant(&self, room: &str, identity: &str) -> bool {
+        debug!("[media/none] remove '{identity}' from '{room}'");
+        true
+    }
+
+    async fn set_held(&self, room: &str, identity: &str, held: bool, _video: bool) -> bool {
+        debug!("[media/none] '{identity}' in '{room}' held={held}");
+        true
+    }
+}
@@ -0,0 +1,57 @@
+// src/media/p2p.rs — Peer-to-peer media backend.
+//
+// No media server: the two parties of a 1-to-1 call open an RTCPeerConnection
+// to each other and exchange SDP / ICE through the webrtc_* socket events
+// (handlers::webrtc). There is nothing to create, record or moderate
+// server-side, and group calls are refused since a mesh does not scale.
+
+use async_trait::async_trait;
+use tracing::warn;
+
+use super::{JoinCredentials, MediaBackend, ParticipantRole};
+
+pub struct P2pBackend;
+
+#[async_trait]
+impl MediaBackend for P2pBackend {
+    fn name(&self) -> &'static str { "p2p" }
+
+    /// A mesh does not scale past two peers.
+    fn multi_party(&self) -> bool { false }
+    fn relays_signaling(&self) -> bool { true }
+
+    async fn create_room(&self, _room: &str) -> bool { true }
+
+    async fn delete_room(&self, _room: &str) {}
+
+    /// Nothing to authenticate against — the client only learns to signal over the socket.
+    fn join_credentials(
+        &self,
+        _room:     &str,
+        _identity: &str,
+        _call_id:  &str,
+        _role:     ParticipantRole,
+        _video:    bool,
+    ) -> Option<JoinCredentials> {
+        Some(JoinCredentials { media: self.name(), url: String::new(), token: String::new() })
+    }
+
+    async fn list_participants(&self, _room: &str) -> Option<Vec<String>> {
+        None
+    }
+
+    async fn mute_participant(&self, room: &str, identity: &str) -> bool {
+        warn!("[media/p2p] cannot mute '{identity}' in '{room}' — media never reaches the server");
+        false
+    }
+
+    async fn remove_participant(&self, room: &str, identity: &str) -> bool {
+        warn!("[media/p2p] cannot remove '{identity}' from '{room}' — media never reaches the server");
+        false
+    }
+
+    /// Clients pause their own tracks on CALL_HELD; nothing to enforce here.
+    async fn set_held(&self, _room: &str, _identity: &str, _held: bool, _video: bool) -> bool {
+        true
+    }
+}
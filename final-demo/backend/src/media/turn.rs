@@ -0,0 +1,133 @@
+// src/media/turn.rs — Time-limited TURN credentials (TURN REST API convention).
+//
+// The TURN server (e.g. coturn with `use-auth-secret`) shares a secret with us.
+// A credential is   username   = "{expiry_unix}:{user_id}"
+//                   credential = base64(HMAC-SHA1(secret, username))
+// and the TURN server rejects it once the expiry has passed, so nothing has to
+// be revoked and no per-user state is kept here.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha1::Sha1;
+use tracing::info;
+
+use crate::settings::TurnSettings;
+
+/// One entry of an RTCConfiguration's `iceServers`.
+#[derive(Debug, Serialize, Clone)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+pub struct TurnConfig {
+    pub turn_urls: Vec<String>,   // turn:/turns: URLs sharing the secret
+    pub stun_urls: Vec<String>,   // optional plain STUN servers
+    pub secret:    String,
+    pub ttl_sec:   u64,
+}
+
+impl TurnConfig {
+    /// None unless `media.turn` has both urls and a secret.
+    pub fn from_settings(turn: &TurnSettings) -> Option<Self> {
+        let secret = turn.secret.clone().filter(|s| !s.is_empty())?;
+        if turn.urls.is_empty() { return None; }
+
+        let config = Self {
+            turn_urls: turn.urls.clone(),
+            stun_urls: turn.stun_urls.clone(),
+            secret,
+            ttl_sec:   turn.ttl_sec,
+        };
+        info!("[turn] issuing {}s credentials for {}", config.ttl_sec, config.turn_urls.join(", "));
+        Some(config)
+    }
+
+    /// ICE servers for `user_id`, with a TURN credential valid for `ttl_sec`.
+    pub fn ice_servers_for(&self, user_id: &str) -> Vec<IceServer> {
+        let expiry   = chrono::Utc::now().timestamp() as u64 + self.ttl_sec;
+        let username = format!("{expiry}:{user_id}");
+
+        let mut mac = Hmac::<Sha1>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(username.as_bytes());
+        let credential = STANDARD.encode(mac.finalize().into_bytes());
+
+        let mut servers = Vec::with_capacity(2);
+        if !self.stun_urls.is_empty() {
+            servers.push(IceServer { urls: self.stun_urls.clone(), username: None, credential: None });
+        }
+        servers.push(IceServer {
+            urls:       self.turn_urls.clone(),
+            username:   Some(username),
+            credential: Some(credential),
+        });
+        servers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(urls: &[&str], stun_urls: &[&str], secret: Option<&str>) -> TurnSettings {
+        TurnSettings {
+            urls:      urls.iter().map(|s| s.to_string()).collect(),
+            stun_urls: stun_urls.iter().map(|s| s.to_string()).collect(),
+            secret:    secret.map(str::to_owned),
+            ttl_sec:   600,
+        }
+    }
+
+    #[test]
+    fn turn_needs_urls_and_a_secret() {
+        assert!(TurnConfig::from_settings(&settings(&[], &[], Some("s3cret"))).is_none());
+        assert!(TurnConfig::from_settings(&settings(&["turn:t.example.com"], &[], None)).is_none());
+        assert!(TurnConfig::from_settings(&settings(&["turn:t.example.com"], &[], Some(""))).is_none());
+        assert!(TurnConfig::from_settings(&settings(&["turn:t.example.com"], &[], Some("s3cret"))).is_some());
+    }
+
+    #[test]
+    fn credentials_follow_the_turn_rest_convention() {
+        let config = TurnConfig::from_settings(&settings(&["turn:t.example.com", "turns:t.example.com"], &[], Some("s3cret"))).unwrap();
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        let [turn] = config.ice_servers_for("alice").try_into().unwrap();
+        assert_eq!(turn.urls, ["turn:t.example.com", "turns:t.example.com"]);
+
+        let username = turn.username.unwrap();
+        let (expiry, user) = username.split_once(':').unwrap();
+        assert_eq!(user, "alice");
+        let expiry: u64 = expiry.parse().unwrap();
+        assert!((now + 600..=now + 602).contains(&expiry), "expiry {expiry}, now {now}");
+
+        // What the TURN server recomputes from the shared secret
+        let mut mac = Hmac::<Sha1>::new_from_slice(b"s3cret").unwrap();
+        mac.update(username.as_bytes());
+        mac.verify_slice(&STANDARD.decode(turn.credential.unwrap()).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn stun_servers_come_first_without_credentials() {
+        let config = TurnConfig::from_settings(&settings(&["turn:t.example.com"], &["stun:s.example.com"], Some("s3cret"))).unwrap();
+        let servers = config.ice_servers_for("bob");
+        assert_eq!(servers.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&servers[0]).unwrap(),
+            serde_json::json!({ "urls": ["stun:s.example.com"] }),
+        );
+        assert!(servers[1].username.as_deref().is_some_and(|u| u.ends_with(":bob")));
+    }
+
+    #[test]
+    fn users_get_distinct_credentials() {
+        let config = TurnConfig::from_settings(&settings(&["turn:t.example.com"], &[], Some("s3cret"))).unwrap();
+        let alice = config.ice_servers_for("alice").remove(0).credential;
+        let bob   = config.ice_servers_for("bob").remove(0).credential;
+        assert_ne!(alice, bob);
+    }
+}
@@ -0,0 +1,350 @@
+// src/push.rs — Fan-out of FCM pushes with bounded concurrency.
+//
+// Every handler hands its tokens to one shared PushDispatcher instead of
+// looping over them serially. A single semaphore caps the number of HTTPS
+// requests in flight across all batches, and the shared reqwest client keeps
+// its HTTP/2 connection to FCM warm between batches.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use gcp_auth::TokenProvider;
+use serde::Serialize;
+use tokio::sync::{Notify, Semaphore};
+use tracing::{info, warn};
+
+use crate::{
+    fcm::{self, Push, TokenStatus},
+    types::{Device, Platform, PushKind, RingStrategy, UserMap, UserState},
+};
+
+pub const DEFAULT_MAX_CONCURRENCY: usize = 32;
+
+/// One device to deliver to. `user_id` is needed to evict the device if FCM reports it dead.
+#[derive(Debug, Clone)]
+pub struct PushTarget {
+    pub user_id: String,
+    pub device:  Device,
+}
+
+/// Every FCM device of `user` whose preferences accept this kind of push.
+pub fn targets_for(user: &UserState, push: &Push) -> Vec<PushTarget> {
+    user.devices.iter()
+        .filter(|d| d.push_kind == PushKind::Fcm)
+        .filter(|d| if push.is_call() { d.prefs.calls } else { d.prefs.messages })
+        .map(|d| PushTarget { user_id: user.user_id.clone(), device: d.clone() })
+        .collect()
+}
+
+/// Devices to ring `after_sec` into a 1-to-1 call.
+#[derive(Debug, Clone)]
+pub struct RingStage {
+    pub after_sec: u64,
+    pub targets:   Vec<PushTarget>,
+}
+
+/// The devices `user` is rung on for `push`, staged by their ring strategy.
+/// With no tab open there is nothing to wait for, so the first stage goes
+/// out straight away.
+pub fn ring_stages(user: &UserState, push: &Push) -> Vec<RingStage> {
+    let targets = targets_for(user, push);
+    let mut stages = match user.call_prefs.ring_strategy {
+        RingStrategy::All => vec![RingStage { after_sec: 0, targets }],
+        RingStrategy::DesktopFirst { delay_sec } => {
+            let (desktop, mobile) = targets.into_iter().partition(|t| t.device.platform == Platform::Web);
+            vec![
+                RingStage { after_sec: 0,         targets: desktop },
+                RingStage { after_sec: delay_sec, targets: mobile },
+            ]
+        }
+        RingStrategy::Sequential { step_sec } => {
+            let mut levels: BTreeMap<u8, Vec<PushTarget>> = BTreeMap::new();
+            for target in targets {
+                levels.entry(target.device.prefs.ring_priority).or_default().push(target);
+            }
+            levels.into_values().zip(0..)
+                .map(|(targets, i)| RingStage { after_sec: i * step_sec, targets })
+                .collect()
+        }
+    };
+    stages.retain(|s| !s.targets.is_empty());
+
+    if !user.is_online() {
+        let first = stages.first().map_or(0, |s| s.after_sec);
+        for stage in &mut stages { stage.after_sec -= first; }
+    }
+    stages
+}
+
+/// Outcome of one batch (same counters as `SendResponse` in notification-using-fcm-token).
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BatchResult {
+    pub success_count: u32,
+    pub failure_count: u32,
+    pub evicted_count: u32,
+}
+
+/// Counters since startup, served by `GET /push/metrics`.
+#[derive(Debug, Serialize)]
+pub struct PushMetricsSnapshot {
+    pub sent:            u64,
+    pub failed:          u64,
+    pub evicted:         u64,
+    pub in_flight:       usize,
+    pub max_concurrency: usize,
+    pub avg_latency_ms:  f64,
+    pub max_latency_ms:  u64,
+}
+
+#[derive(Debug, Default)]
+struct PushMetrics {
+    sent:             AtomicU64,
+    failed:           AtomicU64,
+    evicted:          AtomicU64,
+    latency_total_ms: AtomicU64,
+    latency_max_ms:   AtomicU64,
+}
+
+pub struct PushDispatcher {
+    auth:            Arc<dyn TokenProvider>,
+    http:            reqwest::Client,
+    users:           UserMap,
+    permits:         Semaphore,
+    max_concurrency: usize,
+    metrics:         PushMetrics,
+    batches:         AtomicUsize,   // dispatched batches still sending
+    idle:            Notify,        // woken whenever one finishes
+}
+
+impl PushDispatcher {
+    pub fn new(
+        auth:            Arc<dyn TokenProvider>,
+        http:            reqwest::Client,
+        users:           UserMap,
+        max_concurrency: usize,
+    ) -> Self {
+        let max_concurrency = max_concurrency.max(1);
+        Self {
+            auth,
+            http,
+            users,
+            permits: Semaphore::new(max_concurrency),
+            max_concurrency,
+            metrics: PushMetrics::default(),
+            batches: AtomicUsize::new(0),
+            idle:    Notify::new(),
+        }
+    }
+
+    /// Sends `push` to every target in a background task.
+    pub fn dispatch(self: &Arc<Self>, push: Push, targets: Vec<PushTarget>) {
+        if targets.is_empty() { return; }
+        let this = self.clone();
+        this.batches.fetch_add(1, Ordering::AcqRel);
+        tokio::spawn(async move {
+            this.send_batch(push, targets).await;
+            this.batches.fetch_sub(1, Ordering::AcqRel);
+            this.idle.notify_waiters();
+        });
+    }
+
+    /// Waits up to `deadline` for every dispatched batch to finish. Returns
+    /// how many were still sending when it gave up.
+    pub async fn drain(&self, deadline: Duration) -> usize {
+        let all_sent = async {
+            loop {
+                // Registered before the check, so a batch finishing in between still wakes us
+                let finished = self.idle.notified();
+                if self.batches.load(Ordering::Acquire) == 0 { return; }
+                finished.await;
+            }
+        };
+        let _ = tokio::time::timeout(deadline, all_sent).await;
+        self.batches.load(Ordering::Acquire)
+    }
+
+    /// Sends `push` to every target and waits for all of them.
+    /// Dead tokens are evicted from the user map before returning.
+    pub async fn send_batch(&self, push: Push, targets: Vec<PushTarget>) -> BatchResult {
+        let label   = push.label();
+        let started = Instant::now();
+
+        let mut pending: FuturesUnordered<_> = targets.into_iter()
+            .map(|target| self.send_one(&push, target))
+            .collect();
+
+        let mut result = BatchResult::default();
+        while let Some((target, status)) = pending.next().await {
+            match status {
+                TokenStatus::Ok     => result.success_count += 1,
+                TokenStatus::Failed => result.failure_count += 1,
+                TokenStatus::Evict  => {
+                    result.failure_count += 1;
+                    result.evicted_count += 1;
+                    self.evict(&target).await;
+                }
+            }
+        }
+
+        info!(
+            "[push/{label}] batch done in {}ms — {} ok, {} failed ({} evicted)",
+            started.elapsed().as_millis(),
+            result.success_count, result.failure_count, result.evicted_count,
+        );
+        result
+    }
+
+    pub fn metrics(&self) -> PushMetricsSnapshot {
+        let m       = &self.metrics;
+        let sent    = m.sent.load(Ordering::Relaxed);
+        let failed  = m.failed.load(Ordering::Relaxed);
+        let evicted = m.evicted.load(Ordering::Relaxed);
+        let total   = sent + failed + evicted;
+        PushMetricsSnapshot {
+            sent,
+            failed,
+            evicted,
+            in_flight:       self.max_concurrency - self.permits.available_permits(),
+            max_concurrency: self.max_concurrency,
+            avg_latency_ms:  if total == 0 { 0.0 }
+                             else { m.latency_total_ms.load(Ordering::Relaxed) as f64 / total as f64 },
+            max_latency_ms:  m.latency_max_ms.load(Ordering::Relaxed),
+        }
+    }
+
+    // ── Internal helpers ──────────────────────────────────────────────────────
+
+    async fn send_one(&self, push: &Push, target: PushTarget) -> (PushTarget, TokenStatus) {
+        // The semaphore is never closed, so acquire() cannot fail
+        let Ok(_permit) = self.permits.acquire().await else {
+            return (target, TokenStatus::Failed);
+        };
+        let sent_at = Instant::now();
+        let status  = fcm::send(&target.device, push, self.auth.as_ref(), &self.http).await;
+        self.record(status, sent_at.elapsed());
+        (target, status)
+    }
+
+    fn record(&self, status: TokenStatus, latency: Duration) {
+        let m = &self.metrics;
+        let counter = match status {
+            TokenStatus::Ok     => &m.sent,
+            TokenStatus::Failed => &m.failed,
+            TokenStatus::Evict  => &m.evicted,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let ms = latency.as_millis() as u64;
+        m.latency_total_ms.fetch_add(ms, Ordering::Relaxed);
+        m.latency_max_ms.fetch_max(ms, Ordering::Relaxed);
+    }
+
+    async fn evict(&self, target: &PushTarget) {
+        let mut map = self.users.write().await;
+        if let Some(u) = map.get_mut(&target.user_id) {
+            let before = u.devices.len();
+            u.devices.retain(|d| d.token != target.device.token);
+            if u.devices.len() < before {
+                warn!(
+                    "[fcm] evicted device '{}' of '{}' — dead token ({} devices remaining)",
+                    target.device.device_id, target.user_id, u.devices.len()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use socketioxide::socket::Sid;
+
+    use super::*;
+    use crate::types::DevicePrefs;
+
+    fn device(id: &str, platform: Platform, ring_priority: u8) -> Device {
+        let now = chrono::Utc::now();
+        Device {
+            device_id:    id.into(),
+            platform,
+            push_kind:    PushKind::Fcm,
+            token:        format!("token-{id}"),
+            api_key:      String::new(),
+            app_version:  None,
+            prefs:        DevicePrefs { ring_priority, ..DevicePrefs::default() },
+            created_at:   now,
+            refreshed_at: now,
+        }
+    }
+
+    /// `bob` with a browser, a phone and a tablet, rung with `strategy`.
+    fn bob(strategy: RingStrategy, online: bool) -> UserState {
+        let mut user = UserState::new("bob");
+        user.call_prefs.ring_strategy = strategy;
+        user.devices = vec![
+            device("web",    Platform::Web,     0),
+            device("phone",  Platform::Android, 1),
+            device("tablet", Platform::Ios,     2),
+        ];
+        if online { user.socket_ids.push(Sid::new()); }
+        user
+    }
+
+    fn call() -> Push {
+        Push::Call { call_id: "c1".into(), from: "alice".into(), to: "bob".into(), video: false, ring_sec: 30 }
+    }
+
+    /// (after_sec, device ids) of every stage.
+    fn plan(stages: &[RingStage]) -> Vec<(u64, Vec<&str>)> {
+        stages.iter()
+            .map(|s| (s.after_sec, s.targets.iter().map(|t| t.device.device_id.as_str()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn all_rings_every_device_at_once() {
+        let stages = ring_stages(&bob(RingStrategy::All, true), &call());
+        assert_eq!(plan(&stages), [(0, vec!["web", "phone", "tablet"])]);
+    }
+
+    #[test]
+    fn desktop_first_holds_phones_back() {
+        let stages = ring_stages(&bob(RingStrategy::DesktopFirst { delay_sec: 10 }, true), &call());
+        assert_eq!(plan(&stages), [(0, vec!["web"]), (10, vec!["phone", "tablet"])]);
+    }
+
+    #[test]
+    fn sequential_follows_ring_priority() {
+        let mut user = bob(RingStrategy::Sequential { step_sec: 5 }, true);
+        user.devices[2].prefs.ring_priority = 0;
+        let stages = ring_stages(&user, &call());
+        assert_eq!(plan(&stages), [(0, vec!["web", "tablet"]), (5, vec!["phone"])]);
+    }
+
+    #[test]
+    fn offline_users_skip_the_wait_for_a_missing_stage() {
+        let mut user = bob(RingStrategy::DesktopFirst { delay_sec: 10 }, false);
+        user.devices.retain(|d| d.platform != Platform::Web);
+        let stages = ring_stages(&user, &call());
+        assert_eq!(plan(&stages), [(0, vec!["phone", "tablet"])]);
+
+        // Online, the tabs ring first and the phones still wait their turn
+        user.socket_ids.push(Sid::new());
+        assert_eq!(plan(&ring_stages(&user, &call())), [(10, vec!["phone", "tablet"])]);
+    }
+
+    #[test]
+    fn devices_refusing_calls_are_left_out() {
+        let mut user = bob(RingStrategy::Sequential { step_sec: 5 }, true);
+        user.devices[0].prefs.calls = false;
+        user.devices[1].push_kind   = PushKind::WebPush;
+        // Levels left without devices take no turn
+        assert_eq!(plan(&ring_stages(&user, &call())), [(0, vec!["tablet"])]);
+    }
+}
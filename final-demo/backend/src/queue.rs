@@ -0,0 +1,202 @@
+// src/queue.rs — Call queue definitions and who is waiting in them.
+//
+// Queues are read once at startup from `storage.queues_file` (JSON array of
+// QueueDef, default "queues.json"); without the file there are none. A queued caller is
+// a Ringing CallSession keyed "queue::{call_id}" (see handlers::queue); this
+// store only keeps the order callers joined each queue in, plus what the
+// strategies need: the round-robin cursor and when each agent was last busy.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::Path,
+};
+use tokio::{sync::Mutex, time::Instant};
+use tracing::{error, info, warn};
+
+use crate::types::{QueueDef, QueueStrategy, MAX_RING_TIMEOUT_SEC, MIN_RING_TIMEOUT_SEC};
+
+#[derive(Default)]
+struct Inner {
+    waiting:   HashMap<String, VecDeque<String>>,   // queue_id → call ids, longest waiting first
+    offered:   HashMap<String, Instant>,            // call_id → when its agents started ringing
+    cursor:    HashMap<String, usize>,              // queue_id → member index offered last (round robin)
+    last_busy: HashMap<String, Instant>,            // agent → last seen on a call (longest idle)
+}
+
+pub struct Queues {
+    defs:  HashMap<String, QueueDef>,
+    inner: Mutex<Inner>,
+}
+
+impl Queues {
+    /// Loads `path` if it exists. Queues without an id or members are
+    /// skipped; timings are brought within bounds.
+    pub fn load(path: &Path) -> Self {
+        let stored: Vec<QueueDef> = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                error!("[queue] ignoring unreadable {}: {e}", path.display());
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        let mut defs = HashMap::new();
+        for mut def in stored {
+            if def.queue_id.trim().is_empty() || def.members.is_empty() {
+                warn!("[queue] skipping queue '{}' without an id or members", def.queue_id);
+                continue;
+            }
+            // A member listed twice would be offered every call twice; keep the first listing
+            let mut seen = HashSet::new();
+            def.members.retain(|m| seen.insert(m.clone()));
+            def.agent_ring_sec = def.agent_ring_sec.clamp(MIN_RING_TIMEOUT_SEC, MAX_RING_TIMEOUT_SEC);
+            def.max_wait_sec   = def.max_wait_sec.max(def.agent_ring_sec);
+            def.max_queued     = def.max_queued.max(1);
+            defs.insert(def.queue_id.clone(), def);
+        }
+        info!("[queue] {} queues loaded from {}", defs.len(), path.display());
+        Self { defs, inner: Mutex::new(Inner::default()) }
+    }
+
+    pub fn get(&self, queue_id: &str) -> Option<&QueueDef> {
+        self.defs.get(queue_id)
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &QueueDef> {
+        self.defs.values()
+    }
+
+    /// Puts `call_id` at the back of `queue_id`. Its 1-based position, or
+    /// None when the queue is full.
+    pub async fn join(&self, queue_id: &str, call_id: &str) -> Option<usize> {
+        let max = self.defs.get(queue_id)?.max_queued;
+        let mut inner = self.inner.lock().await;
+        let line = inner.waiting.entry(queue_id.to_owned()).or_default();
+        if line.len() >= max { return None; }
+        line.push_back(call_id.to_owned());
+        Some(line.len())
+    }
+
+    /// Takes `call_id` out of `queue_id`, wherever it stands.
+    pub async fn leave(&self, queue_id: &str, call_id: &str) {
+        let mut inner = self.inner.lock().await;
+        if let Some(line) = inner.waiting.get_mut(queue_id) {
+            line.retain(|c| c != call_id);
+        }
+        inner.offered.remove(call_id);
+    }
+
+    /// Call ids waiting in `queue_id`, longest waiting first.
+    pub async fn waiting(&self, queue_id: &str) -> Vec<String> {
+        self.inner.lock().await.waiting.get(queue_id)
+            .map(|line| line.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Of `available` (members of `def` free to take a call), the agents to
+    /// offer the next caller to, as `def.strategy` picks them.
+    pub async fn pick(&self, def: &QueueDef, available: &[String]) -> Vec<String> {
+        if available.is_empty() { return Vec::new(); }
+        let mut inner = self.inner.lock().await;
+        match def.strategy {
+            QueueStrategy::RingAll => available.to_vec(),
+            QueueStrategy::LongestIdle => {
+                // Never seen on a call counts as idle the longest
+                let agent = available.iter()
+                    .min_by_key(|a| inner.last_busy.get(*a).copied())
+                    .cloned();
+                agent.into_iter().collect()
+            }
+            QueueStrategy::RoundRobin => {
+                let last = inner.cursor.get(&def.queue_id).copied();
+                let n = def.members.len();
+                let start = last.map_or(0, |i| i + 1);
+                let next = (0..n)
+                    .map(|i| (start + i) % n)
+                    .find(|i| available.contains(&def.members[*i]));
+                let Some(index) = next else { return Vec::new(); };
+                inner.cursor.insert(def.queue_id.clone(), index);
+                vec![def.members[index].clone()]
+            }
+        }
+    }
+
+    /// Records that `call_id` has just been offered to agents.
+    pub async fn offered(&self, call_id: &str) {
+        self.inner.lock().await.offered.insert(call_id.to_owned(), Instant::now());
+    }
+
+    /// Whether the agents ringing for `call_id` have rung for `ring_sec`.
+    pub async fn offer_expired(&self, call_id: &str, ring_sec: u64) -> bool {
+        self.inner.lock().await.offered.get(call_id)
+            .is_some_and(|at| at.elapsed().as_secs() >= ring_sec)
+    }
+
+    /// Records that `agent` is on a call right now.
+    pub async fn busy(&self, agent: &str) {
+        self.inner.lock().await.last_busy.insert(agent.to_owned(), Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Queues read from a file holding `json`.
+    fn load(json: &str) -> Queues {
+        let path = std::env::temp_dir().join(format!("queues-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, json).unwrap();
+        let queues = Queues::load(&path);
+        let _ = std::fs::remove_file(&path);
+        queues
+    }
+
+    fn queue(strategy: &str) -> Queues {
+        load(&format!(r#"[{{"queue_id":"support","name":"Support","members":["a","b","c"],"strategy":"{strategy}"}}]"#))
+    }
+
+    fn agents(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn ring_all_offers_every_available_agent() {
+        let queues = queue("ring_all");
+        let def = queues.get("support").unwrap();
+        assert_eq!(queues.pick(def, &agents(&["c", "a"])).await, ["c", "a"]);
+        assert!(queues.pick(def, &[]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn longest_idle_prefers_agents_never_on_a_call() {
+        let queues = queue("longest_idle");
+        let def = queues.get("support").unwrap();
+        queues.busy("a").await;
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        queues.busy("b").await;
+
+        assert_eq!(queues.pick(def, &agents(&["a", "b", "c"])).await, ["c"]);
+        assert_eq!(queues.pick(def, &agents(&["b", "a"])).await, ["a"]);
+    }
+
+    #[tokio::test]
+    async fn round_robin_resumes_after_the_last_offer() {
+        let queues = queue("round_robin");
+        let def = queues.get("support").unwrap();
+        let all = agents(&["a", "b", "c"]);
+        assert_eq!(queues.pick(def, &all).await, ["a"]);
+        assert_eq!(queues.pick(def, &all).await, ["b"]);
+        assert_eq!(queues.pick(def, &agents(&["a", "b"])).await, ["a"]);   // c is away: wrap round
+        assert_eq!(queues.pick(def, &all).await, ["b"]);
+        assert!(queues.pick(def, &agents(&["z"])).await.is_empty());
+        assert_eq!(queues.pick(def, &all).await, ["c"]);
+    }
+
+    #[test]
+    fn repeated_members_keep_their_first_place() {
+        let queues = load(r#"[{"queue_id":"support","name":"Support","members":["b","a","b","c","a"]}]"#);
+        assert_eq!(queues.get("support").unwrap().members, ["b", "a", "c"]);
+    }
+}
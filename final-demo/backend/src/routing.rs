@@ -0,0 +1,138 @@
+// src/routing.rs — Which tab carries each side of a 1-to-1 call.
+//
+// A user may have several tabs open, but a 1-to-1 call is carried by one tab
+// per party: the caller's is the tab that placed it (`caller_socket_id`), the
+// callee's the tab that answered (`callee_socket_id`). Until the callee
+// answers, the call rings on every tab they have open.
+//
+// Signalling — credentials, CALL_ACCEPTED, CALL_ENDED, hold/resume, WebRTC —
+// goes to the carrying tab only. A party's other tabs get the lightweight
+// CALL_ON_OTHER_DEVICE when the call starts there and
+// CALL_ON_OTHER_DEVICE_ENDED when it goes away: enough to show "call in
+// progress elsewhere", cancel or hang up from there, or pull the call over
+// with transfer_call_to_device.
+
+use serde::Serialize;
+use socketioxide::{extract::SocketRef, socket::Sid, SocketIo};
+
+use crate::types::{
+    event, CallEndedPayload, CallOnOtherDeviceEndedPayload, CallOnOtherDevicePayload, CallSession,
+    CallStatus, UserMap,
+};
+
+/// Anything sockets can be looked up through: the socket handling the
+/// current event, or the server handle in HTTP routes and timers.
+pub trait Sockets {
+    fn socket(&self, sid: Sid) -> Option<SocketRef>;
+}
+
+impl Sockets for SocketRef {
+    fn socket(&self, sid: Sid) -> Option<SocketRef> {
+        if sid == self.id { Some(self.clone()) } else { self.broadcast().get_socket(sid) }
+    }
+}
+
+impl Sockets for SocketIo {
+    fn socket(&self, sid: Sid) -> Option<SocketRef> {
+        self.get_socket(sid)
+    }
+}
+
+// ── Lookup ────────────────────────────────────────────────────────────────────
+
+/// The tab carrying `user`'s side of `session`. None for a callee who has not
+/// answered yet, and for anyone who is not a party.
+pub fn bound_socket(session: &CallSession, user: &str) -> Option<Sid> {
+    if session.caller == user {
+        Some(session.caller_socket_id)
+    } else if session.target.id() == user {
+        session.callee_socket_id
+    } else {
+        None
+    }
+}
+
+/// The tabs carrying `session`, one per party that has one.
+pub fn bound_sockets(session: &CallSession) -> Vec<Sid> {
+    std::iter::once(session.caller_socket_id).chain(session.callee_socket_id).collect()
+}
+
+/// The party of `session` that is not `user`.
+pub fn other_party(session: &CallSession, user: &str) -> String {
+    if session.caller == user { session.target.id().to_owned() } else { session.caller.clone() }
+}
+
+/// What `user`'s other tabs are told about `session`.
+pub fn elsewhere_payload(session: &CallSession, user: &str) -> CallOnOtherDevicePayload {
+    CallOnOtherDevicePayload {
+        call_id: session.call_id.clone(),
+        peer:    other_party(session, user),
+        video:   session.video,
+        ringing: session.status == CallStatus::Ringing,
+    }
+}
+
+// ── Delivery ──────────────────────────────────────────────────────────────────
+
+pub fn emit_to<T: Serialize>(via: &impl Sockets, sids: &[Sid], ev: &'static str, payload: &T) {
+    for sid in sids {
+        if let Some(peer) = via.socket(*sid) {
+            let _ = peer.emit(ev, payload);
+        }
+    }
+}
+
+/// Sends `ev` to the tab carrying `user`'s side of `session` — or to all of
+/// their tabs while it is still ringing them.
+pub async fn emit_to_side<T: Serialize>(
+    via:     &impl Sockets,
+    users:   &UserMap,
+    session: &CallSession,
+    user:    &str,
+    ev:      &'static str,
+    payload: &T,
+) {
+    let sids = match bound_socket(session, user) {
+        Some(sid) => vec![sid],
+        None      => tabs_of(users, user).await,
+    };
+    emit_to(via, &sids, ev, payload);
+}
+
+/// Sends `ev` to the tabs of `user` that do not carry `session`.
+pub async fn emit_elsewhere<T: Serialize>(
+    via:     &impl Sockets,
+    users:   &UserMap,
+    session: &CallSession,
+    user:    &str,
+    ev:      &'static str,
+    payload: &T,
+) {
+    let Some(bound) = bound_socket(session, user) else { return; };
+    let sids: Vec<Sid> = tabs_of(users, user).await.into_iter().filter(|sid| *sid != bound).collect();
+    emit_to(via, &sids, ev, payload);
+}
+
+/// CALL_ON_OTHER_DEVICE to the tabs of `user` that do not carry `session`.
+pub async fn announce_elsewhere(via: &impl Sockets, users: &UserMap, session: &CallSession, user: &str) {
+    emit_elsewhere(via, users, session, user, event::CALL_ON_OTHER_DEVICE,
+        &elsewhere_payload(session, user)).await;
+}
+
+/// CALL_ON_OTHER_DEVICE_ENDED to the tabs of `user` that do not carry `session`.
+pub async fn release_elsewhere(via: &impl Sockets, users: &UserMap, session: &CallSession, user: &str) {
+    emit_elsewhere(via, users, session, user, event::CALL_ON_OTHER_DEVICE_ENDED,
+        &CallOnOtherDeviceEndedPayload { call_id: session.call_id.clone() }).await;
+}
+
+/// Ends `session` for `user`: CALL_ENDED on the tab carrying it,
+/// CALL_ON_OTHER_DEVICE_ENDED on the rest.
+pub async fn end_for(via: &impl Sockets, users: &UserMap, session: &CallSession, user: &str, reason: &str) {
+    emit_to_side(via, users, session, user, event::CALL_ENDED,
+        &CallEndedPayload { reason: reason.to_owned() }).await;
+    release_elsewhere(via, users, session, user).await;
+}
+
+async fn tabs_of(users: &UserMap, user: &str) -> Vec<Sid> {
+    users.read().await.get(user).map(|u| u.socket_ids.clone()).unwrap_or_default()
+}
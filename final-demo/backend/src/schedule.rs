@@ -0,0 +1,136 @@
+// src/schedule.rs — Store and timer wheel for scheduled calls.
+//
+// Every scheduled call is kept in memory and mirrored to `storage.schedule_file`
+// (JSON, default "scheduled_calls.json") after each change, so bookings survive a
+// restart. Timers are not persisted themselves: the wheel is rebuilt from the
+// stored calls on startup, and anything that fell due while the server was
+// down fires on the first tick.
+//
+// The wheel maps a due unix second to the timers firing then. Removing or
+// changing a call leaves its timers in place; they are checked against the
+// stored call when they fire and dropped if stale.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::types::ScheduledCall;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimerKind { Reminder, Start }
+
+#[derive(Debug, Clone)]
+pub struct Timer {
+    pub schedule_id: String,
+    pub kind:        TimerKind,
+}
+
+#[derive(Default)]
+struct Inner {
+    calls: HashMap<String, ScheduledCall>,   // schedule_id → call
+    wheel: BTreeMap<i64, Vec<Timer>>,        // due unix second → timers
+}
+
+pub struct Scheduler {
+    path:  PathBuf,
+    inner: Mutex<Inner>,
+}
+
+impl Scheduler {
+    /// Loads `path` if it exists and arms the timers of every call in it.
+    pub fn open(path: PathBuf) -> Self {
+        let stored: Vec<ScheduledCall> = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                error!("[schedule] ignoring unreadable {}: {e}", path.display());
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        let mut inner = Inner::default();
+        for call in stored {
+            arm(&mut inner.wheel, &call);
+            inner.calls.insert(call.schedule_id.clone(), call);
+        }
+        info!("[schedule] {} scheduled calls loaded from {}", inner.calls.len(), path.display());
+        Self { path, inner: Mutex::new(inner) }
+    }
+
+    pub async fn insert(&self, call: ScheduledCall) {
+        let mut inner = self.inner.lock().await;
+        arm(&mut inner.wheel, &call);
+        inner.calls.insert(call.schedule_id.clone(), call);
+        self.save(&inner).await;
+    }
+
+    pub async fn remove(&self, schedule_id: &str) -> Option<ScheduledCall> {
+        let mut inner = self.inner.lock().await;
+        let call = inner.calls.remove(schedule_id)?;
+        self.save(&inner).await;
+        Some(call)
+    }
+
+    pub async fn get(&self, schedule_id: &str) -> Option<ScheduledCall> {
+        self.inner.lock().await.calls.get(schedule_id).cloned()
+    }
+
+    /// Calls `user_id` created or is invited to, soonest first.
+    pub async fn for_user(&self, user_id: &str) -> Vec<ScheduledCall> {
+        let inner = self.inner.lock().await;
+        let mut calls: Vec<ScheduledCall> = inner.calls.values()
+            .filter(|c| c.created_by == user_id || c.attendees.iter().any(|a| a == user_id))
+            .cloned()
+            .collect();
+        calls.sort_by_key(|c| c.start_at);
+        calls
+    }
+
+    /// Marks the reminder of `schedule_id` as sent. None if it already was,
+    /// or the call is gone.
+    pub async fn mark_reminded(&self, schedule_id: &str) -> Option<ScheduledCall> {
+        let mut inner = self.inner.lock().await;
+        let call = inner.calls.get_mut(schedule_id).filter(|c| !c.reminded)?;
+        call.reminded = true;
+        let call = call.clone();
+        self.save(&inner).await;
+        Some(call)
+    }
+
+    /// Takes every timer due at or before `now` (unix seconds), in due order.
+    pub async fn due(&self, now: i64) -> Vec<Timer> {
+        let mut inner = self.inner.lock().await;
+        let later = inner.wheel.split_off(&(now + 1));
+        std::mem::replace(&mut inner.wheel, later).into_values().flatten().collect()
+    }
+
+    // Write to a sibling file, then rename, so a crash never leaves half a file.
+    async fn save(&self, inner: &Inner) {
+        let mut calls: Vec<&ScheduledCall> = inner.calls.values().collect();
+        calls.sort_by_key(|c| c.start_at);
+        let bytes = match serde_json::to_vec_pretty(&calls) {
+            Ok(b)  => b,
+            Err(e) => { error!("[schedule] serialize failed: {e}"); return; }
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        if let Err(e) = tokio::fs::write(&tmp, &bytes).await {
+            warn!("[schedule] could not write {}: {e}", tmp.display());
+            return;
+        }
+        if let Err(e) = tokio::fs::rename(&tmp, &self.path).await {
+            warn!("[schedule] could not replace {}: {e}", self.path.display());
+        }
+    }
+}
+
+fn arm(wheel: &mut BTreeMap<i64, Vec<Timer>>, call: &ScheduledCall) {
+    let start = call.start_at.timestamp();
+    if call.remind_before > 0 && !call.reminded {
+        wheel.entry(start - call.remind_before as i64 * 60).or_default()
+            .push(Timer { schedule_id: call.schedule_id.clone(), kind: TimerKind::Reminder });
+    }
+    wheel.entry(start).or_default()
+        .push(Timer { schedule_id: call.schedule_id.clone(), kind: TimerKind::Start });
+}
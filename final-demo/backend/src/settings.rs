@@ -0,0 +1,592 @@
+// src/settings.rs — Server settings: config file, environment overrides, validation.
+//
+// Read once at startup from a TOML file — `--config <path>`, else CONFIG_FILE,
+// else "config.toml" when there is one — with every field defaulted, then
+// overridden by the environment variables the server has always read
+// (LIVEKIT_URL, RING_TIMEOUT_SEC, …), so an env-only deployment keeps working.
+// Every problem is reported at once before anything starts; `--check-config`
+// stops right there. See config.example.toml for every key.
+//
+// Startup code is handed its section; leaf helpers (fcm, snapshot, voicemail,
+// shutdown) read the process-wide copy through `get()`.
+
+use std::{
+    fmt::Display,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::{
+    push::DEFAULT_MAX_CONCURRENCY,
+    types::{DEFAULT_RING_TIMEOUT_SEC, MAX_RING_TIMEOUT_SEC, MIN_RING_TIMEOUT_SEC},
+};
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+const USAGE: &str = "usage: backend [--config <path>] [--check-config]";
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+// ── Sections ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub server:   ServerSettings,
+    pub fcm:      FcmSettings,
+    pub calls:    CallSettings,
+    pub media:    MediaSettings,
+    pub storage:  StorageSettings,
+    pub shutdown: ShutdownSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub bind: SocketAddr,   // BIND_ADDR
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self { bind: SocketAddr::from(([127, 0, 0, 1], 3001)) }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FcmSettings {
+    pub project_id:      String,            // FCM_PROJECT_ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials:     Option<PathBuf>,   // GOOGLE_APPLICATION_CREDENTIALS — service-account JSON
+    pub max_concurrency: usize,             // FCM_MAX_CONCURRENCY
+    pub preview_chars:   usize,             // FCM_PREVIEW_CHARS — chat text shown in a push
+}
+
+impl Default for FcmSettings {
+    fn default() -> Self {
+        Self {
+            project_id:      "notification-25684".into(),
+            credentials:     None,
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            preview_chars:   200,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CallSettings {
+    pub ring_timeout_sec:       u64,   // RING_TIMEOUT_SEC
+    pub group_ring_timeout_sec: u64,   // GROUP_RING_TIMEOUT_SEC
+}
+
+impl Default for CallSettings {
+    fn default() -> Self {
+        Self { ring_timeout_sec: DEFAULT_RING_TIMEOUT_SEC, group_ring_timeout_sec: DEFAULT_RING_TIMEOUT_SEC }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaKind {
+    #[serde(rename = "livekit")]
+    LiveKit,
+    #[serde(rename = "p2p")]
+    P2p,
+    #[serde(rename = "none")]
+    Noop,
+}
+
+impl FromStr for MediaKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "livekit" => Ok(Self::LiveKit),
+            "p2p"     => Ok(Self::P2p),
+            "none"    => Ok(Self::Noop),
+            _         => Err("expected livekit, p2p or none".into()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MediaSettings {
+    /// MEDIA_BACKEND. When unset, LiveKit if `livekit.url` is set, else none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<MediaKind>,
+    pub livekit: LiveKitSettings,
+    pub turn:    TurnSettings,
+}
+
+impl MediaSettings {
+    pub fn backend(&self) -> MediaKind {
+        self.backend.unwrap_or(if self.livekit.url.is_some() { MediaKind::LiveKit } else { MediaKind::Noop })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LiveKitSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url:               Option<String>,   // LIVEKIT_URL — ws://, sent to clients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_url:           Option<String>,   // LIVEKIT_API_URL — http://, room management
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub egress_url:        Option<String>,   // LIVEKIT_EGRESS_URL — defaults to api_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key:           Option<String>,   // LIVEKIT_API_KEY
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "redact")]
+    pub api_secret:        Option<String>,   // LIVEKIT_API_SECRET
+    pub egress_output_dir: String,           // EGRESS_OUTPUT_DIR
+    pub recordings_dir:    String,           // RECORDINGS_DIR
+    pub empty_timeout_sec: u32,              // LIVEKIT_EMPTY_TIMEOUT_SEC — empty rooms are deleted after this
+    pub max_participants:  u32,              // LIVEKIT_MAX_PARTICIPANTS — per room
+}
+
+impl Default for LiveKitSettings {
+    fn default() -> Self {
+        Self {
+            url:               None,
+            api_url:           None,
+            egress_url:        None,
+            api_key:           None,
+            api_secret:        None,
+            egress_output_dir: "/out".into(),
+            recordings_dir:    "recordings".into(),
+            empty_timeout_sec: 300,
+            max_participants:  50,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TurnSettings {
+    pub urls:      Vec<String>,      // TURN_URLS (comma separated) — empty disables TURN
+    pub stun_urls: Vec<String>,      // STUN_URLS
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "redact")]
+    pub secret:    Option<String>,   // TURN_SECRET
+    pub ttl_sec:   u64,              // TURN_TTL_SEC
+}
+
+impl Default for TurnSettings {
+    fn default() -> Self {
+        Self { urls: Vec::new(), stun_urls: Vec::new(), secret: None, ttl_sec: 60 * 60 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    pub state_file:    PathBuf,   // STATE_FILE
+    pub schedule_file: PathBuf,   // SCHEDULE_FILE
+    pub queues_file:   PathBuf,   // QUEUES_FILE
+    pub voicemail_dir: PathBuf,   // VOICEMAIL_DIR
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            state_file:    "state.json".into(),
+            schedule_file: "scheduled_calls.json".into(),
+            queues_file:   "queues.json".into(),
+            voicemail_dir: "voicemail".into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownSettings {
+    pub keep_rooms: bool,   // SHUTDOWN_KEEP_ROOMS
+    pub drain_sec:  u64,    // SHUTDOWN_DRAIN_SEC
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self { keep_rooms: false, drain_sec: 10 }
+    }
+}
+
+// ── Command line ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub config: Option<PathBuf>,
+    pub check:  bool,
+}
+
+impl Cli {
+    pub fn parse() -> Result<Self, String> {
+        let mut cli  = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--check-config" => cli.check = true,
+                "--config"       => cli.config = Some(args.next().ok_or(USAGE)?.into()),
+                _                => return Err(format!("unknown argument '{arg}'\n{USAGE}")),
+            }
+        }
+        Ok(cli)
+    }
+}
+
+// ── Loading ───────────────────────────────────────────────────────────────────
+
+impl Settings {
+    /// Reads the config file, applies the environment and validates the
+    /// result. Also returns the file read, if any; on failure, every error.
+    pub fn load(explicit: Option<&Path>) -> Result<(Self, Option<PathBuf>), Vec<String>> {
+        let explicit = explicit.map(Path::to_path_buf)
+            .or_else(|| env("CONFIG_FILE").map(PathBuf::from));
+
+        let (mut settings, file) = match &explicit {
+            Some(path) => (read(path)?, Some(path.clone())),
+            None => {
+                let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if path.is_file() { (read(&path)?, Some(path)) } else { (Self::default(), None) }
+            }
+        };
+
+        let mut errors = Vec::new();
+        settings.apply_env(&mut errors);
+        settings.validate(&mut errors);
+        if errors.is_empty() { Ok((settings, file)) } else { Err(errors) }
+    }
+
+    /// The effective settings as TOML, secrets masked.
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap_or_else(|e| format!("# cannot render settings: {e}\n"))
+    }
+
+    fn apply_env(&mut self, errors: &mut Vec<String>) {
+        let mut o = Overrides { errors };
+        o.value("BIND_ADDR",                      &mut self.server.bind);
+        o.value("FCM_PROJECT_ID",                 &mut self.fcm.project_id);
+        o.some("GOOGLE_APPLICATION_CREDENTIALS",  &mut self.fcm.credentials);
+        o.value("FCM_MAX_CONCURRENCY",            &mut self.fcm.max_concurrency);
+        o.value("FCM_PREVIEW_CHARS",              &mut self.fcm.preview_chars);
+        o.value("RING_TIMEOUT_SEC",               &mut self.calls.ring_timeout_sec);
+        o.value("GROUP_RING_TIMEOUT_SEC",         &mut self.calls.group_ring_timeout_sec);
+        o.some("MEDIA_BACKEND",                   &mut self.media.backend);
+        o.some("LIVEKIT_URL",                     &mut self.media.livekit.url);
+        o.some("LIVEKIT_API_URL",                 &mut self.media.livekit.api_url);
+        o.some("LIVEKIT_EGRESS_URL",              &mut self.media.livekit.egress_url);
+        o.some("LIVEKIT_API_KEY",                 &mut self.media.livekit.api_key);
+        o.some("LIVEKIT_API_SECRET",              &mut self.media.livekit.api_secret);
+        o.value("EGRESS_OUTPUT_DIR",              &mut self.media.livekit.egress_output_dir);
+        o.value("RECORDINGS_DIR",                 &mut self.media.livekit.recordings_dir);
+        o.value("LIVEKIT_EMPTY_TIMEOUT_SEC",      &mut self.media.livekit.empty_timeout_sec);
+        o.value("LIVEKIT_MAX_PARTICIPANTS",       &mut self.media.livekit.max_participants);
+        o.list("TURN_URLS",                       &mut self.media.turn.urls);
+        o.list("STUN_URLS",                       &mut self.media.turn.stun_urls);
+        o.some("TURN_SECRET",                     &mut self.media.turn.secret);
+        o.value("TURN_TTL_SEC",                   &mut self.media.turn.ttl_sec);
+        o.value("STATE_FILE",                     &mut self.storage.state_file);
+        o.value("SCHEDULE_FILE",                  &mut self.storage.schedule_file);
+        o.value("QUEUES_FILE",                    &mut self.storage.queues_file);
+        o.value("VOICEMAIL_DIR",                  &mut self.storage.voicemail_dir);
+        o.flag("SHUTDOWN_KEEP_ROOMS",             &mut self.shutdown.keep_rooms);
+        o.value("SHUTDOWN_DRAIN_SEC",             &mut self.shutdown.drain_sec);
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        let mut fail = |key: &str, msg: String| errors.push(format!("{key}: {msg}"));
+
+        // FCM
+        if self.fcm.project_id.trim().is_empty() {
+            fail("fcm.project_id", "must not be empty".into());
+        }
+        match &self.fcm.credentials {
+            None => fail("fcm.credentials", "must be set (or GOOGLE_APPLICATION_CREDENTIALS)".into()),
+            Some(path) if !path.is_file() => fail("fcm.credentials", format!("no such file {}", path.display())),
+            Some(path) => if let Err(e) = gcp_auth::CustomServiceAccount::from_file(path) {
+                fail("fcm.credentials", format!("{} is not a service-account JSON: {e}", path.display()));
+            },
+        }
+        if self.fcm.max_concurrency == 0 {
+            fail("fcm.max_concurrency", "must be at least 1".into());
+        }
+        if self.fcm.preview_chars == 0 {
+            fail("fcm.preview_chars", "must be at least 1".into());
+        }
+
+        // Calls
+        for (key, sec) in [
+            ("calls.ring_timeout_sec",       self.calls.ring_timeout_sec),
+            ("calls.group_ring_timeout_sec", self.calls.group_ring_timeout_sec),
+        ] {
+            if !(MIN_RING_TIMEOUT_SEC..=MAX_RING_TIMEOUT_SEC).contains(&sec) {
+                fail(key, format!("must be between {MIN_RING_TIMEOUT_SEC} and {MAX_RING_TIMEOUT_SEC} (got {sec})"));
+            }
+        }
+
+        // Media
+        let lk = &self.media.livekit;
+        if self.media.backend() == MediaKind::LiveKit {
+            for (key, value) in [
+                ("media.livekit.url",        &lk.url),
+                ("media.livekit.api_url",    &lk.api_url),
+                ("media.livekit.api_key",    &lk.api_key),
+                ("media.livekit.api_secret", &lk.api_secret),
+            ] {
+                if value.as_deref().is_none_or(|v| v.trim().is_empty()) {
+                    fail(key, "required by the livekit backend".into());
+                }
+            }
+            if let Some(url) = &lk.url && !(url.starts_with("ws://") || url.starts_with("wss://")) {
+                fail("media.livekit.url", format!("must be a ws:// or wss:// URL (got {url})"));
+            }
+            for (key, value) in [("media.livekit.api_url", &lk.api_url), ("media.livekit.egress_url", &lk.egress_url)] {
+                if let Some(url) = value && !(url.starts_with("http://") || url.starts_with("https://")) {
+                    fail(key, format!("must be an http:// or https:// URL (got {url})"));
+                }
+            }
+            if lk.max_participants < 2 {
+                fail("media.livekit.max_participants", format!("must be at least 2 (got {})", lk.max_participants));
+            }
+        }
+
+        let turn = &self.media.turn;
+        if !turn.urls.is_empty() && turn.secret.as_deref().is_none_or(str::is_empty) {
+            fail("media.turn.secret", "required when media.turn.urls is set".into());
+        }
+        if let Some(url) = turn.urls.iter().find(|u| !(u.starts_with("turn:") || u.starts_with("turns:"))) {
+            fail("media.turn.urls", format!("expected turn: or turns: URLs (got {url})"));
+        }
+        if let Some(url) = turn.stun_urls.iter().find(|u| !(u.starts_with("stun:") || u.starts_with("stuns:"))) {
+            fail("media.turn.stun_urls", format!("expected stun: or stuns: URLs (got {url})"));
+        }
+        if turn.ttl_sec < 60 {
+            fail("media.turn.ttl_sec", format!("must be at least 60 (got {})", turn.ttl_sec));
+        }
+    }
+}
+
+/// Makes `settings` the process-wide copy returned by `get()`.
+pub fn init(settings: Settings) -> &'static Settings {
+    SETTINGS.get_or_init(|| settings)
+}
+
+/// The settings loaded at startup.
+pub fn get() -> &'static Settings {
+    SETTINGS.get().expect("settings::init runs before anything reads them")
+}
+
+fn read(path: &Path) -> Result<Settings, Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| vec![format!("{}: {e}", path.display())])?;
+    toml::from_str(&text).map_err(|e| vec![format!("{}: {e}", path.display())])
+}
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key).ok().map(|v| v.trim().to_owned()).filter(|v| !v.is_empty())
+}
+
+fn redact<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(_) => serializer.serialize_str("********"),
+        None    => serializer.serialize_none(),
+    }
+}
+
+// ── Environment overrides ─────────────────────────────────────────────────────
+
+/// Applies set, non-empty variables; values that do not parse become errors.
+struct Overrides<'a> {
+    errors: &'a mut Vec<String>,
+}
+
+impl Overrides<'_> {
+    fn parse<T: FromStr>(&mut self, key: &str, raw: &str) -> Option<T>
+    where
+        T::Err: Display,
+    {
+        raw.parse()
+            .map_err(|e| self.errors.push(format!("{key}: {e} (got \"{raw}\")")))
+            .ok()
+    }
+
+    fn value<T: FromStr>(&mut self, key: &str, field: &mut T)
+    where
+        T::Err: Display,
+    {
+        if let Some(raw) = env(key) && let Some(v) = self.parse(key, &raw) { *field = v; }
+    }
+
+    fn some<T: FromStr>(&mut self, key: &str, field: &mut Option<T>)
+    where
+        T::Err: Display,
+    {
+        if let Some(raw) = env(key) && let Some(v) = self.parse(key, &raw) { *field = Some(v); }
+    }
+
+    fn list(&mut self, key: &str, field: &mut Vec<String>) {
+        if let Some(raw) = env(key) {
+            *field = raw.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_owned).collect();
+        }
+    }
+
+    fn flag(&mut self, key: &str, field: &mut bool) {
+        match env(key).as_deref() {
+            None                  => {}
+            Some("true" | "1")    => *field = true,
+            Some("false" | "0")   => *field = false,
+            Some(raw)             => self.errors.push(format!("{key}: expected true or false (got \"{raw}\")")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Tests that touch the process environment take turns.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn errors_of(settings: &Settings) -> Vec<String> {
+        let mut errors = Vec::new();
+        settings.validate(&mut errors);
+        errors
+    }
+
+    fn has(errors: &[String], key: &str) -> bool {
+        errors.iter().any(|e| e.starts_with(&format!("{key}: ")))
+    }
+
+    /// Applies `vars` over the settings parsed from `file`, then unsets them.
+    fn with_env(file: &str, vars: &[(&str, &str)]) -> (Settings, Vec<String>) {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        let mut settings: Settings = toml::from_str(file).unwrap();
+        let mut errors = Vec::new();
+        // SAFETY: every test reading these variables holds ENV
+        unsafe { for (k, v) in vars { std::env::set_var(k, v); } }
+        settings.apply_env(&mut errors);
+        unsafe { for (k, _) in vars { std::env::remove_var(k); } }
+        (settings, errors)
+    }
+
+    // ── Validation ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn defaults_only_lack_fcm_credentials() {
+        assert_eq!(errors_of(&Settings::default()), ["fcm.credentials: must be set (or GOOGLE_APPLICATION_CREDENTIALS)"]);
+    }
+
+    #[test]
+    fn ring_timeouts_are_bounded() {
+        let mut settings = Settings::default();
+        settings.calls.ring_timeout_sec       = MIN_RING_TIMEOUT_SEC - 1;
+        settings.calls.group_ring_timeout_sec = MAX_RING_TIMEOUT_SEC + 1;
+        let errors = errors_of(&settings);
+        assert!(has(&errors, "calls.ring_timeout_sec"));
+        assert!(has(&errors, "calls.group_ring_timeout_sec"));
+
+        settings.calls.ring_timeout_sec       = MIN_RING_TIMEOUT_SEC;
+        settings.calls.group_ring_timeout_sec = MAX_RING_TIMEOUT_SEC;
+        assert!(!has(&errors_of(&settings), "calls.ring_timeout_sec"));
+    }
+
+    #[test]
+    fn livekit_needs_its_keys_and_url_schemes() {
+        let mut settings = Settings::default();
+        settings.media.backend = Some(MediaKind::LiveKit);
+        let errors = errors_of(&settings);
+        for key in ["media.livekit.url", "media.livekit.api_url", "media.livekit.api_key", "media.livekit.api_secret"] {
+            assert!(has(&errors, key), "{key} missing from {errors:?}");
+        }
+
+        let lk = &mut settings.media.livekit;
+        lk.url        = Some("https://livekit.example.com".into());
+        lk.api_url    = Some("wss://livekit.example.com".into());
+        lk.egress_url = Some("livekit.example.com".into());
+        lk.api_key    = Some("key".into());
+        lk.api_secret = Some("secret".into());
+        let errors = errors_of(&settings);
+        assert!(has(&errors, "media.livekit.url"));
+        assert!(has(&errors, "media.livekit.api_url"));
+        assert!(has(&errors, "media.livekit.egress_url"));
+        assert!(!has(&errors, "media.livekit.api_key"));
+    }
+
+    #[test]
+    fn livekit_keys_are_not_checked_for_other_backends() {
+        let mut settings = Settings::default();
+        settings.media.backend = Some(MediaKind::P2p);
+        settings.media.livekit.max_participants = 0;
+        assert!(!errors_of(&settings).iter().any(|e| e.starts_with("media.livekit")));
+    }
+
+    #[test]
+    fn turn_urls_need_a_secret_and_their_scheme() {
+        let mut settings = Settings::default();
+        settings.media.turn.urls      = vec!["stun:turn.example.com".into()];
+        settings.media.turn.stun_urls = vec!["turn:turn.example.com".into()];
+        settings.media.turn.ttl_sec   = 59;
+        let errors = errors_of(&settings);
+        for key in ["media.turn.secret", "media.turn.urls", "media.turn.stun_urls", "media.turn.ttl_sec"] {
+            assert!(has(&errors, key), "{key} missing from {errors:?}");
+        }
+    }
+
+    // ── Environment overrides ─────────────────────────────────────────────────
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let file = "[calls]\nring_timeout_sec = 40\ngroup_ring_timeout_sec = 45\n[media.turn]\nurls = [\"turn:a\"]\n";
+        let (settings, errors) = with_env(file, &[
+            ("RING_TIMEOUT_SEC",    " 50 "),
+            ("TURN_URLS",           "turn:b, ,turns:c"),
+            ("SHUTDOWN_KEEP_ROOMS", "1"),
+        ]);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(settings.calls.ring_timeout_sec, 50);
+        assert_eq!(settings.calls.group_ring_timeout_sec, 45);
+        assert_eq!(settings.media.turn.urls, ["turn:b", "turns:c"]);
+        assert!(settings.shutdown.keep_rooms);
+    }
+
+    #[test]
+    fn empty_variables_leave_the_file_value() {
+        let (settings, errors) = with_env("[calls]\nring_timeout_sec = 40\n", &[("RING_TIMEOUT_SEC", "  ")]);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(settings.calls.ring_timeout_sec, 40);
+    }
+
+    #[test]
+    fn unparsable_variables_are_reported() {
+        let (settings, errors) = with_env("[calls]\nring_timeout_sec = 40\n", &[
+            ("RING_TIMEOUT_SEC",    "soon"),
+            ("MEDIA_BACKEND",       "sfu"),
+            ("SHUTDOWN_KEEP_ROOMS", "yes"),
+        ]);
+        assert_eq!(settings.calls.ring_timeout_sec, 40);
+        assert!(settings.media.backend.is_none());
+        assert!(errors.iter().any(|e| e.starts_with("RING_TIMEOUT_SEC: ") && e.ends_with("(got \"soon\")")), "{errors:?}");
+        assert!(errors.contains(&"MEDIA_BACKEND: expected livekit, p2p or none (got \"sfu\")".to_owned()));
+        assert!(errors.contains(&"SHUTDOWN_KEEP_ROOMS: expected true or false (got \"yes\")".to_owned()));
+    }
+
+    // ── Rendering ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn secrets_are_redacted() {
+        let mut settings = Settings::default();
+        settings.media.livekit.api_key    = Some("devkey".into());
+        settings.media.livekit.api_secret = Some("livekit-secret".into());
+        settings.media.turn.secret        = Some("turn-secret".into());
+        let rendered = settings.to_toml();
+        assert!(!rendered.contains("livekit-secret") && !rendered.contains("turn-secret"), "{rendered}");
+        assert_eq!(rendered.matches("\"********\"").count(), 2, "{rendered}");
+        assert!(rendered.contains("api_key = \"devkey\""));
+
+        // Unset secrets are left out rather than masked
+        assert!(!Settings::default().to_toml().contains("********"));
+    }
+}
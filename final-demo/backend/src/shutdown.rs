@@ -0,0 +1,135 @@
+// src/shutdown.rs — Graceful shutdown on SIGTERM / Ctrl-C.
+//
+// `run` is handed to axum's graceful shutdown. Once a signal arrives:
+//   1. `draining` is set — call, group_call, call_queue and scheduled starts
+//      are refused from here on;
+//   2. every socket gets SERVER_SHUTDOWN with a reconnect hint;
+//   3. every call is ended and its media room deleted. With
+//      `shutdown.keep_rooms`, calls already in progress are instead handed
+//      off to the media server: nobody is told they ended, and the rooms close
+//      once their last participant leaves;
+//   4. the sockets are closed, so axum has no connection left to wait for;
+//   5. pushes still in flight get `shutdown.drain_sec` (default 10) to go out;
+//   6. state is saved (crate::snapshot).
+
+use std::{sync::atomic::Ordering, time::Duration};
+
+use socketioxide::SocketIo;
+use tracing::{info, warn};
+
+use crate::{
+    handlers::{call::dismiss_ringing, group_call::members_of},
+    media::session_room_name,
+    routing, settings, snapshot, stats,
+    types::{
+        event, AppState, CallCancelledPayload, CallOutcome, CallSession, CallStatus, CallTarget,
+        GroupCallEndedPayload, ServerShutdownPayload,
+    },
+    webhook::emit_to_users,
+};
+
+/// Clients wait this long before reconnecting, giving the next instance time to come up.
+const RECONNECT_AFTER_MS: u64 = 3_000;
+/// Lets the last events reach the clients before their sockets are closed.
+const FLUSH_MS: u64 = 500;
+const REASON: &str = "Server restarting";
+
+/// Whether shutdown has begun; new calls are refused while it has.
+pub fn draining(state: &AppState) -> bool {
+    state.draining.load(Ordering::Acquire)
+}
+
+/// Resolves on SIGTERM or Ctrl-C, once everything above is done.
+pub async fn run(state: AppState, io: SocketIo) {
+    signal().await;
+    info!("[shutdown] signal received — draining");
+    state.draining.store(true, Ordering::Release);
+
+    let config     = &settings::get().shutdown;
+    let keep_media = config.keep_rooms;
+    let _ = io.emit(event::SERVER_SHUTDOWN, &ServerShutdownPayload {
+        reason:             REASON.into(),
+        reconnect_after_ms: RECONNECT_AFTER_MS,
+        keep_media,
+    });
+
+    let sessions: Vec<(String, CallSession)> = state.calls.write().await.drain().collect();
+    let count = sessions.len();
+    for (key, session) in sessions {
+        end_call(&state, &io, &key, session, keep_media).await;
+    }
+    info!("[shutdown] {count} calls {}", if keep_media { "ended or handed off" } else { "ended" });
+
+    tokio::time::sleep(Duration::from_millis(FLUSH_MS)).await;
+    io.close().await;
+
+    let drain_sec = config.drain_sec;
+    match state.push.drain(Duration::from_secs(drain_sec)).await {
+        0       => info!("[shutdown] push queue drained"),
+        pending => warn!("[shutdown] gave up on {pending} push batches after {drain_sec}s"),
+    }
+
+    snapshot::save(&state).await;
+    info!("[shutdown] done");
+}
+
+async fn signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => { sigterm.recv().await; }
+            Err(e)          => { warn!("[shutdown] cannot listen for SIGTERM: {e}"); std::future::pending::<()>().await; }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c    => {}
+        _ = terminate => {}
+    }
+}
+
+/// Ends `session` (already out of CallMap) for everyone in it, or with
+/// `keep_media` leaves a call in progress running in its room.
+async fn end_call(state: &AppState, io: &SocketIo, key: &str, session: CallSession, keep_media: bool) {
+    let in_progress = session.status != CallStatus::Ringing;
+    let hand_off    = keep_media && in_progress;
+    let outcome     = if hand_off { CallOutcome::Completed } else { CallOutcome::Dropped };
+    stats::ended(&state.history, &session.call_id, outcome).await;
+    if hand_off { return; }
+
+    match &session.target {
+        CallTarget::User(callee) if in_progress => {
+            for party in [&session.caller, callee] {
+                routing::end_for(io, &state.users, &session, party, REASON).await;
+            }
+        }
+        CallTarget::User(callee) => {
+            routing::end_for(io, &state.users, &session, &session.caller, REASON).await;
+            let tabs = state.users.read().await.get(callee).map(|u| u.socket_ids.clone()).unwrap_or_default();
+            for sid in tabs {
+                if let Some(peer) = io.get_socket(sid) {
+                    dismiss_ringing(&peer, key, callee, &session.call_id, REASON);
+                }
+            }
+        }
+        CallTarget::Group(group_id) | CallTarget::AdHoc(group_id) => {
+            let members = members_of(state, &session).await;
+            emit_to_users(io, &state.users, members.iter().map(String::as_str), event::GROUP_CALL_ENDED,
+                &GroupCallEndedPayload { group_id: group_id.clone(), reason: REASON.into() }).await;
+        }
+        CallTarget::Queue(_) => {
+            routing::end_for(io, &state.users, &session, &session.caller, REASON).await;
+            emit_to_users(io, &state.users, session.invited.iter().map(String::as_str), event::CALL_CANCELLED,
+                &CallCancelledPayload { by: session.caller.clone() }).await;
+        }
+    }
+
+    if in_progress {
+        state.media.delete_room(&session_room_name(&session)).await;
+    }
+}
@@ -0,0 +1,166 @@
+// src/snapshot.rs — State carried over a restart.
+//
+// On a graceful shutdown (crate::shutdown) groups, chat history, call history
+// and every user's devices and call prefs are written to `storage.state_file`
+// (JSON, default "state.json"); startup reads them back. Sockets, calls, queues and
+// voicemail offers are not kept: by then every call has been ended and
+// clients reconnect on their own. Scheduled calls have their own file
+// (crate::schedule).
+
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    settings,
+    types::{
+        new_api_key, AppState, CallPrefs, CallRecord, Device, DevicePrefs, Group, Platform,
+        PushKind, StoredMessage, UserState,
+    },
+};
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub users:    Vec<SavedUser>,
+    #[serde(default)]
+    pub groups:   Vec<Group>,
+    #[serde(default)]
+    pub messages: HashMap<String, Vec<StoredMessage>>,
+    #[serde(default)]
+    pub history:  Vec<CallRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedUser {
+    pub user_id:    String,
+    pub devices:    Vec<SavedDevice>,
+    pub call_prefs: CallPrefs,
+}
+
+/// `Device` with its push token and API key, which `Device` itself never serializes.
+#[derive(Serialize, Deserialize)]
+pub struct SavedDevice {
+    pub device_id:    String,
+    pub platform:     Platform,
+    pub push_kind:    PushKind,
+    pub token:        String,
+    #[serde(default)]
+    pub api_key:      String,
+    pub app_version:  Option<String>,
+    pub prefs:        DevicePrefs,
+    pub created_at:   DateTime<Utc>,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+impl From<&Device> for SavedDevice {
+    fn from(d: &Device) -> Self {
+        Self {
+            device_id:    d.device_id.clone(),
+            platform:     d.platform,
+            push_kind:    d.push_kind,
+            token:        d.token.clone(),
+            api_key:      d.api_key.clone(),
+            app_version:  d.app_version.clone(),
+            prefs:        d.prefs,
+            created_at:   d.created_at,
+            refreshed_at: d.refreshed_at,
+        }
+    }
+}
+
+impl From<SavedDevice> for Device {
+    fn from(d: SavedDevice) -> Self {
+        Self {
+            device_id:    d.device_id,
+            platform:     d.platform,
+            push_kind:    d.push_kind,
+            token:        d.token,
+            // Snapshots from before API keys: a fresh key, handed out on the next registration
+            api_key:      if d.api_key.is_empty() { new_api_key() } else { d.api_key },
+            app_version:  d.app_version,
+            prefs:        d.prefs,
+            created_at:   d.created_at,
+            refreshed_at: d.refreshed_at,
+        }
+    }
+}
+
+impl Snapshot {
+    /// Reads the state file; empty when there is none.
+    pub fn load() -> Self {
+        let path = path();
+        let snapshot: Self = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                error!("[snapshot] ignoring unreadable {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        info!(
+            "[snapshot] restored {} users, {} groups, {} conversations, {} call records from {}",
+            snapshot.users.len(), snapshot.groups.len(), snapshot.messages.len(),
+            snapshot.history.len(), path.display(),
+        );
+        snapshot
+    }
+
+    /// Users as registered, all offline until they reconnect.
+    pub fn take_users(&mut self) -> HashMap<String, UserState> {
+        std::mem::take(&mut self.users).into_iter()
+            .map(|u| {
+                let mut state = UserState::new(u.user_id.clone());
+                state.devices    = u.devices.into_iter().map(Device::from).collect();
+                state.call_prefs = u.call_prefs;
+                (u.user_id, state)
+            })
+            .collect()
+    }
+
+    pub fn take_groups(&mut self) -> HashMap<String, Group> {
+        std::mem::take(&mut self.groups).into_iter().map(|g| (g.group_id.clone(), g)).collect()
+    }
+
+    pub fn take_history(&mut self) -> HashMap<String, CallRecord> {
+        std::mem::take(&mut self.history).into_iter().map(|r| (r.call_id.clone(), r)).collect()
+    }
+}
+
+/// Writes the current state to the state file — to a sibling file first, then
+/// renamed, so a crash never leaves half a file.
+pub async fn save(state: &AppState) {
+    let snapshot = Snapshot {
+        users: state.users.read().await.values()
+            .map(|u| SavedUser {
+                user_id:    u.user_id.clone(),
+                devices:    u.devices.iter().map(SavedDevice::from).collect(),
+                call_prefs: u.call_prefs.clone(),
+            })
+            .collect(),
+        groups:   state.groups.read().await.values().cloned().collect(),
+        messages: state.messages.read().await.clone(),
+        history:  state.history.read().await.values().cloned().collect(),
+    };
+
+    let path = path();
+    let bytes = match serde_json::to_vec(&snapshot) {
+        Ok(b)  => b,
+        Err(e) => { error!("[snapshot] serialize failed: {e}"); return; }
+    };
+    let tmp = path.with_extension("json.tmp");
+    if let Err(e) = tokio::fs::write(&tmp, &bytes).await {
+        warn!("[snapshot] could not write {}: {e}", tmp.display());
+        return;
+    }
+    if let Err(e) = tokio::fs::rename(&tmp, &path).await {
+        warn!("[snapshot] could not replace {}: {e}", path.display());
+        return;
+    }
+    info!("[snapshot] state saved to {} ({} bytes)", path.display(), bytes.len());
+}
+
+fn path() -> PathBuf {
+    settings::get().storage.state_file.clone()
+}
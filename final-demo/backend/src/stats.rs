@@ -0,0 +1,173 @@
+// src/stats.rs — Call timing and quality statistics.
+//
+// Handlers stamp the call history as a call progresses: `joined` when the
+// callee or a member picks up, `ended` when its session goes away. Clients
+// add their end-of-call quality reports through call_quality_report.
+// `GET /stats/calls` (api.rs) folds the history into `CallStats`, once for
+// the requesting user and once for the whole server.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::types::{CallHistory, CallOutcome, CallRecord};
+
+// ── Recording ─────────────────────────────────────────────────────────────────
+
+/// Records that `users` joined `call_id`, alongside its caller. The first
+/// join marks the call answered.
+pub async fn joined(history: &CallHistory, call_id: &str, users: &[&str]) {
+    let mut history = history.write().await;
+    let Some(record) = history.get_mut(call_id) else { return; };
+    record.answered_at.get_or_insert_with(Utc::now);
+
+    let caller = record.caller.clone();
+    for uid in std::iter::once(caller.as_str()).chain(users.iter().copied()) {
+        if !record.participants.iter().any(|p| p == uid) {
+            record.participants.push(uid.to_owned());
+        }
+    }
+}
+
+/// Records that `agent` took queued `call_id`: from then on it is a 1-to-1
+/// call with the agent, so the record names them instead of the queue.
+pub async fn taken_by(history: &CallHistory, call_id: &str, agent: &str) {
+    let mut history = history.write().await;
+    let Some(record) = history.get_mut(call_id) else { return; };
+    record.target   = agent.to_owned();
+    record.is_group = false;
+}
+
+/// Records how `call_id` ended; only the first end counts. A call that was
+/// never answered can neither complete nor drop, so those count as cancelled.
+pub async fn ended(history: &CallHistory, call_id: &str, outcome: CallOutcome) {
+    let mut history = history.write().await;
+    let Some(record) = history.get_mut(call_id) else { return; };
+    if record.ended_at.is_some() { return; }
+
+    record.ended_at = Some(Utc::now());
+    record.outcome  = Some(match outcome {
+        CallOutcome::Completed | CallOutcome::Dropped if record.answered_at.is_none() => CallOutcome::Cancelled,
+        other => other,
+    });
+}
+
+// ── Aggregates ────────────────────────────────────────────────────────────────
+
+/// Body of `GET /stats/calls`.
+#[derive(Debug, Serialize)]
+pub struct CallStatsReport {
+    pub user_id: String,
+    pub user:    CallStats,
+    pub global:  CallStats,
+}
+
+/// Aggregates over finished calls. Averages are None when nothing was measured.
+#[derive(Debug, Default, Serialize)]
+pub struct CallStats {
+    pub calls:                  usize,
+    pub answered:               usize,
+    pub completed:              usize,
+    pub dropped:                usize,
+    pub missed:                 usize,
+    pub rejected:               usize,
+    pub cancelled:              usize,
+    pub drop_rate_pct:          Option<f64>,   // dropped / answered
+    pub avg_duration_sec:       Option<f64>,   // answered → ended
+    pub avg_ring_to_answer_sec: Option<f64>,
+    pub quality:                QualityStats,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct QualityStats {
+    pub reports:             usize,
+    pub avg_rtt_ms:          Option<f64>,
+    pub avg_jitter_ms:       Option<f64>,
+    pub avg_packet_loss_pct: Option<f64>,
+    pub avg_mos:             Option<f64>,
+    pub by_network:          BTreeMap<String, usize>,   // reports per network type
+}
+
+pub fn report(history: &HashMap<String, CallRecord>, user_id: &str) -> CallStatsReport {
+    CallStatsReport {
+        user_id: user_id.to_owned(),
+        user:    CallStats::of(history.values().filter(|r| involves(r, user_id)), Some(user_id)),
+        global:  CallStats::of(history.values(), None),
+    }
+}
+
+impl CallStats {
+    /// Folds the finished calls among `records`. With `reporter` set, only
+    /// that user's quality reports are counted.
+    fn of<'a>(records: impl IntoIterator<Item = &'a CallRecord>, reporter: Option<&str>) -> Self {
+        let mut stats = Self::default();
+        let (mut duration, mut ring) = (Mean::default(), Mean::default());
+        let (mut rtt, mut jitter, mut loss, mut mos) = (Mean::default(), Mean::default(), Mean::default(), Mean::default());
+
+        for record in records {
+            let (Some(outcome), Some(ended_at)) = (record.outcome, record.ended_at) else { continue; };
+            stats.calls += 1;
+            match outcome {
+                CallOutcome::Completed => stats.completed += 1,
+                CallOutcome::Dropped   => stats.dropped   += 1,
+                CallOutcome::Missed    => stats.missed    += 1,
+                CallOutcome::Rejected  => stats.rejected  += 1,
+                CallOutcome::Cancelled => stats.cancelled += 1,
+            }
+            if let Some(answered_at) = record.answered_at {
+                stats.answered += 1;
+                duration.add(Some(seconds(ended_at - answered_at)));
+                ring.add(Some(seconds(answered_at - record.started_at)));
+            }
+
+            for q in record.quality.iter().filter(|q| reporter.is_none_or(|u| q.user_id == u)) {
+                stats.quality.reports += 1;
+                rtt.add(q.rtt_ms);
+                jitter.add(q.jitter_ms);
+                loss.add(q.packet_loss_pct);
+                mos.add(q.mos);
+                if let Some(network) = &q.network_type {
+                    *stats.quality.by_network.entry(network.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        if stats.answered > 0 {
+            stats.drop_rate_pct = Some(round2(stats.dropped as f64 * 100.0 / stats.answered as f64));
+        }
+        stats.avg_duration_sec            = duration.get();
+        stats.avg_ring_to_answer_sec      = ring.get();
+        stats.quality.avg_rtt_ms          = rtt.get();
+        stats.quality.avg_jitter_ms       = jitter.get();
+        stats.quality.avg_packet_loss_pct = loss.get();
+        stats.quality.avg_mos             = mos.get();
+        stats
+    }
+}
+
+/// Calls `user_id` placed, was rung for, or took part in.
+fn involves(record: &CallRecord, user_id: &str) -> bool {
+    record.caller == user_id
+        || (!record.is_group && record.target == user_id)
+        || record.participants.iter().any(|p| p == user_id)
+}
+
+#[derive(Default)]
+struct Mean { sum: f64, n: usize }
+
+impl Mean {
+    fn add(&mut self, value: Option<f64>) {
+        if let Some(v) = value {
+            self.sum += v;
+            self.n   += 1;
+        }
+    }
+    fn get(&self) -> Option<f64> {
+        (self.n > 0).then(|| round2(self.sum / self.n as f64))
+    }
+}
+
+fn seconds(d: chrono::TimeDelta) -> f64 { d.num_milliseconds() as f64 / 1000.0 }
+
+fn round2(v: f64) -> f64 { (v * 100.0).round() / 100.0 }
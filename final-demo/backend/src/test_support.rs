@@ -0,0 +1,97 @@
+// src/test_support.rs — Fixtures shared by the unit tests.
+//
+// An AppState wired to in-memory stores and a chosen media backend, with no
+// FCM credentials and nothing on disk until a test writes there.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use async_trait::async_trait;
+use socketioxide::{extract::SocketRef, socket::Sid, SocketIo};
+use tokio::sync::RwLock;
+
+use crate::{
+    media::MediaBackend,
+    push::PushDispatcher,
+    queue::Queues,
+    schedule::Scheduler,
+    settings::{self, Settings},
+    types::{AppState, CallRecord, CallSession, CallStatus, CallTarget, RingTimeouts},
+};
+
+/// Token provider for tests: every push fails before reaching FCM.
+struct NoAuth;
+
+#[async_trait]
+impl gcp_auth::TokenProvider for NoAuth {
+    async fn token(&self, _scopes: &[&str]) -> Result<Arc<gcp_auth::Token>, gcp_auth::Error> {
+        Err(gcp_auth::Error::Str("no FCM in tests"))
+    }
+
+    async fn project_id(&self) -> Result<Arc<str>, gcp_auth::Error> {
+        Err(gcp_auth::Error::Str("no FCM in tests"))
+    }
+}
+
+pub fn state_with_media(media: Arc<dyn MediaBackend>) -> AppState {
+    settings::init(Settings::default());
+
+    let users = Arc::new(RwLock::new(HashMap::new()));
+    let push  = PushDispatcher::new(Arc::new(NoAuth), reqwest::Client::new(), Arc::clone(&users), 1);
+    let defaults = Settings::default();
+    AppState {
+        users,
+        groups:    Arc::new(RwLock::new(HashMap::new())),
+        calls:     Arc::new(RwLock::new(HashMap::new())),
+        messages:  Arc::new(RwLock::new(HashMap::new())),
+        history:   Arc::new(RwLock::new(HashMap::new())),
+        push:      Arc::new(push),
+        media,
+        turn:      None,
+        schedule:  Arc::new(Scheduler::open(std::env::temp_dir().join(format!("schedule-{}.json", uuid::Uuid::new_v4())))),
+        ring:      RingTimeouts::from_settings(&defaults.calls),
+        voicemail: Arc::new(RwLock::new(HashMap::new())),
+        queues:    Arc::new(Queues::load(Path::new(""))),
+        draining:  Arc::new(AtomicBool::new(false)),
+    }
+}
+
+/// A Socket.IO handle with no clients: emits go nowhere.
+pub fn io() -> SocketIo {
+    let (_, io) = SocketIo::new_layer();
+    io.ns("/", |_: SocketRef| {});
+    io
+}
+
+/// A session for `target` placed by `caller`, recorded in history as answered
+/// when `status` is Active.
+pub async fn add_call(state: &AppState, key: &str, call_id: &str, caller: &str, target: CallTarget, status: CallStatus) {
+    let mut record = CallRecord::new(call_id, caller, &target, false);
+    if status == CallStatus::Active {
+        record.answered_at = Some(chrono::Utc::now());
+    }
+    state.history.write().await.insert(call_id.to_owned(), record);
+
+    let session = CallSession {
+        call_id:          call_id.to_owned(),
+        caller:           caller.to_owned(),
+        callee_socket_id: (status == CallStatus::Active).then(Sid::new),
+        target,
+        status,
+        caller_socket_id: Sid::new(),
+        participants:     Vec::new(),
+        listeners:        Vec::new(),
+        removed:          Vec::new(),
+        locked:           false,
+        invited:          Vec::new(),
+        held_by:          None,
+        video:            false,
+        recording:        None,
+        handoff:          None,
+        _timeout_handle:  Arc::new(tokio::spawn(async {}).abort_handle()),
+    };
+    state.calls.write().await.insert(key.to_owned(), session);
+}
@@ -1,33 +1,177 @@
 // src/types.rs — Central type definitions shared across all handler modules.
 
-use gcp_auth::TokenProvider;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use socketioxide::socket::Sid;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::{atomic::AtomicBool, Arc}, time::Instant};
 use tokio::sync::RwLock;
-use crate::livekit::LiveKitConfig;
+use crate::{media::{turn::{IceServer, TurnConfig}, MediaBackend}, push::PushDispatcher, queue::Queues, schedule::Scheduler, settings::CallSettings};
 
 // ── Constants ─────────────────────────────────────────────────────────────────
 
-pub const FCM_PROJECT_ID:   &str = "notification-25684";
-pub const RING_TIMEOUT_SEC: u64  = 30; // Seconds before unanswered call auto-cancels
+pub const DEFAULT_RING_TIMEOUT_SEC: u64 = 30; // Seconds before an unanswered call auto-cancels (calls.* in the settings override)
+pub const MIN_RING_TIMEOUT_SEC: u64 = 10;      // Bounds for the configured and per-call ring timeouts
+pub const MAX_RING_TIMEOUT_SEC: u64 = 120;
+pub const CHAT_PUSH_TTL_SEC: u64 = 24 * 60 * 60; // Chat pushes older than a day are dropped by FCM
+pub const DEVICE_STALE_DAYS: i64 = 60; // Devices whose token was not refreshed for this long are pruned
+pub const VOICEMAIL_MAX_BYTES: usize = 5 * 1024 * 1024; // Largest voicemail upload accepted
+pub const VOICEMAIL_OFFER_TTL_SEC: i64 = 5 * 60;       // How long after "No answer" the caller may still leave one
+pub const DEFAULT_REMINDER_MIN: u32 = 10; // Scheduled-call reminder lead time when the client sends none
+pub const HANDOFF_GRACE_SEC: u64 = 30;    // How long after transfer_call_to_device the old tab's media leave is expected
 
 // ── User ──────────────────────────────────────────────────────────────────────
 
+/// Device family a push token was registered from.
+/// Selects which of the `android` / `apns` / `webpush` blocks carries the visible notification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Android,
+    Ios,
+    #[default]
+    Web,   // clients that predate the `platform` field are all browsers
+}
+
+/// Transport the device's token belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushKind {
+    #[default]
+    Fcm,
+    /// Raw VAPID subscription (see notification-using-push-subscription).
+    /// Recorded so the device shows up in `list_devices`, but not delivered to yet.
+    WebPush,
+}
+
+/// How chat pushes reach a device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatDelivery {
+    /// Visible notification carrying a preview of the message.
+    #[default]
+    Preview,
+    /// Content-free wake-up (conversation key + message id); the client
+    /// fetches the message from `GET /messages`, so no content transits push infrastructure.
+    Sync,
+}
+
+/// Which pushes a device wants. Calls and messages default to on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DevicePrefs {
+    #[serde(default = "default_true")]
+    pub calls:         bool,
+    #[serde(default = "default_true")]
+    pub messages:      bool,
+    #[serde(default)]
+    pub chat_delivery: ChatDelivery,
+    /// Order under `RingStrategy::Sequential`, lowest first.
+    #[serde(default)]
+    pub ring_priority: u8,
+}
+
+impl Default for DevicePrefs {
+    fn default() -> Self { Self { calls: true, messages: true, chat_delivery: ChatDelivery::Preview, ring_priority: 0 } }
+}
+
+fn default_true() -> bool { true }
+
+/// One registered device of a user. Token refreshes replace `token` in place.
+#[derive(Debug, Clone, Serialize)]
+pub struct Device {
+    pub device_id:    String,
+    pub platform:     Platform,
+    pub push_kind:    PushKind,
+    #[serde(skip_serializing)]   // never echo push tokens back to clients
+    pub token:        String,
+    /// Bearer credential for the HTTP endpoints, issued by the server when the
+    /// device first registers and sent only to the tab that registered it.
+    #[serde(skip_serializing)]
+    pub api_key:      String,
+    pub app_version:  Option<String>,
+    pub prefs:        DevicePrefs,
+    pub created_at:   DateTime<Utc>,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+/// Who may ring a user, consulted by `call` before anything rings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CallPrefs {
+    /// Do Not Disturb: every 1-to-1 call is refused straight away.
+    #[serde(default)]
+    pub dnd:           bool,
+    /// Only users in `contacts` may call.
+    #[serde(default)]
+    pub contacts_only: bool,
+    #[serde(default)]
+    pub contacts:      Vec<String>,
+    /// Offer refused callers to leave a message instead, and unanswered
+    /// ones a voicemail once the ring times out.
+    #[serde(default)]
+    pub leave_message: bool,
+    #[serde(default)]
+    pub ring_strategy: RingStrategy,
+}
+
+/// How a 1-to-1 call reaches a user's registered devices. Open tabs always
+/// ring straight away; each later stage only goes out while the call is still
+/// ringing, and stages due after the ring timeout never do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RingStrategy {
+    /// Every device at once.
+    #[default]
+    All,
+    /// Web devices with the tabs, phones `delay_sec` later.
+    DesktopFirst { delay_sec: u64 },
+    /// One `ring_priority` level at a time, `step_sec` apart.
+    Sequential { step_sec: u64 },
+}
+
+impl RingStrategy {
+    /// Keeps delays between one second and the longest ring timeout.
+    pub fn clamped(self) -> Self {
+        let clamp = |sec: u64| sec.clamp(1, MAX_RING_TIMEOUT_SEC);
+        match self {
+            Self::All                        => Self::All,
+            Self::DesktopFirst { delay_sec } => Self::DesktopFirst { delay_sec: clamp(delay_sec) },
+            Self::Sequential { step_sec }    => Self::Sequential { step_sec: clamp(step_sec) },
+        }
+    }
+}
+
+impl CallPrefs {
+    /// Why a call from `caller` is refused, if it is.
+    pub fn refusal(&self, user_id: &str, caller: &str) -> Option<String> {
+        if self.dnd {
+            Some(format!("'{user_id}' is on Do Not Disturb"))
+        } else if self.contacts_only && !self.contacts.iter().any(|c| c == caller) {
+            Some(format!("'{user_id}' only takes calls from contacts"))
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UserState {
     pub user_id:    String,
     pub socket_ids: Vec<Sid>,
-    pub fcm_tokens: Vec<String>,
+    pub devices:    Vec<Device>,
+    pub call_prefs: CallPrefs,
 }
 
 impl UserState {
     pub fn new(user_id: impl Into<String>) -> Self {
-        Self { user_id: user_id.into(), socket_ids: Vec::new(), fcm_tokens: Vec::new() }
+        Self { user_id: user_id.into(), socket_ids: Vec::new(), devices: Vec::new(), call_prefs: CallPrefs::default() }
     }
     pub fn is_online(&self) -> bool { !self.socket_ids.is_empty() }
 }
 
+/// A fresh device API key: two v4 UUIDs (244 random bits), hex encoded.
+pub fn new_api_key() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
 pub type UserMap = Arc<RwLock<HashMap<String, UserState>>>;
 
 // ── Group ─────────────────────────────────────────────────────────────────────
@@ -53,45 +197,267 @@ pub type GroupMap = Arc<RwLock<HashMap<String, Group>>>;
 // ── Call session ──────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum CallStatus { Ringing, Active }
+pub enum CallStatus { Ringing, Active, Held }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CallTarget {
     User(String),
     Group(String),
+    AdHoc(String),  // 1-to-1 call escalated by invite_to_call — keyed "adhoc::{call_id}"
+    Queue(String),  // caller waiting in a call queue for an agent — keyed "queue::{call_id}"
 }
 
 impl CallTarget {
     pub fn id(&self) -> &str {
-        match self { CallTarget::User(id) | CallTarget::Group(id) => id }
+        match self {
+            CallTarget::User(id) | CallTarget::Group(id) | CallTarget::AdHoc(id) | CallTarget::Queue(id) => id,
+        }
     }
+    /// Group and ad-hoc calls: any number of participants, joined through the group_* events.
+    pub fn is_multi_party(&self) -> bool { matches!(self, CallTarget::Group(_) | CallTarget::AdHoc(_)) }
+}
+
+/// CallMap key of the ad-hoc call a 1-to-1 call becomes when escalated.
+pub fn adhoc_key(call_id: &str) -> String {
+    format!("adhoc::{call_id}")
+}
+
+/// CallMap key of a call ringing a user who is already on an active call.
+pub fn waiting_key(callee: &str) -> String {
+    format!("waiting::{callee}")
+}
+
+/// CallMap key of a 1-to-1 call while it is on hold.
+pub fn held_key(call_id: &str) -> String {
+    format!("held::{call_id}")
+}
+
+/// CallMap key of a caller waiting in a call queue. Once an agent answers,
+/// the call becomes an ordinary 1-to-1 call keyed by the agent.
+pub fn queue_key(call_id: &str) -> String {
+    format!("queue::{call_id}")
 }
 
 pub type GroupParticipants = Vec<String>;
 
 #[derive(Debug, Clone)]
 pub struct CallSession {
+    pub call_id:          String,   // unique per ring — lets clients drop stale pushes
     pub caller:           String,
     pub target:           CallTarget,
     pub status:           CallStatus,
     pub caller_socket_id: Sid,
+    pub callee_socket_id: Option<Sid>,  // tab that accepted a 1-to-1 call (see crate::routing)
     pub participants:     GroupParticipants,
+    pub listeners:        Vec<String>,  // group participants who joined listen-only
+    pub removed:          Vec<String>,  // removed by a moderator — may not rejoin this call
+    pub locked:           bool,         // no new joiners (group calls)
+    pub invited:          Vec<String>,  // ad-hoc calls: everyone invited, standing in for group members; queued calls: agents ringing now
+    pub held_by:          Option<String>,  // party that put the call on hold (status Held)
     pub video:            bool,
+    pub recording:        Option<String>,  // egress id of the recording in progress
+    pub handoff:          Option<(String, Instant)>,  // party moving the call to another tab, and since when (see crate::webhook)
     pub _timeout_handle:  Arc<tokio::task::AbortHandle>,
 }
 
 pub type CallMap = Arc<RwLock<HashMap<String, CallSession>>>;
 
+/// Lets the caller of an unanswered 1-to-1 call leave one voicemail.
+#[derive(Debug, Clone)]
+pub struct VoicemailOffer {
+    pub caller:     String,
+    pub callee:     String,
+    pub token:      String,   // bearer secret for POST /voicemail/:call_id, sent only to the caller's tab
+    pub expires_at: DateTime<Utc>,
+}
+
+/// call_id → offer.
+pub type VoicemailOffers = Arc<RwLock<HashMap<String, VoicemailOffer>>>;
+
+/// Default ring timeouts, from the settings.
+#[derive(Debug, Clone, Copy)]
+pub struct RingTimeouts {
+    pub direct_sec: u64,
+    pub group_sec:  u64,
+}
+
+impl RingTimeouts {
+    /// `calls.ring_timeout_sec` (1-to-1) and `calls.group_ring_timeout_sec`,
+    /// already checked against the bounds.
+    pub fn from_settings(calls: &CallSettings) -> Self {
+        Self { direct_sec: calls.ring_timeout_sec, group_sec: calls.group_ring_timeout_sec }
+    }
+
+    /// A per-call override, falling back to `default` and kept within bounds.
+    pub fn clamp(requested: Option<u64>, default: u64) -> u64 {
+        requested.unwrap_or(default).clamp(MIN_RING_TIMEOUT_SEC, MAX_RING_TIMEOUT_SEC)
+    }
+}
+
+// ── Call history ──────────────────────────────────────────────────────────────
+
+/// One recording made during a call. `file` is relative to the recordings directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub egress_id:  String,
+    pub file:       String,
+    pub started_by: String,
+    pub started_at: DateTime<Utc>,
+    pub stopped_at: Option<DateTime<Utc>>,
+}
+
+/// How a call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallOutcome {
+    Completed,   // answered, then hung up
+    Dropped,     // answered, then cut short by a disconnect or the media session closing
+    Missed,      // rang out
+    Rejected,
+    Cancelled,   // the caller gave up (or went away) while it rang
+}
+
+/// End-of-call network quality as measured by one participant's client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub user_id:         String,
+    pub rtt_ms:          Option<f64>,
+    pub jitter_ms:       Option<f64>,
+    pub packet_loss_pct: Option<f64>,
+    pub mos:             Option<f64>,   // estimated, 1.0–5.0
+    pub network_type:    Option<String>,
+    pub reported_at:     DateTime<Utc>,
+}
+
+/// Permanent record of a call, kept after its CallSession is gone.
+/// The timing fields are written by `crate::stats` as the call progresses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallRecord {
+    pub call_id:      String,
+    pub caller:       String,
+    pub target:       String,   // callee user_id or group_id
+    pub is_group:     bool,
+    pub video:        bool,
+    pub started_at:   DateTime<Utc>,           // ringing began
+    pub answered_at:  Option<DateTime<Utc>>,   // first callee or member joined
+    pub ended_at:     Option<DateTime<Utc>>,
+    pub outcome:      Option<CallOutcome>,
+    pub participants: Vec<String>,             // everyone who took part, caller first
+    pub recordings:   Vec<Recording>,
+    pub quality:      Vec<QualityReport>,      // at most one per participant
+}
+
+impl CallRecord {
+    pub fn new(call_id: &str, caller: &str, target: &CallTarget, video: bool) -> Self {
+        Self {
+            call_id:      call_id.to_owned(),
+            caller:       caller.to_owned(),
+            target:       target.id().to_owned(),
+            is_group:     target.is_multi_party(),
+            video,
+            started_at:   Utc::now(),
+            answered_at:  None,
+            ended_at:     None,
+            outcome:      None,
+            participants: Vec::new(),
+            recordings:   Vec::new(),
+            quality:      Vec::new(),
+        }
+    }
+}
+
+/// call_id → record.
+pub type CallHistory = Arc<RwLock<HashMap<String, CallRecord>>>;
+
+// ── Scheduled calls ───────────────────────────────────────────────────────────
+
+/// A call booked for later. Persisted by `schedule::Scheduler`, so it has to
+/// round-trip through serde; it is also the payload of the call_scheduled and
+/// scheduled_call_reminder events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledCall {
+    pub schedule_id:   String,
+    pub created_by:    String,
+    pub title:         String,
+    pub to:            Option<String>,   // 1-to-1 callee …
+    pub group_id:      Option<String>,   // … or group, exactly one is set
+    pub attendees:     Vec<String>,      // everyone invited except the creator
+    pub start_at:      DateTime<Utc>,
+    pub remind_before: u32,              // minutes; 0 = no reminder
+    pub video:         bool,
+    #[serde(default)]
+    pub reminded:      bool,
+}
+
+impl ScheduledCall {
+    /// Creator first, then the attendees.
+    pub fn everyone(&self) -> Vec<String> {
+        std::iter::once(self.created_by.clone()).chain(self.attendees.iter().cloned()).collect()
+    }
+}
+
+// ── Call queues ───────────────────────────────────────────────────────────────
+
+/// Which of a queue's available agents are offered the caller at the head of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStrategy {
+    /// The agent who has gone longest without a call.
+    #[default]
+    LongestIdle,
+    /// Members in turn, starting after the one offered last.
+    RoundRobin,
+    /// Every available agent at once; the first to answer takes it.
+    RingAll,
+}
+
+/// A shared line, loaded from storage.queues_file (see `crate::queue`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueDef {
+    pub queue_id:       String,
+    pub name:           String,
+    pub members:        Vec<String>,   // agents, in round-robin order
+    #[serde(default)]
+    pub strategy:       QueueStrategy,
+    /// How long one offer rings before the next agent is tried.
+    #[serde(default = "default_agent_ring_sec")]
+    pub agent_ring_sec: u64,
+    /// How long a caller waits before overflowing.
+    #[serde(default = "default_max_wait_sec")]
+    pub max_wait_sec:   u64,
+    /// Callers beyond this many are overflowed straight away.
+    #[serde(default = "default_max_queued")]
+    pub max_queued:     usize,
+    /// User rung instead when the queue is full, unstaffed or waited out.
+    #[serde(default)]
+    pub overflow_to:    Option<String>,
+}
+
+fn default_agent_ring_sec() -> u64 { 20 }
+fn default_max_wait_sec() -> u64 { 5 * 60 }
+fn default_max_queued() -> usize { 20 }
+
 // ── Chat messages ─────────────────────────────────────────────────────────────
 
 /// A single stored message (shared shape for both DM and group messages).
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredMessage {
     pub message_id: String,
     pub from:       String,
     pub target:     String, // target can be { User_id , Group_id }
     pub content:    String,
     pub timestamp:  String, 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voicemail:  Option<Voicemail>,
+}
+
+/// Audio attached to a voicemail message. `url` is relative to the backend origin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Voicemail {
+    pub call_id:      String,
+    pub url:          String,
+    pub content_type: String,
+    pub size_bytes:   usize,
 }
 
 /// conversation_key → ordered list of messages (oldest first).
@@ -119,9 +485,15 @@ pub struct AppState {
     pub groups:   GroupMap,
     pub calls:    CallMap,
     pub messages: MessageStore, 
-    pub auth:     Arc<dyn TokenProvider>,
-    pub http:     reqwest::Client,
-    pub livekit:  Arc<crate::livekit::LiveKitConfig>,
+    pub history:  CallHistory,
+    pub push:     Arc<PushDispatcher>,
+    pub media:    Arc<dyn MediaBackend>,
+    pub turn:     Option<Arc<TurnConfig>>,  // None when TURN_URLS / TURN_SECRET are unset
+    pub schedule: Arc<Scheduler>,
+    pub ring:     RingTimeouts,
+    pub voicemail: VoicemailOffers,
+    pub queues:   Arc<Queues>,
+    pub draining: Arc<AtomicBool>,   // set once shutdown begins: no new calls (see crate::shutdown)
 }
 
 // ── Inbound payloads (client → server) ───────────────────────────────────────
@@ -129,19 +501,77 @@ pub struct AppState {
 #[derive(Debug, Deserialize)]
 pub struct RegisterPayload      { pub user_id: String }
 #[derive(Debug, Deserialize)]
-pub struct StoreFcmTokenPayload { pub user_id: String, pub token: String }
+pub struct StoreFcmTokenPayload {
+    pub user_id:     String,
+    pub token:       String,
+    /// Stable per-install id. Older clients omit it and share one device per
+    /// platform, so their token refreshes still replace the previous token.
+    pub device_id:   Option<String>,
+    #[serde(default)]
+    pub platform:    Platform,
+    #[serde(default)]
+    pub push_kind:   PushKind,
+    pub app_version: Option<String>,
+    /// Omitted → keep the device's current preferences.
+    pub prefs:       Option<DevicePrefs>,
+}
+#[derive(Debug, Deserialize)]
+pub struct ListDevicesPayload  { pub user_id: String }
+#[derive(Debug, Deserialize)]
+pub struct GetCallPrefsPayload { pub user_id: String }
+#[derive(Debug, Deserialize)]
+pub struct SetCallPrefsPayload { pub user_id: String, pub prefs: CallPrefs }
+#[derive(Debug, Deserialize)]
+pub struct RemoveDevicePayload { pub user_id: String, pub device_id: String }
 
 // 1-to-1 call events
 #[derive(Debug, Deserialize)]
-pub struct CallPayload    { pub from: String, pub to: String, pub video: Option<bool> }
+pub struct CallPayload    {
+    pub from:             String,
+    pub to:               String,
+    pub video:            Option<bool>,
+    /// Overrides the server default, within MIN/MAX_RING_TIMEOUT_SEC.
+    pub ring_timeout_sec: Option<u64>,
+}
 #[derive(Debug, Deserialize)]
 pub struct CancelPayload  { pub from: String, pub to: String }
 #[derive(Debug, Deserialize)]
-pub struct AcceptPayload  { pub from: String, pub to: String }
+pub struct AcceptPayload  {
+    pub from:        String,
+    pub to:          String,
+    /// Only used when accepting a waiting call: what happens to the call in progress.
+    #[serde(default)]
+    pub active_call: ActiveCallAction,
+}
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActiveCallAction {
+    #[default]
+    Hold,
+    End,
+}
 #[derive(Debug, Deserialize)]
 pub struct RejectPayload  { pub from: String, pub to: String }
 #[derive(Debug, Deserialize)]
 pub struct CutCallPayload { pub from: String, pub to: String }
+#[derive(Debug, Deserialize)]
+pub struct HoldCallPayload     { pub from: String, pub call_id: String }
+#[derive(Debug, Deserialize)]
+pub struct TransferCallPayload { pub from: String, pub call_id: String, pub to: String }
+/// Moves `from`'s side of call `call_id` onto the sending tab.
+#[derive(Debug, Deserialize)]
+pub struct TransferCallToDevicePayload { pub from: String, pub call_id: String }
+/// Sent by each participant once a call is over; every metric is optional.
+#[derive(Debug, Deserialize)]
+pub struct CallQualityReportPayload {
+    pub from:            String,
+    pub call_id:         String,
+    pub rtt_ms:          Option<f64>,
+    pub jitter_ms:       Option<f64>,
+    pub packet_loss_pct: Option<f64>,
+    pub mos:             Option<f64>,
+    pub network_type:    Option<String>,   // e.g. "wifi", "cellular", "ethernet"
+}
 
 // Group management
 #[derive(Debug, Deserialize)]
@@ -165,13 +595,85 @@ pub struct RemoveGroupMemberPayload {
 
 // Group call events
 #[derive(Debug, Deserialize)]
-pub struct GroupCallPayload   { pub from: String, pub group_id: String, pub video: Option<bool> }
+pub struct GroupCallPayload   {
+    pub from:             String,
+    pub group_id:         String,
+    pub video:            Option<bool>,
+    pub ring_timeout_sec: Option<u64>,
+}
 #[derive(Debug, Deserialize)]
-pub struct GroupAcceptPayload { pub from: String, pub group_id: String }
+pub struct GroupAcceptPayload {
+    pub from:        String,
+    pub group_id:    String,
+    #[serde(default)]
+    pub listen_only: bool,
+}
 #[derive(Debug, Deserialize)]
 pub struct GroupRejectPayload { pub from: String, pub group_id: String }
 #[derive(Debug, Deserialize)]
 pub struct GroupCutPayload    { pub from: String, pub group_id: String }
+#[derive(Debug, Deserialize)]
+pub struct GroupCallQueryPayload { pub from: String, pub group_id: String }
+#[derive(Debug, Deserialize)]
+pub struct JoinGroupCallPayload {
+    pub from:        String,
+    pub group_id:    String,
+    #[serde(default)]
+    pub listen_only: bool,
+}
+#[derive(Debug, Deserialize)]
+pub struct GroupCallRingPayload { pub from: String, pub group_id: String, pub user_ids: Vec<String> }
+
+// Ad-hoc escalation
+#[derive(Debug, Deserialize)]
+pub struct InviteToCallPayload { pub from: String, pub call_id: String, pub user_id: String }
+
+// Group call moderation (caller / group admin only)
+#[derive(Debug, Deserialize)]
+pub struct GroupModeratePayload { pub from: String, pub group_id: String, pub user_id: String }
+#[derive(Debug, Deserialize)]
+pub struct GroupLockPayload     { pub from: String, pub group_id: String, pub locked: bool }
+
+// LiveKit inbound
+/// `group_id` selects a group call; without it the caller's active 1-to-1 call is used.
+#[derive(Debug, Deserialize)]
+pub struct RefreshLiveKitTokenPayload { pub from: String, pub group_id: Option<String> }
+#[derive(Debug, Deserialize)]
+pub struct RecordingPayload { pub from: String, pub call_id: String }
+
+// P2P signaling (MEDIA_BACKEND=p2p)
+#[derive(Debug, Deserialize)]
+pub struct WebRtcSdpPayload { pub from: String, pub to: String, pub sdp: String }
+#[derive(Debug, Deserialize)]
+pub struct WebRtcIcePayload { pub from: String, pub to: String, pub candidate: serde_json::Value }
+#[derive(Debug, Deserialize)]
+pub struct TurnCredentialsRequestPayload { pub from: String }
+
+// Scheduled calls
+/// Exactly one of `to` / `group_id`. `start_at` is RFC 3339.
+#[derive(Debug, Deserialize)]
+pub struct ScheduleCallPayload {
+    pub from:          String,
+    pub to:            Option<String>,
+    pub group_id:      Option<String>,
+    pub start_at:      DateTime<Utc>,
+    pub title:         Option<String>,
+    pub remind_before: Option<u32>,
+    pub video:         Option<bool>,
+}
+#[derive(Debug, Deserialize)]
+pub struct CancelScheduledCallPayload { pub from: String, pub schedule_id: String }
+#[derive(Debug, Deserialize)]
+pub struct ListScheduledCallsPayload  { pub from: String }
+
+// Call queues
+#[derive(Debug, Deserialize)]
+pub struct CallQueuePayload  { pub from: String, pub queue_id: String, pub video: Option<bool> }
+/// leave_queue (the caller), accept_queue_call / reject_queue_call (an agent offered it).
+#[derive(Debug, Deserialize)]
+pub struct QueueCallPayload  { pub from: String, pub call_id: String }
+#[derive(Debug, Deserialize)]
+pub struct ListQueuesPayload { pub from: String }
 
 // Chat inbound
 #[derive(Debug, Deserialize)]
@@ -197,6 +699,7 @@ pub mod event {
     pub const USER_ONLINE:         &str = "user_online";
     pub const USER_OFFLINE:        &str = "user_offline";
     pub const REGISTER_ERROR:      &str = "register_error";
+    pub const SERVER_SHUTDOWN:     &str = "server_shutdown";   // server going down — reconnect after the hint
 
     // 1-to-1 call lifecycle
     pub const INCOMING_CALL:       &str = "incoming_call";
@@ -204,6 +707,16 @@ pub mod event {
     pub const CALL_REJECTED:       &str = "call_rejected";
     pub const CALL_CANCELLED:      &str = "call_cancelled";
     pub const CALL_ENDED:          &str = "call_ended";
+    pub const VOICEMAIL_OFFER:     &str = "voicemail_offer";   // after "No answer": the caller may upload a voicemail
+    pub const CALL_UNAVAILABLE:    &str = "call_unavailable";  // refused by the callee's call prefs; nothing rang
+    pub const CALL_ESCALATED:      &str = "call_escalated";   // 1-to-1 became an ad-hoc group call
+    pub const CALL_HELD:           &str = "call_held";
+    pub const CALL_RESUMED:        &str = "call_resumed";
+    pub const CALL_TRANSFERRED:    &str = "call_transferred";
+    pub const WAITING_CALL_ENDED:  &str = "waiting_call_ended";  // a waiting or held call went away; the current one stays
+    pub const CALL_ON_OTHER_DEVICE: &str = "call_on_other_device"; // we are in a call on another tab; it can be moved here
+    pub const CALL_ON_OTHER_DEVICE_ENDED: &str = "call_on_other_device_ended"; // ...and it is over
+    pub const CALL_MOVED:          &str = "call_moved";          // a party's call moved to another of their tabs
 
     // Group management
     pub const GROUP_CREATED:       &str = "group_created";
@@ -215,6 +728,23 @@ pub mod event {
     pub const GROUP_MEMBER_JOINED: &str = "group_member_joined";
     pub const GROUP_MEMBER_LEFT:   &str = "group_member_left";
     pub const GROUP_CALL_ENDED:    &str = "group_call_ended";
+    pub const GROUP_CALL_STATUS:   &str = "group_call_status";
+
+    // Group call moderation
+    pub const GROUP_PARTICIPANT_MUTED:   &str = "group_participant_muted";
+    pub const GROUP_PARTICIPANT_REMOVED: &str = "group_participant_removed";
+    pub const GROUP_CALL_LOCKED:         &str = "group_call_locked";
+
+    // Scheduled calls
+    pub const CALL_SCHEDULED:           &str = "call_scheduled";
+    pub const SCHEDULED_CALL_CANCELLED: &str = "scheduled_call_cancelled";
+    pub const SCHEDULED_CALL_REMINDER:  &str = "scheduled_call_reminder";
+    pub const SCHEDULED_CALL_STARTED:   &str = "scheduled_call_started";   // to the creator's tab placing the call
+    pub const SCHEDULED_CALLS:          &str = "scheduled_calls";
+
+    // Call queues
+    pub const QUEUE_POSITION:      &str = "queue_position";   // to a queued caller's tab whenever the line moves
+    pub const QUEUES:              &str = "queues";
 
     // Chat
     pub const DIRECT_MESSAGE:      &str = "direct_message";
@@ -222,10 +752,25 @@ pub mod event {
     pub const MESSAGE_SENT:        &str = "message_sent";
     pub const MESSAGE_HISTORY:     &str = "message_history";
 
+    // Devices
+    pub const DEVICE_REGISTERED:   &str = "device_registered";   // to the registering tab: the device's API key
+    pub const DEVICE_LIST:         &str = "device_list";
+    pub const CALL_PREFS:          &str = "call_prefs";
+
     // live kit
     pub const LIVEKIT_TOKEN:       &str = "livekit_token";        // 1-to-1 call token
     pub const GROUP_LIVEKIT_TOKEN: &str = "group_livekit_token";  // group call token
 
+    // Recording
+    pub const RECORDING_STARTED:   &str = "recording_started";
+    pub const RECORDING_STOPPED:   &str = "recording_stopped";
+
+    // P2P signaling
+    pub const WEBRTC_OFFER:        &str = "webrtc_offer";
+    pub const WEBRTC_ANSWER:       &str = "webrtc_answer";
+    pub const WEBRTC_ICE:          &str = "webrtc_ice_candidate";
+    pub const TURN_CREDENTIALS:    &str = "turn_credentials";
+
     pub const ERROR:               &str = "error";
 }
 
@@ -243,10 +788,21 @@ pub struct UserListPayload    { pub users: Vec<UserEntry> }
 pub struct UserOnlinePayload  { pub user_id: String }
 #[derive(Debug, Serialize)]
 pub struct UserOfflinePayload { pub user_id: String }
+/// `keep_media`: calls in progress stay up in their media rooms; only signalling goes away.
+#[derive(Debug, Serialize)]
+pub struct ServerShutdownPayload { pub reason: String, pub reconnect_after_ms: u64, pub keep_media: bool }
 
 // 1-to-1 call responses
 #[derive(Debug, Serialize)]
-pub struct IncomingCallPayload  { pub from: String, pub video: bool }
+pub struct IncomingCallPayload  {
+    pub call_id: String,
+    pub from:    String,
+    pub video:   bool,
+    pub waiting: bool,   // callee is on another call — offer accept-and-hold / accept-and-end
+    /// Offered to an agent of this queue: answer with accept_queue_call / reject_queue_call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue:   Option<String>,
+}
 #[derive(Debug, Serialize)]
 pub struct CallAcceptedPayload  { pub by: String }
 #[derive(Debug, Serialize)]
@@ -255,6 +811,42 @@ pub struct CallRejectedPayload  { pub by: String }
 pub struct CallCancelledPayload { pub by: String }
 #[derive(Debug, Serialize)]
 pub struct CallEndedPayload     { pub reason: String }
+/// POST the audio to `upload_url` with `Authorization: Bearer <token>` before `expires_at`.
+#[derive(Debug, Serialize)]
+pub struct VoicemailOfferPayload {
+    pub call_id:    String,
+    pub to:         String,
+    pub upload_url: String,
+    pub token:      String,
+    pub max_bytes:  usize,
+    pub expires_at: DateTime<Utc>,
+}
+/// `leave_message`: the callee would rather get a message — offer the caller that.
+#[derive(Debug, Serialize)]
+pub struct CallUnavailablePayload { pub to: String, pub reason: String, pub leave_message: bool }
+/// From now on the call is driven with the group_* events under `group_id`.
+#[derive(Debug, Serialize)]
+pub struct CallEscalatedPayload {
+    pub call_id:      String,
+    pub group_id:     String,
+    pub by:           String,
+    pub participants: Vec<String>,
+}
+#[derive(Debug, Serialize)]
+pub struct CallHoldPayload        { pub call_id: String, pub by: String }
+/// To the party left behind by a blind transfer: they are now ringing `to` on a new call.
+#[derive(Debug, Serialize)]
+pub struct CallTransferredPayload { pub call_id: String, pub by: String, pub to: String }
+/// `ringing`: we placed the call and it has not been answered yet (cancel, not hang up).
+#[derive(Debug, Serialize)]
+pub struct CallOnOtherDevicePayload { pub call_id: String, pub peer: String, pub video: bool, pub ringing: bool }
+#[derive(Debug, Serialize)]
+pub struct CallOnOtherDeviceEndedPayload { pub call_id: String }
+/// To the tab the call left (leave the media room) and to the other party (expect a new peer).
+#[derive(Debug, Serialize)]
+pub struct CallMovedPayload { pub call_id: String, pub user_id: String }
+#[derive(Debug, Serialize)]
+pub struct WaitingCallEndedPayload { pub call_id: String, pub reason: String }
 
 // Group management responses
 #[derive(Debug, Serialize, Clone)]
@@ -276,6 +868,7 @@ pub struct GroupDeletedPayload { pub group_id: String }
 // Group call responses
 #[derive(Debug, Serialize)]
 pub struct GroupIncomingCallPayload {
+    pub call_id:    String,
     pub from:       String,
     pub group_id:   String,
     pub group_name: String,
@@ -287,6 +880,25 @@ pub struct GroupMemberJoinedPayload { pub group_id: String, pub user_id: String
 pub struct GroupMemberLeftPayload   { pub group_id: String, pub user_id: String }
 #[derive(Debug, Serialize)]
 pub struct GroupCallEndedPayload    { pub group_id: String, pub reason: String }
+/// Answer to group_call_status; `call_id` is None when the group has no call.
+#[derive(Debug, Serialize)]
+pub struct GroupCallStatusPayload {
+    pub group_id:     String,
+    pub call_id:      Option<String>,
+    pub caller:       Option<String>,
+    pub active:       bool,
+    pub video:        bool,
+    pub locked:       bool,
+    pub participants: Vec<String>,
+    /// Identities the media server reports in the room; absent when the
+    /// backend cannot tell (p2p, none) or the call is still ringing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connected:    Option<Vec<String>>,
+}
+#[derive(Debug, Serialize, Clone)]
+pub struct GroupParticipantModeratedPayload { pub group_id: String, pub user_id: String, pub by: String }
+#[derive(Debug, Serialize, Clone)]
+pub struct GroupCallLockedPayload   { pub group_id: String, pub locked: bool, pub by: String }
 
 // Chat responses
 #[derive(Debug, Serialize, Clone)]
@@ -296,6 +908,8 @@ pub struct DirectMessagePayload {
     pub to:         String,
     pub content:    String,
     pub timestamp:  String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voicemail:  Option<Voicemail>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -314,6 +928,13 @@ pub struct MessageHistoryPayload {
     pub messages:         Vec<StoredMessage>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct DeviceListPayload { pub devices: Vec<Device> }
+#[derive(Debug, Serialize)]
+pub struct DeviceRegisteredPayload { pub device_id: String, pub api_key: String }
+#[derive(Debug, Serialize)]
+pub struct CallPrefsPayload  { pub prefs: CallPrefs }
+
 #[derive(Debug, Serialize)]
 pub struct ErrorPayload { pub message: String }
 
@@ -323,6 +944,8 @@ pub struct ErrorPayload { pub message: String }
 /// Client uses url + token to connect to LiveKit room directly.
 #[derive(Debug, Serialize, Clone)]
 pub struct LiveKitTokenPayload {
+    pub call_id: String,
+    pub media:   String,   // "livekit" | "p2p" | "none" — how the client should connect
     pub room:  String,   // room name
     pub token: String,   // JWT access token
     pub url:   String,   // wss://your-livekit-server
@@ -331,8 +954,60 @@ pub struct LiveKitTokenPayload {
 /// Same but for group calls — includes group_id so client knows which call it's for.
 #[derive(Debug, Serialize, Clone)]
 pub struct GroupLiveKitTokenPayload {
+    pub call_id:  String,
+    pub media:    String,
     pub group_id: String,
     pub room:     String,
     pub token:    String,
     pub url:      String,
-}
\ No newline at end of file
+}
+
+// Recording responses — sent to every participant so nobody is recorded unknowingly
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingStartedPayload { pub call_id: String, pub egress_id: String, pub by: String }
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingStoppedPayload {
+    pub call_id:      String,
+    pub egress_id:    String,
+    pub by:           String,
+    pub download_url: String,   // relative to the backend origin
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WebRtcSdpRelayPayload { pub call_id: String, pub from: String, pub sdp: String }
+#[derive(Debug, Serialize, Clone)]
+pub struct WebRtcIceRelayPayload { pub call_id: String, pub from: String, pub candidate: serde_json::Value }
+
+// TURN
+#[derive(Debug, Serialize, Clone)]
+pub struct TurnCredentialsPayload { pub ice_servers: Vec<IceServer>, pub ttl: u64 }
+
+// Scheduled calls
+#[derive(Debug, Serialize)]
+pub struct ScheduledCallCancelledPayload { pub schedule_id: String, pub by: String, pub reason: String }
+/// The creator's UI enters the calling state; the usual call events follow.
+#[derive(Debug, Serialize)]
+pub struct ScheduledCallStartedPayload {
+    pub schedule_id: String,
+    pub to:          Option<String>,
+    pub group_id:    Option<String>,
+    pub video:       bool,
+}
+#[derive(Debug, Serialize)]
+pub struct ScheduledCallsPayload { pub calls: Vec<ScheduledCall> }
+
+// Call queues
+#[derive(Debug, Serialize)]
+pub struct QueuePositionPayload { pub call_id: String, pub queue_id: String, pub position: usize, pub waiting: usize }
+/// A queue as listed to its members: who is waiting and which agents are free.
+#[derive(Debug, Serialize)]
+pub struct QueueInfo {
+    pub queue_id:  String,
+    pub name:      String,
+    pub strategy:  QueueStrategy,
+    pub members:   Vec<String>,
+    pub available: Vec<String>,
+    pub waiting:   usize,
+}
+#[derive(Debug, Serialize)]
+pub struct QueuesPayload { pub queues: Vec<QueueInfo> }
\ No newline at end of file
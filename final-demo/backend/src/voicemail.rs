@@ -0,0 +1,142 @@
+// src/voicemail.rs — Voicemail left after an unanswered 1-to-1 call.
+//
+// When a ring times out and the callee has `leave_message` set, the caller's
+// tab gets VOICEMAIL_OFFER carrying a one-time upload token. The audio is then
+// POSTed to /voicemail/:call_id (see api.rs), written to `storage.voicemail_dir`
+// (default "voicemail") and stored in the DM conversation as a message from
+// the caller whose `voicemail` field points at GET /voicemail/:call_id.
+// The callee gets it like any other DM: DIRECT_MESSAGE on open tabs and a
+// "new voicemail" push on their devices.
+
+use std::path::PathBuf;
+
+use socketioxide::SocketIo;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    fcm::Push,
+    push::targets_for,
+    settings,
+    types::{
+        dm_key, event, AppState, DirectMessagePayload, StoredMessage, Voicemail, VoicemailOffer,
+        VoicemailOfferPayload, VOICEMAIL_MAX_BYTES, VOICEMAIL_OFFER_TTL_SEC,
+    },
+    webhook::emit_to_users,
+};
+
+/// Accepted upload types and the extension each is stored under.
+const FORMATS: &[(&str, &str)] = &[
+    ("audio/webm", "webm"),
+    ("audio/ogg",  "ogg"),
+    ("audio/mp4",  "m4a"),
+    ("audio/mpeg", "mp3"),
+    ("audio/wav",  "wav"),
+];
+
+/// Extension for an upload's Content-Type, ignoring parameters such as `;codecs=opus`.
+pub fn extension(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim();
+    FORMATS.iter()
+        .find(|(m, _)| m.eq_ignore_ascii_case(mime))
+        .map(|(_, ext)| *ext)
+}
+
+/// Where the audio of `call_id` is stored.
+pub fn file_path(call_id: &str, ext: &str) -> PathBuf {
+    settings::get().storage.voicemail_dir.join(format!("{call_id}.{ext}"))
+}
+
+/// Lets `caller` leave a voicemail for `callee` on `call_id`. Expired offers
+/// are dropped on the way.
+pub async fn offer(state: &AppState, call_id: &str, caller: &str, callee: &str) -> VoicemailOfferPayload {
+    let token      = Uuid::new_v4().to_string();
+    let now        = chrono::Utc::now();
+    let expires_at = now + chrono::Duration::seconds(VOICEMAIL_OFFER_TTL_SEC);
+
+    let mut offers = state.voicemail.write().await;
+    offers.retain(|_, o| o.expires_at > now);
+    offers.insert(call_id.to_owned(), VoicemailOffer {
+        caller:     caller.to_owned(),
+        callee:     callee.to_owned(),
+        token:      token.clone(),
+        expires_at,
+    });
+
+    VoicemailOfferPayload {
+        call_id:    call_id.to_owned(),
+        to:         callee.to_owned(),
+        upload_url: format!("/voicemail/{call_id}"),
+        token,
+        max_bytes:  VOICEMAIL_MAX_BYTES,
+        expires_at,
+    }
+}
+
+/// Redeems the offer for `call_id`. A wrong token leaves it in place;
+/// a matching one consumes it, so each call yields at most one voicemail.
+pub async fn take_offer(state: &AppState, call_id: &str, token: &str) -> Option<VoicemailOffer> {
+    let mut offers = state.voicemail.write().await;
+    if offers.get(call_id)?.token != token { return None; }
+    offers.remove(call_id).filter(|o| o.expires_at > chrono::Utc::now())
+}
+
+/// Writes the audio to disk, stores it as a DM from the caller and delivers
+/// it to both parties' tabs plus the callee's devices.
+pub async fn deliver(
+    state:        &AppState,
+    io:           &SocketIo,
+    call_id:      &str,
+    offer:        VoicemailOffer,
+    content_type: &str,
+    ext:          &str,
+    audio:        &[u8],
+) -> std::io::Result<StoredMessage> {
+    let path = file_path(call_id, ext);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(&path, audio).await?;
+
+    let VoicemailOffer { caller, callee, .. } = offer;
+    let message = StoredMessage {
+        message_id: Uuid::new_v4().to_string(),
+        from:       caller.clone(),
+        target:     callee.clone(),
+        content:    "🎙 Voicemail".into(),
+        timestamp:  chrono::Utc::now().to_rfc3339(),
+        voicemail:  Some(Voicemail {
+            call_id:      call_id.to_owned(),
+            url:          format!("/voicemail/{call_id}"),
+            content_type: content_type.to_owned(),
+            size_bytes:   audio.len(),
+        }),
+    };
+    state.messages.write().await
+        .entry(dm_key(&caller, &callee)).or_default()
+        .push(message.clone());
+
+    let outbound = DirectMessagePayload {
+        message_id: message.message_id.clone(),
+        from:       caller.clone(),
+        to:         callee.clone(),
+        content:    message.content.clone(),
+        timestamp:  message.timestamp.clone(),
+        voicemail:  message.voicemail.clone(),
+    };
+    emit_to_users(io, &state.users, [callee.as_str(), caller.as_str()], event::DIRECT_MESSAGE, &outbound).await;
+
+    let push = Push::Voicemail {
+        message_id: message.message_id.clone(),
+        from:       caller.clone(),
+        to:         callee.clone(),
+        call_id:    call_id.to_owned(),
+    };
+    let targets = state.users.read().await.get(&callee)
+        .map(|u| targets_for(u, &push))
+        .unwrap_or_default();
+    state.push.dispatch(push, targets);
+
+    info!("[🎙] '{caller}' left a {}-byte voicemail for '{callee}'", audio.len());
+    Ok(message)
+}
@@ -0,0 +1,394 @@
+// src/webhook.rs — Media server webhook receiver.
+//
+// POST /livekit/webhook keeps CallMap in step with the media server. The
+// backend verifies each delivery (LiveKit signs it with a JWT carrying the
+// body's SHA-256), so only the configured server can end calls through here.
+//
+// RoomStarted        → logged
+// ParticipantJoined  → group: participant recorded (e.g. rejoined after a drop)
+// ParticipantLeft    → 1-to-1: call ended for both sides (on the tabs carrying it)
+//                      when the caller or callee left; other identities are ignored, as
+//                      is the leave of a tab a party just moved the call away from
+//                      group:  GROUP_MEMBER_LEFT, or GROUP_CALL_ENDED when nobody is left
+// RoomFinished       → any session still bound to the room is ended
+// RecordingEnded     → the recording's stop time is written to the call history
+
+use std::collections::HashMap;
+
+use axum::{extract::State, http::{header, HeaderMap, StatusCode}, Extension};
+use socketioxide::SocketIo;
+use tracing::{info, warn};
+
+use crate::{
+    handlers::{
+        group_call::members_of,
+        recording::{mark_recording_stopped, participants_of},
+    },
+    media::{session_room_name, MediaEvent},
+    routing, stats,
+    types::{
+        event, AppState, CallOutcome, CallSession, CallStatus, CallTarget,
+        GroupCallEndedPayload, GroupMemberLeftPayload, RecordingStoppedPayload, UserMap,
+        HANDOFF_GRACE_SEC,
+    },
+};
+
+pub async fn on_media_webhook(
+    State(state):    State<AppState>,
+    Extension(io):   Extension<SocketIo>,
+    headers:         HeaderMap,
+    body:            String,
+) -> StatusCode {
+    let Some(auth_token) = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()) else {
+        return StatusCode::UNAUTHORIZED;
+    };
+
+    let Some(ev) = state.media.parse_webhook(&body, auth_token) else {
+        return StatusCode::UNAUTHORIZED;
+    };
+
+    match ev {
+        MediaEvent::RoomStarted { room } =>
+            info!("[media/webhook] room '{room}' started"),
+        MediaEvent::ParticipantJoined { room, identity } =>
+            on_participant_joined(&state, &room, &identity).await,
+        MediaEvent::ParticipantLeft { room, identity } =>
+            on_participant_left(&state, &io, &room, &identity).await,
+        MediaEvent::RoomFinished { room } =>
+            on_room_finished(&state, &io, &room).await,
+        MediaEvent::RecordingEnded { recording_id } =>
+            on_recording_ended(&state, &io, &recording_id).await,
+        MediaEvent::Other { kind } =>
+            info!("[media/webhook] ignoring '{kind}'"),
+    }
+    StatusCode::OK
+}
+
+// ── Event handlers ────────────────────────────────────────────────────────────
+
+async fn on_participant_joined(state: &AppState, room: &str, uid: &str) {
+    let mut calls = state.calls.write().await;
+    let Some(key) = session_key_for_room(&calls, room) else { return; };
+    let session = calls.get_mut(&key).unwrap();
+
+    // Removed by a moderator but reconnected with a still-valid token
+    if session.removed.iter().any(|r| r == uid) {
+        drop(calls);
+        warn!("[media/webhook] removed participant '{uid}' rejoined room '{room}' — disconnecting");
+        state.media.remove_participant(room, uid).await;
+        return;
+    }
+
+    if session.target.is_multi_party() && !session.participants.iter().any(|p| p == uid) {
+        session.participants.retain(|p| p != &format!("-{uid}"));
+        session.participants.push(uid.to_owned());
+        let call_id = session.call_id.clone();
+        drop(calls);
+        stats::joined(&state.history, &call_id, &[uid]).await;
+        info!("[media/webhook] '{uid}' joined room '{room}' without signaling — recorded");
+    }
+}
+
+async fn on_participant_left(state: &AppState, io: &SocketIo, room: &str, uid: &str) {
+    let mut calls = state.calls.write().await;
+    let Some(key) = session_key_for_room(&calls, room) else { return; };
+    let session = calls.get_mut(&key).unwrap();
+
+    // A ringing session has not reached the media server yet — the ring timeout owns it
+    if session.status != CallStatus::Active { return; }
+
+    match session.target.clone() {
+        CallTarget::User(callee) => {
+            let caller = session.caller.clone();
+            // Recorders and anyone else in the room do not hold the call up
+            if uid != caller && uid != callee { return; }
+            // transfer_call_to_device: the old tab's connection is dropped once the new one joins
+            if let Some((mover, since)) = session.handoff.take()
+                && mover == uid && since.elapsed().as_secs() < HANDOFF_GRACE_SEC
+            {
+                info!("[media/webhook] '{uid}' left room '{room}' after moving the call to another tab — kept");
+                return;
+            }
+            let session = calls.remove(&key).unwrap();
+            drop(calls);
+            stats::ended(&state.history, &session.call_id, CallOutcome::Dropped).await;
+
+            let reason = format!("'{uid}' left the call");
+            for party in [&caller, &callee] {
+                routing::end_for(io, &state.users, &session, party, &reason).await;
+            }
+            info!("[media/webhook] 1-to-1 call '{caller}' ↔ '{callee}' ended — '{uid}' left the room");
+        }
+        CallTarget::Queue(_) => {}   // never Active
+        CallTarget::Group(group_id) | CallTarget::AdHoc(group_id) => {
+            if !session.participants.iter().any(|p| p == uid) { return; }
+            session.participants.retain(|p| p != uid);
+
+            // Remaining real participants (strip sentinels and reject markers)
+            let remaining: Vec<String> = session.participants.iter()
+                .filter(|p| !p.starts_with('-') && !p.starts_with('@'))
+                .cloned()
+                .collect();
+
+            if remaining.is_empty() {
+                let session = calls.remove(&key).unwrap();
+                let members = members_of(state, &session).await;
+                drop(calls);
+                stats::ended(&state.history, &session.call_id, CallOutcome::Dropped).await;
+                end_group_call(state, io, &group_id, &members, "Everyone left the call").await;
+            } else {
+                drop(calls);
+                let left = GroupMemberLeftPayload { group_id: group_id.clone(), user_id: uid.to_owned() };
+                emit_to_users(io, &state.users, remaining.iter().map(String::as_str),
+                    event::GROUP_MEMBER_LEFT, &left).await;
+                info!("[media/webhook] '{uid}' left group call '{group_id}' ({} remaining)", remaining.len());
+            }
+        }
+    }
+}
+
+async fn on_room_finished(state: &AppState, io: &SocketIo, room: &str) {
+    let mut calls = state.calls.write().await;
+    let Some(key) = session_key_for_room(&calls, room) else {
+        info!("[media/webhook] room '{room}' finished");
+        return;
+    };
+    let session = calls.remove(&key).unwrap();
+    drop(calls);
+    stats::ended(&state.history, &session.call_id, CallOutcome::Dropped).await;
+
+    match session.target {
+        CallTarget::User(ref callee) => {
+            for party in [&session.caller, callee] {
+                routing::end_for(io, &state.users, &session, party, "Media session closed").await;
+            }
+        }
+        CallTarget::Group(ref group_id) | CallTarget::AdHoc(ref group_id) => {
+            let members = members_of(state, &session).await;
+            end_group_call(state, io, group_id, &members, "Media session closed").await;
+        }
+        CallTarget::Queue(_) => {}   // never matched to a room
+    }
+    warn!("[media/webhook] room '{room}' finished while its call was still tracked — session ended");
+}
+
+async fn on_recording_ended(state: &AppState, io: &SocketIo, egress_id: &str) {
+    let call_id = state.history.read().await.values()
+        .find(|r| r.recordings.iter().any(|rec| rec.egress_id == egress_id))
+        .map(|r| r.call_id.clone());
+    let Some(call_id) = call_id else { return; };
+
+    mark_recording_stopped(state, &call_id, egress_id).await;
+
+    // Still marked as recording → it ended without stop_recording (egress failure,
+    // size limit…); participants must learn that they are no longer recorded
+    let participants = {
+        let mut calls = state.calls.write().await;
+        calls.values_mut()
+            .find(|s| s.recording.as_deref() == Some(egress_id))
+            .map(|s| { s.recording = None; participants_of(s) })
+    };
+    if let Some(participants) = participants {
+        let stopped = RecordingStoppedPayload {
+            call_id:      call_id.clone(),
+            egress_id:    egress_id.to_owned(),
+            by:           "server".into(),
+            download_url: format!("/recordings/{egress_id}"),
+        };
+        emit_to_users(io, &state.users, participants.iter().map(String::as_str),
+            event::RECORDING_STOPPED, &stopped).await;
+    }
+    info!("[media/webhook] recording '{egress_id}' of call '{call_id}' ended");
+}
+
+// ── Internal helpers ──────────────────────────────────────────────────────────
+
+/// Finds the CallMap key whose session owns `room`.
+fn session_key_for_room(calls: &HashMap<String, CallSession>, room: &str) -> Option<String> {
+    calls.iter()
+        .find(|(_, s)| !matches!(s.target, CallTarget::Queue(_)) && session_room_name(s) == room)   // queued: no room yet
+        .map(|(k, _)| k.clone())
+}
+
+async fn end_group_call(state: &AppState, io: &SocketIo, group_id: &str, members: &[String], reason: &str) {
+    let payload = GroupCallEndedPayload { group_id: group_id.to_owned(), reason: reason.to_owned() };
+    emit_to_users(io, &state.users, members.iter().map(String::as_str),
+        event::GROUP_CALL_ENDED, &payload).await;
+    info!("[media/webhook] group call '{group_id}' ended — {reason}");
+}
+
+pub async fn emit_to_users<'a, T: serde::Serialize>(
+    io:      &SocketIo,
+    users:   &UserMap,
+    targets: impl IntoIterator<Item = &'a str>,
+    ev:      &'static str,
+    payload: &T,
+) {
+    let users = users.read().await;
+    for uid in targets {
+        let Some(s) = users.get(uid) else { continue; };
+        for sid in &s.socket_ids {
+            if let Some(peer) = io.get_socket(*sid) {
+                let _ = peer.emit(ev, payload);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::HeaderValue;
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use livekit_api::access_token::AccessToken;
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::{
+        media::{livekit::{LiveKitBackend, LiveKitConfig}, MediaBackend},
+        settings::LiveKitSettings,
+        test_support,
+    };
+
+    const KEY:    &str = "devkey";
+    const SECRET: &str = "devsecret-devsecret-devsecret-32";
+
+    fn livekit() -> LiveKitBackend {
+        LiveKitBackend::new(LiveKitConfig::from_settings(&LiveKitSettings {
+            url:        Some("ws://livekit.test".into()),
+            api_url:    Some("http://livekit.test".into()),
+            api_key:    Some(KEY.into()),
+            api_secret: Some(SECRET.into()),
+            ..LiveKitSettings::default()
+        }))
+    }
+
+    /// The Authorization header LiveKit sends with `body`.
+    fn sign(body: &str, secret: &str) -> String {
+        let hash = STANDARD.encode(Sha256::digest(body.as_bytes()));
+        AccessToken::with_api_key(KEY, secret).with_sha256(&hash).to_jwt().unwrap()
+    }
+
+    fn participant_left(room: &str, identity: &str) -> String {
+        format!(r#"{{"event":"participant_left","room":{{"name":"{room}"}},"participant":{{"identity":"{identity}"}}}}"#)
+    }
+
+    fn room_finished(room: &str) -> String {
+        format!(r#"{{"event":"room_finished","room":{{"name":"{room}"}}}}"#)
+    }
+
+    async fn deliver(state: &AppState, body: String, auth: &str) -> StatusCode {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(auth).unwrap());
+        on_media_webhook(State(state.clone()), Extension(test_support::io()), headers, body).await
+    }
+
+    // ── Verification ──────────────────────────────────────────────────────────
+
+    #[test]
+    fn accepts_a_signed_delivery() {
+        let body = participant_left("call::c1", "bob");
+        let ev = livekit().parse_webhook(&body, &sign(&body, SECRET));
+        assert!(matches!(ev, Some(MediaEvent::ParticipantLeft { ref room, ref identity })
+            if room == "call::c1" && identity == "bob"));
+    }
+
+    #[test]
+    fn rejects_a_tampered_body() {
+        let body = participant_left("call::c1", "bob");
+        let auth = sign(&body, SECRET);
+        let tampered = participant_left("call::c1", "alice");
+        assert!(livekit().parse_webhook(&tampered, &auth).is_none());
+    }
+
+    #[test]
+    fn rejects_a_foreign_signature() {
+        let body = room_finished("call::c1");
+        assert!(livekit().parse_webhook(&body, &sign(&body, "some-other-secret-some-other-sec")).is_none());
+        assert!(livekit().parse_webhook(&body, "not-a-jwt").is_none());
+    }
+
+    #[tokio::test]
+    async fn unsigned_deliveries_change_nothing() {
+        let state = test_support::state_with_media(Arc::new(livekit()));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Active).await;
+
+        let body = room_finished("call::c1");
+        let auth = sign(&participant_left("call::c1", "bob"), SECRET);
+        assert_eq!(deliver(&state, body, &auth).await, StatusCode::UNAUTHORIZED);
+        assert!(state.calls.read().await.contains_key("bob"));
+    }
+
+    // ── Dispatch ──────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn participant_left_ends_a_call_only_for_its_parties() {
+        let state = test_support::state_with_media(Arc::new(livekit()));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Active).await;
+
+        let body = participant_left("call::c1", "EG_recorder");
+        assert_eq!(deliver(&state, body.clone(), &sign(&body, SECRET)).await, StatusCode::OK);
+        assert!(state.calls.read().await.contains_key("bob"), "a recorder leaving must not end the call");
+
+        let body = participant_left("call::c1", "bob");
+        assert_eq!(deliver(&state, body.clone(), &sign(&body, SECRET)).await, StatusCode::OK);
+        assert!(!state.calls.read().await.contains_key("bob"));
+        assert_eq!(state.history.read().await["c1"].outcome, Some(CallOutcome::Dropped));
+    }
+
+    #[tokio::test]
+    async fn participant_left_after_a_tab_handoff_keeps_the_call() {
+        let state = test_support::state_with_media(Arc::new(livekit()));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Active).await;
+        state.calls.write().await.get_mut("bob").unwrap().handoff = Some(("bob".into(), std::time::Instant::now()));
+
+        // The old tab being dropped by the media server
+        let body = participant_left("call::c1", "bob");
+        deliver(&state, body.clone(), &sign(&body, SECRET)).await;
+        assert!(state.calls.read().await.contains_key("bob"));
+
+        // Only that one leave is expected
+        deliver(&state, body.clone(), &sign(&body, SECRET)).await;
+        assert!(!state.calls.read().await.contains_key("bob"));
+    }
+
+    #[tokio::test]
+    async fn participant_left_ignores_ringing_calls() {
+        let state = test_support::state_with_media(Arc::new(livekit()));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Ringing).await;
+
+        let body = participant_left("call::c1", "alice");
+        deliver(&state, body.clone(), &sign(&body, SECRET)).await;
+        assert!(state.calls.read().await.contains_key("bob"));
+    }
+
+    #[tokio::test]
+    async fn participant_left_keeps_a_group_call_until_the_last_one_leaves() {
+        let state = test_support::state_with_media(Arc::new(livekit()));
+        test_support::add_call(&state, "g1", "c2", "alice", CallTarget::Group("g1".into()), CallStatus::Active).await;
+        state.calls.write().await.get_mut("g1").unwrap().participants = vec!["alice".into(), "bob".into()];
+
+        let body = participant_left("group::g1::c2", "alice");
+        deliver(&state, body.clone(), &sign(&body, SECRET)).await;
+        assert_eq!(state.calls.read().await["g1"].participants, vec!["bob".to_owned()]);
+
+        let body = participant_left("group::g1::c2", "bob");
+        deliver(&state, body.clone(), &sign(&body, SECRET)).await;
+        assert!(!state.calls.read().await.contains_key("g1"));
+    }
+
+    #[tokio::test]
+    async fn room_finished_ends_the_call_bound_to_it() {
+        let state = test_support::state_with_media(Arc::new(livekit()));
+        test_support::add_call(&state, "bob", "c1", "alice", CallTarget::User("bob".into()), CallStatus::Active).await;
+        test_support::add_call(&state, "dave", "c3", "carol", CallTarget::User("dave".into()), CallStatus::Active).await;
+
+        let body = room_finished("call::c1");
+        assert_eq!(deliver(&state, body.clone(), &sign(&body, SECRET)).await, StatusCode::OK);
+        let calls = state.calls.read().await;
+        assert!(!calls.contains_key("bob"));
+        assert!(calls.contains_key("dave"), "other rooms are untouched");
+        assert_eq!(state.history.read().await["c1"].outcome, Some(CallOutcome::Dropped));
+    }
+}
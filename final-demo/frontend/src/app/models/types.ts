@@ -21,8 +21,21 @@ export type CallState =
   | 'group_ringing' // incoming group ring
   | 'group_active'; // group call active
 
+/** Reply to `group_call_status`; `call_id` is null when the group has no call. */
+export interface GroupCallStatus {
+  group_id: string;
+  call_id: string | null;
+  caller: string | null;
+  active: boolean;
+  video: boolean;
+  locked: boolean;
+  participants: string[];
+  connected?: string[];     // in the media room, when the server can tell
+}
+
 export interface ActiveCall {
   type: 'direct' | 'group';
+  callId?: string;          // server-assigned, unique per call
   peerId?: string;          // for direct
   groupId?: string;         // for group
   groupName?: string;
@@ -31,6 +44,98 @@ export interface ActiveCall {
   video?: boolean;          // true if video call
   startTime?: number;
   rejectedCount?: number;   // ← add this
+  locked?: boolean;         // group call closed to new joiners
+  held?: boolean;           // parked with hold_call, media paused
+  queueId?: string;         // placed into / offered from a call queue
+}
+
+/** End-of-call measurements sent with 'call_quality_report'; unknown values are left out. */
+export interface CallQualityStats {
+  rtt_ms?: number;
+  jitter_ms?: number;
+  packet_loss_pct?: number;
+  mos?: number;             // estimated, 1.0–5.0
+  network_type?: string;    // e.g. 'wifi', 'cellular'
+}
+
+/**
+ * How a 1-to-1 call reaches our registered devices; open tabs always ring at once.
+ * `sequential` goes by each device's `ring_priority` (lowest first).
+ */
+export type RingStrategy =
+  | { mode: 'all' }
+  | { mode: 'desktop_first'; delay_sec: number }
+  | { mode: 'sequential'; step_sec: number };
+
+/** Who may ring us; enforced by the server when a 1-to-1 call is placed. */
+export interface CallPrefs {
+  dnd: boolean;
+  contacts_only: boolean;
+  contacts: string[];
+  leave_message: boolean;   // offer refused callers to leave a message, unanswered ones a voicemail
+  ring_strategy: RingStrategy;
+}
+
+/** Sent after an unanswered call: upload the audio to `upload_url` before `expires_at`. */
+/** A 1-to-1 call the user is on in another tab; `transfer_call_to_device` pulls it here. */
+export interface CallOnOtherDevice {
+  call_id: string;
+  peer: string;
+  video: boolean;
+  ringing: boolean;         // we placed it there and it is not answered yet
+}
+
+export interface VoicemailOffer {
+  call_id: string;
+  to: string;
+  upload_url: string;       // relative to the backend origin
+  token: string;            // one-time bearer token for the upload
+  max_bytes: number;
+  expires_at: string;       // RFC-3339
+}
+
+export interface QueuePosition {
+  call_id: string;
+  queue_id: string;
+  position: number;         // 1 = next to be answered
+  waiting: number;
+}
+
+export interface QueueInfo {
+  queue_id: string;
+  name: string;
+  strategy: 'longest_idle' | 'round_robin' | 'ring_all';
+  members: string[];
+  available: string[];      // agents free to take a call right now
+  waiting: number;
+}
+
+export interface Voicemail {
+  call_id: string;
+  url: string;              // GET with the device's push token as bearer
+  content_type: string;
+  size_bytes: number;
+}
+
+/** A call booked for later; exactly one of `to` / `group_id` is set. */
+export interface ScheduledCall {
+  schedule_id: string;
+  created_by: string;
+  title: string;
+  to: string | null;
+  group_id: string | null;
+  attendees: string[];
+  start_at: string;       // RFC 3339
+  remind_before: number;  // minutes
+  video: boolean;
+  reminded: boolean;
+}
+
+/** A second 1-to-1 call ringing while we are already on one. */
+export interface WaitingCall {
+  callId: string;
+  from: string;
+  video: boolean;
 }
 
 export interface ToastMessage {
@@ -46,6 +151,7 @@ export interface ChatMessage {
   group_id?: string;       // present for group messages
   content: string;
   timestamp: string;       // RFC-3339
+  voicemail?: Voicemail;   // present when the message is a voicemail
 }
 
 export interface ChatConversation {
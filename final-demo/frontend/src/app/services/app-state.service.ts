@@ -734,7 +734,8 @@ import { Injectable } from '@angular/core';
 import { BehaviorSubject } from 'rxjs';
 import {
   UserEntry, Group, CallState, ActiveCall, ToastMessage,
-  ChatMessage, ChatConversation,
+  ChatMessage, ChatConversation, GroupCallStatus, WaitingCall, ScheduledCall,
+  CallPrefs, VoicemailOffer, CallOnOtherDevice, QueuePosition, QueueInfo,
 } from '../models/types';
 import { WebSocketService } from './websocket.service';
 import { PushSubscriptionService } from './push-subscription.service';
@@ -750,9 +751,21 @@ export class AppStateService {
   public activeCall$ = new BehaviorSubject<ActiveCall | null>(null);
   public toasts$ = new BehaviorSubject<ToastMessage[]>([]);
   public conversations$ = new BehaviorSubject<ChatConversation[]>([]);
+  public groupCallStatus$ = new BehaviorSubject<Record<string, GroupCallStatus>>({});
+  public waitingCall$ = new BehaviorSubject<WaitingCall | null>(null);
+  public heldCall$ = new BehaviorSubject<ActiveCall | null>(null);
+  public scheduledCalls$ = new BehaviorSubject<ScheduledCall[]>([]);
+  public callPrefs$ = new BehaviorSubject<CallPrefs>({
+    dnd: false, contacts_only: false, contacts: [], leave_message: false, ring_strategy: { mode: 'all' },
+  });
+  public voicemailOffer$ = new BehaviorSubject<VoicemailOffer | null>(null);
+  public callOnOtherDevice$ = new BehaviorSubject<CallOnOtherDevice | null>(null);
+  public queuePosition$ = new BehaviorSubject<QueuePosition | null>(null);
+  public queues$ = new BehaviorSubject<QueueInfo[]>([]);
 
   // ── Mic mute state (UI binds to this) ────────────────────────────────────
   public micMuted$ = new BehaviorSubject<boolean>(false);
+  public recording$ = new BehaviorSubject<boolean>(false);
 
   private audioCtx: AudioContext | null = null;
   private ringInterval: any = null;
@@ -825,12 +838,22 @@ export class AppStateService {
 
       case 'registered':
         this.currentUserId$.next(data.user_id);
+        this.ws.send('list_scheduled_calls', { from: data.user_id });
+        this.ws.send('get_call_prefs', { user_id: data.user_id });
         break;
 
       case 'register_error':
         this.toast('error', data.message);
         break;
 
+      case 'server_shutdown':
+        // Calls are ended separately unless keep_media hands them off to the media server
+        this.ws.reconnectAfter(data.reconnect_after_ms);
+        this.toast('warning', data.keep_media && this.callState$.value === 'active'
+          ? `🔄 ${data.reason} — your call continues, reconnecting…`
+          : `🔄 ${data.reason} — reconnecting…`);
+        break;
+
       case 'user_list':
         this.users$.next(data.users);
         break;
@@ -857,8 +880,9 @@ export class AppStateService {
         if (call.type === 'direct' && call.peerId === offlineId) {
           this.stopRing();
           this.push.dismissCallNotification(offlineId);
-          this.liveKit.disconnect();
+          this.leaveMedia();
           this.micMuted$.next(false);
+          this.recording$.next(false);
           this.callState$.next('idle');
           this.activeCall$.next(null);
           this.toast('warning', `📵 ${offlineId} disconnected`);
@@ -885,26 +909,61 @@ export class AppStateService {
       // ── 1-to-1 call ──────────────────────────────────────────────────────
 
       case 'incoming_call':
+        if (data.waiting) {
+          // We are on a call — offer accept-and-hold / accept-and-end instead of ringing
+          this.waitingCall$.next({ callId: data.call_id, from: data.from, video: data.video });
+          this.toast('info', `📞 ${data.from} is calling (call waiting)`);
+          break;
+        }
         if (this.callState$.value !== 'idle') break;
         this.callState$.next('ringing');
-        this.activeCall$.next({ type: 'direct', peerId: data.from, direction: 'incoming', video: data.video });
+        this.activeCall$.next({
+          type: 'direct', callId: data.call_id, peerId: data.from,
+          direction: 'incoming', video: data.video, queueId: data.queue,
+        });
         this.startRing();
-        this.toast('info', `📞 Incoming call from ${data.from}`);
+        this.toast('info', data.queue
+          ? `📞 ${data.from} is waiting in ${data.queue}`
+          : `📞 Incoming call from ${data.from}`);
         this.flushPendingAction('direct', data.from);
         break;
 
       case 'call_accepted':
         // Caller side — LiveKit token arrives separately via 'livekit_token' event
+        // (our other tabs get call_on_other_device instead)
+        if (!this.activeCall$.value) break;
         this.stopRing();
+        this.queuePosition$.next(null);
         this.callState$.next('active');
         this.activeCall$.next({
           ...this.activeCall$.value!,
+          peerId: data.by,          // for a queued call: the agent who answered
           direction: 'outgoing',
           startTime: Date.now(),
         } as ActiveCall);
         this.toast('success', `✅ Call connected`);
         break;
 
+      case 'call_unavailable':
+        // Refused by the callee's call prefs before anything rang
+        this.stopRing();
+        this.callState$.next('idle');
+        this.activeCall$.next(null);
+        this.toast('warning', data.leave_message
+          ? `📵 ${data.reason} — leave them a message instead`
+          : `📵 ${data.reason}`);
+        break;
+
+      case 'call_prefs':
+        this.callPrefs$.next(data.prefs);
+        break;
+
+      case 'voicemail_offer':
+        // Follows call_ended "No answer" when the callee takes voicemail
+        this.voicemailOffer$.next(data);
+        this.toast('info', `🎙 ${data.to} didn't answer — leave a voicemail?`);
+        break;
+
       case 'call_rejected':
         this.stopRing();
         this.callState$.next('idle');
@@ -926,10 +985,106 @@ export class AppStateService {
           const call = this.activeCall$.value;
           if (call?.peerId) this.push.dismissCallNotification(call.peerId);
         }
-        this.liveKit.disconnect();
+        this.leaveMedia();
         this.micMuted$.next(false);
+        this.recording$.next(false);
         this.callState$.next('idle');
         this.activeCall$.next(null);
+        this.callOnOtherDevice$.next(null);
+        this.queuePosition$.next(null);
+        this.toast('info', `📵 ${data.reason}`);
+        break;
+
+      case 'call_escalated': {
+        // Someone invited a third party — same media room, but from now on
+        // the call is driven with the group_* events under data.group_id
+        const call = this.activeCall$.value;
+        if (!call || call.callId !== data.call_id) break;
+        this.activeCall$.next({
+          ...call, type: 'group', groupId: data.group_id, groupName: 'Ad-hoc call',
+          participants: data.participants,
+        });
+        this.callState$.next('group_active');
+        if (data.by !== this.userId) this.toast('info', `➕ ${data.by} is adding someone to the call`);
+        break;
+      }
+
+      // ── Call waiting / hold / transfer ────────────────────────────────────
+
+      case 'call_held': {
+        const call = this.activeCall$.value;
+        if (call?.callId === data.call_id) {
+          // The room stays open; we reconnect on resume
+          this.liveKit.disconnect();
+          this.heldCall$.next({ ...call, held: true });
+          this.callState$.next('idle');
+          this.activeCall$.next(null);
+        }
+        this.toast('info', data.by === this.userId ? '⏸ Call on hold' : `⏸ ${data.by} put the call on hold`);
+        break;
+      }
+
+      case 'call_resumed': {
+        const held = this.heldCall$.value;
+        if (held?.callId !== data.call_id) break;
+        this.heldCall$.next(null);
+        this.activeCall$.next({ ...held, held: false });
+        this.callState$.next('active');
+        // The resuming tab gets a livekit_token; the other side asks for one
+        if (data.by !== this.userId)
+          this.ws.send('refresh_livekit_token', { from: this.userId, group_id: null });
+        this.toast('info', `▶ Call with ${held.peerId} resumed`);
+        break;
+      }
+
+      case 'call_transferred': {
+        // We were handed over (or overflowed out of a queue): now ringing data.to on a new call
+        const call = this.activeCall$.value;
+        this.queuePosition$.next(null);
+        this.leaveMedia();
+        this.callState$.next('calling');
+        this.activeCall$.next({
+          type: 'direct', callId: data.call_id, peerId: data.to,
+          direction: 'outgoing', video: call?.video,
+        });
+        this.startRing();
+        this.toast('info', `↪ ${data.by} transferred you to ${data.to}`);
+        break;
+      }
+
+      case 'call_on_other_device': {
+        // Answered on another tab while this one was ringing
+        const call = this.activeCall$.value;
+        if (this.callState$.value === 'ringing' && call?.callId === data.call_id) {
+          this.stopRing();
+          if (call.peerId) this.push.dismissCallNotification(call.peerId);
+          this.callState$.next('idle');
+          this.activeCall$.next(null);
+        }
+        if (this.callState$.value === 'idle') this.callOnOtherDevice$.next(data);
+        break;
+      }
+
+      case 'call_on_other_device_ended':
+        if (this.callOnOtherDevice$.value?.call_id === data.call_id) this.callOnOtherDevice$.next(null);
+        break;
+
+      case 'call_moved':
+        if (data.user_id === this.userId) {
+          // Another of our tabs took the call over; it is not over, so no quality report
+          this.liveKit.disconnect();
+          this.micMuted$.next(false);
+          this.callState$.next('idle');
+          this.activeCall$.next(null);
+          this.toast('info', `📱 Call moved to another device`);
+        } else {
+          this.toast('info', `📱 ${data.user_id} switched devices`);
+        }
+        break;
+
+      case 'waiting_call_ended':
+        if (this.waitingCall$.value?.callId === data.call_id) this.waitingCall$.next(null);
+        if (this.heldCall$.value?.callId === data.call_id) this.heldCall$.next(null);
         this.toast('info', `📵 ${data.reason}`);
         break;
 
@@ -937,6 +1092,9 @@ export class AppStateService {
 
       case 'livekit_token':
         // Received by BOTH caller (after call_accepted) and callee (after accept)
+        if (!this.adoptCallId(data.call_id)) break;
+        // Only the LiveKit backend has a server to connect to ('p2p' / 'none' don't)
+        if (data.media && data.media !== 'livekit') break;
         console.log('[livekit] Token received for room:', data.room);
         this.liveKit.connect(data.url, data.token).catch(err => {
           console.error('[livekit] Connect failed:', err);
@@ -946,6 +1104,8 @@ export class AppStateService {
 
       case 'group_livekit_token':
         // Received by caller on group_call start, and by each member on group_accept
+        if (!this.adoptCallId(data.call_id)) break;
+        if (data.media && data.media !== 'livekit') break;
         console.log('[livekit] Group token received for room:', data.room);
         this.liveKit.connect(data.url, data.token).catch(err => {
           console.error('[livekit] Group connect failed:', err);
@@ -953,6 +1113,84 @@ export class AppStateService {
         });
         break;
 
+      // ── Scheduled calls ───────────────────────────────────────────────────
+
+      case 'scheduled_calls':
+        this.scheduledCalls$.next(data.calls);
+        break;
+
+      // ── Call queues ───────────────────────────────────────────────────────
+
+      case 'queue_position': {
+        const call = this.activeCall$.value;
+        if (this.callState$.value !== 'calling' || call?.queueId !== data.queue_id) break;
+        if (call.callId !== data.call_id) this.activeCall$.next({ ...call, callId: data.call_id });
+        this.queuePosition$.next(data);
+        break;
+      }
+
+      case 'queues':
+        this.queues$.next(data.queues);
+        break;
+
+      case 'call_scheduled': {
+        const others = this.scheduledCalls$.value.filter(c => c.schedule_id !== data.schedule_id);
+        this.scheduledCalls$.next([...others, data].sort((a, b) => a.start_at.localeCompare(b.start_at)));
+        if (data.created_by !== this.userId)
+          this.toast('info', `📅 ${data.created_by} scheduled "${data.title}" for ${new Date(data.start_at).toLocaleString()}`);
+        break;
+      }
+
+      case 'scheduled_call_reminder':
+        this.toast('info', `⏰ "${data.title}" starts in ${data.remind_before} min`);
+        break;
+
+      case 'scheduled_call_cancelled': {
+        this.scheduledCalls$.next(this.scheduledCalls$.value.filter(c => c.schedule_id !== data.schedule_id));
+        // The start may already have put us in the calling state
+        const cs = this.callState$.value;
+        if ((cs === 'calling' || cs === 'group_calling') && !this.activeCall$.value?.callId) {
+          this.stopRing();
+          this.callState$.next('idle');
+          this.activeCall$.next(null);
+        }
+        this.toast('warning', `📅 Scheduled call cancelled: ${data.reason}`);
+        break;
+      }
+
+      case 'scheduled_call_started': {
+        // We created it — the server is placing the call from this tab
+        this.scheduledCalls$.next(this.scheduledCalls$.value.filter(c => c.schedule_id !== data.schedule_id));
+        if (this.callState$.value !== 'idle') break;
+        if (data.group_id) {
+          const group = this.groups$.value.find(g => g.group_id === data.group_id);
+          this.callState$.next('group_calling');
+          this.activeCall$.next({
+            type: 'group', groupId: data.group_id, groupName: group?.name,
+            direction: 'outgoing', participants: [this.userId!], video: data.video,
+          });
+        } else {
+          this.callState$.next('calling');
+          this.activeCall$.next({ type: 'direct', peerId: data.to, direction: 'outgoing', video: data.video });
+        }
+        this.startRing();
+        break;
+      }
+
+      // ── Recording ─────────────────────────────────────────────────────────
+
+      case 'recording_started':
+        if (this.activeCall$.value?.callId !== data.call_id) break;
+        this.recording$.next(true);
+        this.toast('info', `⏺ ${data.by} started recording this call`);
+        break;
+
+      case 'recording_stopped':
+        if (this.activeCall$.value?.callId !== data.call_id) break;
+        this.recording$.next(false);
+        this.toast('info', `⏹ Recording stopped`);
+        break;
+
       // ── Groups ────────────────────────────────────────────────────────────
 
       case 'group_created': {
@@ -976,8 +1214,9 @@ export class AppStateService {
         this.groups$.next(this.groups$.value.filter(g => g.group_id !== data.group_id));
         if (this.activeCall$.value?.groupId === data.group_id) {
           this.stopRing();
-          this.liveKit.disconnect();
+          this.leaveMedia();
           this.micMuted$.next(false);
+          this.recording$.next(false);
           this.callState$.next('idle');
           this.activeCall$.next(null);
         }
@@ -990,6 +1229,7 @@ export class AppStateService {
         this.callState$.next('group_ringing');
         this.activeCall$.next({
           type: 'group',
+          callId: data.call_id,
           groupId: data.group_id,
           groupName: data.group_name,
           direction: 'incoming',
@@ -1064,11 +1304,45 @@ export class AppStateService {
       }
 
       case 'group_call_ended':
-        this.liveKit.disconnect();
+        this.leaveMedia();
         this.micMuted$.next(false);
+        this.recording$.next(false);
         this.handleGroupCallEnded(data);
         break;
 
+      case 'group_call_status':
+        this.groupCallStatus$.next({ ...this.groupCallStatus$.value, [data.group_id]: data });
+        break;
+
+      // ── Group call moderation ─────────────────────────────────────────────
+
+      case 'group_participant_muted':
+        if (this.activeCall$.value?.groupId !== data.group_id) break;
+        if (data.user_id === this.userId) {
+          this.micMuted$.next(true);
+          this.toast('warning', `🔇 ${data.by} muted you`);
+        } else {
+          this.toast('info', `🔇 ${data.by} muted ${data.user_id}`);
+        }
+        break;
+
+      case 'group_participant_removed': {
+        const call = this.activeCall$.value;
+        if (!call || call.groupId !== data.group_id || data.user_id === this.userId) break;
+        const participants = (call.participants || []).filter(p => p !== data.user_id);
+        this.activeCall$.next({ ...call, participants });
+        this.toast('info', `⛔ ${data.by} removed ${data.user_id} from the call`);
+        break;
+      }
+
+      case 'group_call_locked': {
+        const call = this.activeCall$.value;
+        if (!call || call.groupId !== data.group_id) break;
+        this.activeCall$.next({ ...call, locked: data.locked });
+        this.toast('info', data.locked ? `🔒 ${data.by} locked the call` : `🔓 ${data.by} unlocked the call`);
+        break;
+      }
+
       // ── Chat ─────────────────────────────────────────────────────────────
 
       case 'direct_message':
@@ -1078,6 +1352,7 @@ export class AppStateService {
           to: data.to,
           content: data.content,
           timestamp: data.timestamp,
+          voicemail: data.voicemail,
         }, this.dmKey(data.from, data.to));
         break;
 
@@ -1160,8 +1435,15 @@ export class AppStateService {
 
   cancelCall(): void {
     const call = this.activeCall$.value;
-    if (!this.userId || !call?.peerId) return;
-    this.ws.send('cancel', { from: this.userId, to: call.peerId });
+    if (!this.userId) return;
+    if (call?.queueId && call.direction === 'outgoing') {
+      if (call.callId) this.ws.send('leave_queue', { from: this.userId, call_id: call.callId });
+      this.queuePosition$.next(null);
+    } else if (call?.peerId) {
+      this.ws.send('cancel', { from: this.userId, to: call.peerId });
+    } else {
+      return;
+    }
     this.stopRing();
     this.callState$.next('idle');
     this.activeCall$.next(null);
@@ -1176,7 +1458,8 @@ export class AppStateService {
     this.stopRing();
     this.callState$.next('active');
     this.activeCall$.next({ ...call!, startTime: Date.now(), video } as ActiveCall);
-    this.ws.send('accept', { from: this.userId, to: from });
+    if (call?.queueId && call.callId) this.ws.send('accept_queue_call', { from: this.userId, call_id: call.callId });
+    else this.ws.send('accept', { from: this.userId, to: from });
     // LiveKit token arrives via 'livekit_token' event
   }
 
@@ -1187,7 +1470,8 @@ export class AppStateService {
     if (this.callState$.value !== 'ringing') return;
     this.push.dismissCallNotification(from);
     this.stopRing();
-    this.ws.send('reject', { from: this.userId, to: from });
+    if (call?.queueId && call.callId) this.ws.send('reject_queue_call', { from: this.userId, call_id: call.callId });
+    else this.ws.send('reject', { from: this.userId, to: from });
     this.callState$.next('idle');
     this.activeCall$.next(null);
   }
@@ -1201,12 +1485,173 @@ export class AppStateService {
       this.ws.send('group_cut', { from: this.userId, group_id: call.groupId });
     }
     this.stopRing();
-    this.liveKit.disconnect();
+    this.leaveMedia();
     this.micMuted$.next(false);
+    this.recording$.next(false);
     this.callState$.next('idle');
     this.activeCall$.next(null);
   }
 
+  /** Leaves the media room, first sending our quality report if we were connected. */
+  private leaveMedia(): void {
+    const callId = this.activeCall$.value?.callId;
+    const from = this.userId;
+    this.liveKit.collectQualityStats()
+      .then(stats => {
+        if (stats && callId && from) this.ws.send('call_quality_report', { from, call_id: callId, ...stats });
+      })
+      .catch(err => console.warn('[quality] stats unavailable:', err))
+      .finally(() => this.liveKit.disconnect());
+  }
+
+  /** Answer the waiting call; the current one is put on hold or ended. */
+  acceptWaitingCall(activeCall: 'hold' | 'end' = 'hold'): void {
+    const waiting = this.waitingCall$.value;
+    const current = this.activeCall$.value;
+    if (!this.userId || !waiting) return;
+    this.liveKit.disconnect();
+    this.micMuted$.next(false);
+    if (activeCall === 'hold' && current) this.heldCall$.next({ ...current, held: true });
+    this.waitingCall$.next(null);
+    this.callState$.next('active');
+    this.activeCall$.next({
+      type: 'direct', callId: waiting.callId, peerId: waiting.from,
+      direction: 'incoming', video: waiting.video, startTime: Date.now(),
+    });
+    this.ws.send('accept', { from: this.userId, to: waiting.from, active_call: activeCall });
+  }
+
+  rejectWaitingCall(): void {
+    const waiting = this.waitingCall$.value;
+    if (!this.userId || !waiting) return;
+    this.ws.send('reject', { from: this.userId, to: waiting.from });
+    this.waitingCall$.next(null);
+  }
+
+  holdCall(): void {
+    const call = this.activeCall$.value;
+    if (!this.userId || call?.type !== 'direct' || !call.callId) return;
+    this.ws.send('hold_call', { from: this.userId, call_id: call.callId });
+  }
+
+  /** Only whoever put the call on hold can resume it; the current call is held in its place. */
+  resumeCall(): void {
+    const held = this.heldCall$.value;
+    if (!this.userId || !held?.callId) return;
+    this.ws.send('resume_call', { from: this.userId, call_id: held.callId });
+  }
+
+  /** Blind transfer: the other party rings `userId` and we drop out. */
+  transferCall(userId: string): void {
+    const callId = this.activeCall$.value?.callId;
+    if (!this.userId || !callId) return;
+    this.ws.send('transfer_call', { from: this.userId, call_id: callId, to: userId });
+  }
+
+  /** Pull a call we are on in another tab into this one. */
+  transferCallToDevice(): void {
+    const elsewhere = this.callOnOtherDevice$.value;
+    if (!this.userId || !elsewhere || elsewhere.ringing) return;
+    this.callOnOtherDevice$.next(null);
+    this.callState$.next('active');
+    this.activeCall$.next({
+      type: 'direct', callId: elsewhere.call_id, peerId: elsewhere.peer,
+      direction: 'outgoing', video: elsewhere.video, startTime: Date.now(),
+    });
+    this.ws.send('transfer_call_to_device', { from: this.userId, call_id: elsewhere.call_id });
+  }
+
+  /** Wait in a call queue for the next free agent. */
+  callQueue(queueId: string, video = false): void {
+    if (!this.userId || this.callState$.value !== 'idle') return;
+    this.callState$.next('calling');
+    this.activeCall$.next({ type: 'direct', direction: 'outgoing', video, queueId });
+    this.startRing();
+    this.ws.send('call_queue', { from: this.userId, queue_id: queueId, video });
+  }
+
+  listQueues(): void {
+    if (this.userId) this.ws.send('list_queues', { from: this.userId });
+  }
+
+  /** Hang up (or cancel, while it rings) the call we are on in another tab. */
+  endCallOnOtherDevice(): void {
+    const elsewhere = this.callOnOtherDevice$.value;
+    if (!this.userId || !elsewhere) return;
+    this.ws.send(elsewhere.ringing ? 'cancel' : 'cut_call', { from: this.userId, to: elsewhere.peer });
+  }
+
+  /** Bring another user into the current call (turns a 1-to-1 into an ad-hoc group call). */
+  inviteToCall(userId: string): void {
+    const callId = this.activeCall$.value?.callId;
+    if (!this.userId || !callId) return;
+    this.ws.send('invite_to_call', { from: this.userId, call_id: callId, user_id: userId });
+  }
+
+  // ── Call preferences ──────────────────────────────────────────────────────
+
+  /** Replaces our call prefs; every tab gets the result via 'call_prefs'. */
+  setCallPrefs(prefs: Partial<CallPrefs>): void {
+    if (!this.userId) return;
+    this.ws.send('set_call_prefs', { user_id: this.userId, prefs: { ...this.callPrefs$.value, ...prefs } });
+  }
+
+  // ── Voicemail ─────────────────────────────────────────────────────────────
+
+  /** Uploads a recording for the pending offer; it arrives back as a 'direct_message'. */
+  async leaveVoicemail(audio: Blob): Promise<void> {
+    const offer = this.voicemailOffer$.value;
+    if (!offer) return;
+    this.voicemailOffer$.next(null);
+    if (audio.size > offer.max_bytes) {
+      this.toast('error', '🎙 Voicemail is too long');
+      return;
+    }
+    try {
+      const res = await fetch(this.ws.SERVER_URL + offer.upload_url, {
+        method: 'POST',
+        headers: { 'Authorization': `Bearer ${offer.token}`, 'Content-Type': audio.type || 'audio/webm' },
+        body: audio,
+      });
+      if (!res.ok) throw new Error((await res.json().catch(() => null))?.message ?? res.statusText);
+      this.toast('success', `🎙 Voicemail sent to ${offer.to}`);
+    } catch (e: any) {
+      this.toast('error', `🎙 Voicemail not sent: ${e.message}`);
+    }
+  }
+
+  dismissVoicemailOffer(): void {
+    this.voicemailOffer$.next(null);
+  }
+
+  // ── Scheduled calls ───────────────────────────────────────────────────────
+
+  /** Book a call with a user or a group; `startAt` must be in the future. */
+  scheduleCall(target: { to?: string; groupId?: string }, startAt: Date, title?: string,
+               remindBefore?: number, video: boolean = false): void {
+    if (!this.userId) return;
+    this.ws.send('schedule_call', {
+      from: this.userId, to: target.to, group_id: target.groupId,
+      start_at: startAt.toISOString(), title, remind_before: remindBefore, video,
+    });
+  }
+
+  /** Creator only. */
+  cancelScheduledCall(scheduleId: string): void {
+    if (!this.userId) return;
+    this.ws.send('cancel_scheduled_call', { from: this.userId, schedule_id: scheduleId });
+  }
+
+  // ── Recording ─────────────────────────────────────────────────────────────
+
+  /** Caller (or group admin) only — the server rejects anyone else. */
+  toggleRecording(): void {
+    const callId = this.activeCall$.value?.callId;
+    if (!this.userId || !callId) return;
+    const ev = this.recording$.value ? 'stop_recording' : 'start_recording';
+    this.ws.send(ev, { from: this.userId, call_id: callId });
+  }
+
   // ── Group call actions ────────────────────────────────────────────────────
 
   makeGroupCall(groupId: string, video: boolean = false): void {
@@ -1272,6 +1717,54 @@ export class AppStateService {
     this.activeCall$.next(null);
   }
 
+  /** Ask who is in a group's call; the answer lands in groupCallStatus$. */
+  queryGroupCallStatus(groupId: string): void {
+    if (!this.userId) return;
+    this.ws.send('group_call_status', { from: this.userId, group_id: groupId });
+  }
+
+  /** Late join into a running group call (after a missed or rejected ring). */
+  joinGroupCall(groupId: string, listenOnly: boolean = false): void {
+    if (!this.userId || this.callState$.value !== 'idle') return;
+    const status = this.groupCallStatus$.value[groupId];
+    const group = this.groups$.value.find(g => g.group_id === groupId);
+    this.callState$.next('group_active');
+    this.activeCall$.next({
+      type: 'group', groupId, groupName: group?.name, callId: status?.call_id ?? undefined,
+      direction: 'incoming', participants: status?.participants ?? [],
+      video: status?.video ?? false, startTime: Date.now(), locked: status?.locked,
+    });
+    this.ws.send('join_group_call', { from: this.userId, group_id: groupId, listen_only: listenOnly });
+    // LiveKit token arrives via 'group_livekit_token' event
+  }
+
+  /** Re-ring members who missed or declined (caller / group admin only). */
+  ringGroupMembers(userIds: string[]): void {
+    const groupId = this.activeCall$.value?.groupId;
+    if (!this.userId || !groupId || !userIds.length) return;
+    this.ws.send('group_call_ring', { from: this.userId, group_id: groupId, user_ids: userIds });
+  }
+
+  // ── Group call moderation (caller / group admin only) ─────────────────────
+
+  muteGroupParticipant(userId: string): void {
+    const groupId = this.activeCall$.value?.groupId;
+    if (!this.userId || !groupId) return;
+    this.ws.send('group_call_mute_participant', { from: this.userId, group_id: groupId, user_id: userId });
+  }
+
+  removeGroupParticipant(userId: string): void {
+    const groupId = this.activeCall$.value?.groupId;
+    if (!this.userId || !groupId) return;
+    this.ws.send('group_call_remove_participant', { from: this.userId, group_id: groupId, user_id: userId });
+  }
+
+  setGroupCallLocked(locked: boolean): void {
+    const groupId = this.activeCall$.value?.groupId;
+    if (!this.userId || !groupId) return;
+    this.ws.send('group_call_lock', { from: this.userId, group_id: groupId, locked });
+  }
+
   // ── Group management ──────────────────────────────────────────────────────
 
   createGroup(name: string, members: string[]): void {
@@ -1412,6 +1905,22 @@ export class AppStateService {
     this.ws.send('send_group_message', { from: this.userId, group_id: groupId, content });
   }
 
+  // ── Call identity ─────────────────────────────────────────────────────────
+
+  /**
+   * Ties a LiveKit token to the current call. The outgoing side learns the call
+   * id from its first token; a token for any other call is stale and ignored.
+   */
+  private adoptCallId(callId: string): boolean {
+    const call = this.activeCall$.value;
+    if (call?.callId && call.callId !== callId) {
+      console.warn('[livekit] ignoring token for stale call', callId);
+      return false;
+    }
+    if (call && !call.callId) this.activeCall$.next({ ...call, callId });
+    return true;
+  }
+
   // ── Flush pending notification action ─────────────────────────────────────
 
   private flushPendingAction(
@@ -15,6 +15,7 @@ import {
   LocalAudioTrack,
 } from 'livekit-client';
 import { BehaviorSubject } from 'rxjs';
+import { CallQualityStats } from '../models/types';
 
 @Injectable({ providedIn: 'root' })
 export class LiveKitService {
@@ -66,6 +67,27 @@ export class LiveKitService {
     console.log('[livekit] Connected to room:', this.room.name);
   }
 
+  // ── Call quality ──────────────────────────────────────────────────────────
+
+  /** RTT, jitter and loss of our outgoing audio as the far end reports them. Call before disconnect(). */
+  async collectQualityStats(): Promise<CallQualityStats | null> {
+    const sender = this.localAudioTrack?.sender;
+    if (!sender) return null;
+
+    const stats: CallQualityStats = {};
+    (await sender.getStats()).forEach((s: any) => {
+      if (s.type !== 'remote-inbound-rtp') return;
+      if (s.roundTripTime != null) stats.rtt_ms = s.roundTripTime * 1000;
+      if (s.jitter != null) stats.jitter_ms = s.jitter * 1000;
+      if (s.fractionLost != null) stats.packet_loss_pct = s.fractionLost * 100;
+    });
+    if (stats.rtt_ms != null) stats.mos = estimateMos(stats.rtt_ms, stats.jitter_ms ?? 0, stats.packet_loss_pct ?? 0);
+
+    const connection = (navigator as any).connection;
+    if (connection?.type || connection?.effectiveType) stats.network_type = connection.type ?? connection.effectiveType;
+    return stats;
+  }
+
   // ── Disconnect and clean up ───────────────────────────────────────────────
 
   async disconnect(): Promise<void> {
@@ -224,4 +246,12 @@ export class LiveKitService {
       console.log('[livekit] Audio removed for:', identity);
     }
   }
+}
+
+// Simplified ITU-T G.107 E-model: one-way delay plus jitter buffer, and loss, lower R.
+function estimateMos(rttMs: number, jitterMs: number, lossPct: number): number {
+  const latency = rttMs / 2 + jitterMs * 2 + 10;
+  const r = 93.2 - (latency < 160 ? latency / 40 : (latency - 120) / 10) - lossPct * 2.5;
+  const mos = 1 + 0.035 * r + 0.000007 * r * (r - 60) * (100 - r);
+  return Math.round(Math.min(4.5, Math.max(1, mos)) * 100) / 100;
 }
\ No newline at end of file
@@ -16,17 +16,42 @@ const FIREBASE_CONFIG = {
 const FIREBASE_VAPID_KEY =
   'BO9faYhBz9d_XZljy1qc_qE4pX09zy0SNUtAMynYYAApEIZrQxwSjVOIgSQYY3m7fVQyTCq5yl7bucLdWV55Fqc';
 
+const DEVICE_ID_KEY = 'push_device_id';
+const API_KEY_KEY   = 'push_api_key';
+
 const fbApp     = getApps().length ? getApps()[0] : initializeApp(FIREBASE_CONFIG);
 const messaging = getMessaging(fbApp);
 
 @Injectable({ providedIn: 'root' })
 export class PushSubscriptionService {
+  private userId = '';
+
+  constructor(private wsService: WebSocketService) {
+    // The server answers store_fcm_token with this device's key for its HTTP endpoints
+    this.wsService.events$.subscribe(({ event, data }) => {
+      if (event !== 'device_registered') return;
+      localStorage.setItem(API_KEY_KEY, data.api_key);
+      // The service worker fetches content-free chat_sync pushes with it
+      navigator.serviceWorker?.ready.then(reg => {
+        reg.active?.postMessage({
+          type:      'SET_DEVICE_CREDENTIALS',
+          apiKey:    data.api_key,
+          serverUrl: this.wsService.SERVER_URL,
+          userId:    this.userId,
+        });
+      });
+    });
+  }
 
-  constructor(private wsService: WebSocketService) {}
+  /** Bearer credential for GET /messages, /recordings, /voicemail and /stats. */
+  apiKey(): string | null {
+    return localStorage.getItem(API_KEY_KEY);
+  }
 
   // ── Setup ──────────────────────────────────────────────────────────────────
 
   async setupAndSend(userId: string): Promise<boolean> {
+    this.userId = userId;
     if (!('serviceWorker' in navigator) || !('PushManager' in window)) {
       console.warn('[push] not supported');
       return false;
@@ -44,7 +69,12 @@ export class PushSubscriptionService {
       });
       if (!fcmToken) return false;
 
-      this.wsService.send('store_fcm_token', { user_id: userId, token: fcmToken });
+      this.wsService.send('store_fcm_token', {
+        user_id:   userId,
+        token:     fcmToken,
+        device_id: this.deviceId(),
+        platform:  'web',
+      });
       console.log('[push] FCM token sent to backend');
       return true;
     } catch (err) {
@@ -53,6 +83,16 @@ export class PushSubscriptionService {
     }
   }
 
+  /** Stable per-browser id so a refreshed token replaces this device's old one on the server. */
+  private deviceId(): string {
+    let id = localStorage.getItem(DEVICE_ID_KEY);
+    if (!id) {
+      id = crypto.randomUUID();
+      localStorage.setItem(DEVICE_ID_KEY, id);
+    }
+    return id;
+  }
+
   // ── SW → App message listener ──────────────────────────────────────────────
 
   /**
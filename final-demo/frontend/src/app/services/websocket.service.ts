@@ -76,7 +76,7 @@ export interface SocketEvent {
 @Injectable({ providedIn: 'root' })
 export class WebSocketService {
   private socket!: Socket;
-  private readonly SERVER_URL = 'http://localhost:3001';
+  readonly SERVER_URL = 'http://localhost:3001';
 
   public connected$ = new BehaviorSubject<boolean>(false);
   public events$    = new Subject<SocketEvent>();
@@ -99,18 +99,34 @@ export class WebSocketService {
     });
 
     const events = [
-      'registered', 'register_error',
+      'registered', 'register_error', 'server_shutdown',
       'user_list', 'user_online', 'user_offline',
       'incoming_call', 'call_accepted', 'call_rejected',
-      'call_cancelled', 'call_ended',
+      'call_cancelled', 'call_ended', 'call_escalated', 'call_unavailable',
+      'voicemail_offer',
+      // Call waiting / hold / transfer
+      'call_held', 'call_resumed', 'call_transferred', 'waiting_call_ended',
+      // Multi-device handoff
+      'call_on_other_device', 'call_on_other_device_ended', 'call_moved',
+      // Scheduled calls
+      'call_scheduled', 'scheduled_call_cancelled', 'scheduled_call_reminder',
+      'scheduled_call_started', 'scheduled_calls',
+      // Call queues
+      'queue_position', 'queues',
       'group_created', 'group_updated', 'group_deleted',
       'group_incoming_call', 'group_member_joined',
-      'group_member_left', 'group_call_ended',
+      'group_member_left', 'group_call_ended', 'group_call_status',
+      // Group call moderation
+      'group_participant_muted', 'group_participant_removed', 'group_call_locked',
       // Chat
       'direct_message', 'group_message', 'message_sent', 'message_history',
+      // Preferences
+      'call_prefs', 'device_registered',
       // ── LiveKit tokens ──────────────────────────────────────────────────
       'livekit_token',        // 1-to-1 call token
       'group_livekit_token',  // group call token
+      // Recording consent
+      'recording_started', 'recording_stopped',
       'error',
     ];
 
@@ -121,6 +137,11 @@ export class WebSocketService {
     });
   }
 
+  /** Waits at least `ms` before each reconnect attempt (server restart hint). */
+  reconnectAfter(ms: number): void {
+    this.socket?.io.reconnectionDelay(ms);
+  }
+
   send(event: string, payload: any): void {
     this.socket.emit(event, payload);
   }
@@ -14,43 +14,28 @@ const messaging = firebase.messaging();
 
 // ── Background push → show notification ──────────────────────────────────────
 messaging.onBackgroundMessage((payload) => {
+  // Pushes with a webpush.notification block were already shown by the FCM SDK.
+  if (payload.notification) return;
+
   const data   = payload.data || {};
   const action = data.action  || '';
 
   // ── Chat message notification ──────────────────────────────────────────────
   if (action === 'chat_message') {
-    const from      = data.sender     || 'Someone';   // FCM reserves "from" — backend sends "sender"
-    const to        = data.to         || '';
-    const content   = data.content    || '';
-    const groupId   = data.group_id   || '';
-    const groupName = data.group_name || '';
-    const isGroup   = !!groupId;
-
-    // DM notifications are grouped per sender; group chat per group —
-    // so they collapse/stack cleanly instead of spamming the tray.
-    const tag   = isGroup ? `chat-group-${groupId}` : `chat-dm-${from}`;
-    const title = isGroup ? `${from} in ${groupName}` : `💬 ${from}`;
-
-    self.registration.showNotification(title, {
-      body: content,
-      tag,
-      // renotify: true means each new message still makes a sound/vibration
-      // even though it reuses the same tag (replacing the previous bubble).
-      renotify: true,
-      data: { from, to, content, groupId, groupName, isGroup, tag, notifType: 'chat' },
-      actions: [
-        // type:'text' renders an inline reply box on Chrome desktop (Linux/Win/macOS)
-        // and Edge. On platforms that don't support text actions it falls back to
-        // a plain button that opens the app.
-        {
-          action:      'reply',
-          title:       '↩ Reply',
-          type:        'text',
-          placeholder: 'Type a reply…',
-        },
-      ],
+    return showChatNotification({
+      from:      data.sender     || 'Someone',   // FCM reserves "from" — backend sends "sender"
+      to:        data.to         || '',
+      content:   data.content    || '',
+      groupId:   data.group_id   || '',
+      groupName: data.group_name || '',
     });
-    return;
+  }
+
+  // ── Chat sync (content-free) — fetch the message, then notify ─────────────
+  // Never falls through to the call path below.
+  if (action === 'chat_sync') {
+    return fetchSyncedMessage(data.conversation_key || '', data.message_id || '')
+      .then(notice => notice && showChatNotification(notice));
   }
 
   // ── Incoming call notification (direct or group) ───────────────────────────
@@ -83,6 +68,11 @@ messaging.onBackgroundMessage((payload) => {
 self.addEventListener('message', (event) => {
   const { type, from, groupId } = event.data || {};
 
+  if (type === 'SET_DEVICE_CREDENTIALS') {
+    const { apiKey, serverUrl, userId } = event.data;
+    event.waitUntil(saveCredentials({ apiKey, serverUrl, userId }));
+  }
+
   if (type === 'DISMISS_CALL_NOTIFICATION') {
     self.registration.getNotifications({ tag: `incoming-call-${from}` })
       .then(ns => ns.forEach(n => n.close()));
@@ -107,7 +97,7 @@ self.addEventListener('message', (event) => {
 self.addEventListener('notificationclick', (event) => {
   event.notification.close();
 
-  const notifData = event.notification.data || {};
+  const notifData = notificationData(event.notification);
   const notifType = notifData.notifType || 'call';
 
   // ── Chat: inline reply action ──────────────────────────────────────────────
@@ -214,6 +204,103 @@ self.addEventListener('notificationclick', (event) => {
   );
 });
 
+// ── Helper: click data for our own and SDK-displayed notifications ─────────────
+// The FCM SDK keeps the original push under notification.data.FCM_MSG.
+function notificationData(notification) {
+  const fcm = notification.data && notification.data.FCM_MSG;
+  if (!fcm) return notification.data || {};
+
+  const data = fcm.data || {};
+  if (data.action === 'chat_message') {
+    const groupId = data.group_id || '';
+    return {
+      from: data.sender || '', to: data.to || '', content: data.content || '',
+      groupId, groupName: data.group_name || '', isGroup: !!groupId, notifType: 'chat',
+    };
+  }
+  return {
+    from: data.caller || '', to: data.callee || '', callType: data.callType || 'direct',
+    groupId: data.groupId || '', notifType: 'call',
+  };
+}
+
+// ── Helper: chat notification with an inline reply action ─────────────────────
+function showChatNotification({ from, to, content, groupId, groupName }) {
+  const isGroup = !!groupId;
+
+  // DM notifications are grouped per sender; group chat per group —
+  // so they collapse/stack cleanly instead of spamming the tray.
+  const tag   = isGroup ? `chat-group-${groupId}` : `chat-dm-${from}`;
+  const title = isGroup ? `${from} in ${groupName || groupId}` : `💬 ${from}`;
+
+  return self.registration.showNotification(title, {
+    body: content,
+    tag,
+    // renotify: true means each new message still makes a sound/vibration
+    // even though it reuses the same tag (replacing the previous bubble).
+    renotify: true,
+    data: { from, to, content, groupId, groupName, isGroup, tag, notifType: 'chat' },
+    actions: [
+      // type:'text' renders an inline reply box on Chrome desktop (Linux/Win/macOS)
+      // and Edge. On platforms that don't support text actions it falls back to
+      // a plain button that opens the app.
+      {
+        action:      'reply',
+        title:       '↩ Reply',
+        type:        'text',
+        placeholder: 'Type a reply…',
+      },
+    ],
+  });
+}
+
+// ── Device credentials (for chat_sync) ────────────────────────────────────────
+// The page posts the API key the backend issued this device; it is kept in the
+// Cache API so it outlives the worker being stopped between pushes.
+
+const CREDENTIALS_CACHE = 'device-credentials';
+const CREDENTIALS_URL   = '/__device_credentials';
+
+function saveCredentials(credentials) {
+  return caches.open(CREDENTIALS_CACHE)
+    .then(cache => cache.put(CREDENTIALS_URL, new Response(JSON.stringify(credentials))));
+}
+
+function loadCredentials() {
+  return caches.open(CREDENTIALS_CACHE)
+    .then(cache => cache.match(CREDENTIALS_URL))
+    .then(res => res ? res.json() : null);
+}
+
+/** GET /messages for a chat_sync push; resolves to what showChatNotification takes, or null. */
+function fetchSyncedMessage(conversationKey, messageId) {
+  return loadCredentials().then(creds => {
+    if (!creds || !creds.apiKey || !conversationKey || !messageId) return null;
+
+    const url = new URL('/messages', creds.serverUrl);
+    url.searchParams.set('conversation_key', conversationKey);
+    url.searchParams.set('message_id',       messageId);
+
+    return fetch(url.toString(), { headers: { 'Authorization': `Bearer ${creds.apiKey}` } })
+      .then(res => res.ok ? res.json() : null)
+      .then(msg => {
+        if (!msg) return null;
+        // Group keys are "group::{group_id}" and the message targets the group
+        const groupId = conversationKey.startsWith('group::') ? msg.target : '';
+        return {
+          from:      msg.from,
+          to:        groupId ? creds.userId : msg.target,
+          content:   msg.content,
+          groupId,
+          groupName: '',
+        };
+      });
+  }).catch(err => {
+    console.warn('[sw] chat_sync fetch failed', err);
+    return null;
+  });
+}
+
 // ── Helper: focus open tab or open a new one, then tell the app which chat to open ──
 function openOrFocusApp(notifData) {
   const { from, to, groupId } = notifData;